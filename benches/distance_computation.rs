@@ -37,4 +37,28 @@ mod elements {
     vector_dist_impl!(angular_int_vector_100_dist, angular_int::Vector, 100);
     vector_dist_impl!(angular_int_vector_200_dist, angular_int::Vector, 200);
     vector_dist_impl!(angular_int_vector_300_dist, angular_int::Vector, 300);
+
+    vector_dist_impl!(angular_f16_vector_003_dist, dense::DenseVector<dense::F16, dense::Cosine>, 3);
+    vector_dist_impl!(angular_f16_vector_050_dist, dense::DenseVector<dense::F16, dense::Cosine>, 50);
+    vector_dist_impl!(angular_f16_vector_100_dist, dense::DenseVector<dense::F16, dense::Cosine>, 100);
+    vector_dist_impl!(angular_f16_vector_200_dist, dense::DenseVector<dense::F16, dense::Cosine>, 200);
+    vector_dist_impl!(angular_f16_vector_300_dist, dense::DenseVector<dense::F16, dense::Cosine>, 300);
+    vector_dist_impl!(angular_f16_vector_768_dist, dense::DenseVector<dense::F16, dense::Cosine>, 768);
+
+    vector_dist_impl!(angular_bf16_vector_003_dist, dense::DenseVector<dense::BF16, dense::Cosine>, 3);
+    vector_dist_impl!(angular_bf16_vector_050_dist, dense::DenseVector<dense::BF16, dense::Cosine>, 50);
+    vector_dist_impl!(angular_bf16_vector_100_dist, dense::DenseVector<dense::BF16, dense::Cosine>, 100);
+    vector_dist_impl!(angular_bf16_vector_200_dist, dense::DenseVector<dense::BF16, dense::Cosine>, 200);
+    vector_dist_impl!(angular_bf16_vector_300_dist, dense::DenseVector<dense::BF16, dense::Cosine>, 300);
+    vector_dist_impl!(angular_bf16_vector_768_dist, dense::DenseVector<dense::BF16, dense::Cosine>, 768);
+
+    vector_dist_impl!(euclidean_vector_003_dist, euclidean::Vector, 3);
+    vector_dist_impl!(euclidean_vector_050_dist, euclidean::Vector, 50);
+    vector_dist_impl!(euclidean_vector_100_dist, euclidean::Vector, 100);
+    vector_dist_impl!(euclidean_vector_200_dist, euclidean::Vector, 200);
+    vector_dist_impl!(euclidean_vector_300_dist, euclidean::Vector, 300);
+
+    vector_dist_impl!(hamming_vector_0256_dist, hamming::Vector, 256);
+    vector_dist_impl!(hamming_vector_0512_dist, hamming::Vector, 512);
+    vector_dist_impl!(hamming_vector_1024_dist, hamming::Vector, 1024);
 }
@@ -20,13 +20,19 @@ py_class!(pub class Embeddings |py| {
     ///     Path to embeddings
     /// words_path: str
     ///     Path to words
+    /// weights_path: str
+    ///     Path to weights (one per embedding)
+    /// common_component_path: str
+    ///     Path to a common component to remove from the embeddings
     ///
     @classmethod
     def __init__(_cls) -> PyResult<PyObject> { Ok(py.None()) }
 
     def __new__(_cls,
                 embeddings_path: Option<String> = None,
-                words_path: Option<String> = None
+                words_path: Option<String> = None,
+                weights_path: Option<String> = None,
+                common_component_path: Option<String> = None
     ) -> PyResult<Embeddings> {
 
         let embeddings = embeddings_path
@@ -52,6 +58,22 @@ py_class!(pub class Embeddings |py| {
             },
         };
 
+        let mut embeddings = embeddings;
+        if let Some(weights_path) = weights_path {
+            let weights = std::fs::File::open(weights_path).expect("Could not open weights file");
+            unsafe {
+                embeddings.load_weights(&weights).expect("Could not load weights.");
+            }
+        }
+
+        if let Some(common_component_path) = common_component_path {
+            let common_component = std::fs::File::open(common_component_path)
+                .expect("Could not open common component file");
+            unsafe {
+                embeddings.load_common_component(&common_component).expect("Could not load common component.");
+            }
+        }
+
         Embeddings::create_instance(py, RefCell::new(embeddings), RefCell::new(words))
     }
 
@@ -105,19 +127,74 @@ py_class!(pub class Embeddings |py| {
     /// word: str
     ///     The word or label representing the vector
     ///
-    def append(&self, embedding: Vec<f32>, word: String) -> PyResult<bool> {
+    /// Optional:
+    /// weight: f32
+    ///     The weight of the embedding. Default: 1.0
+    ///
+    def append(&self, embedding: Vec<f32>, word: String, weight: f32 = 1.0) -> PyResult<bool> {
         let inserted = self.words(py).borrow_mut().push(word);
         if inserted {
-            self.embeddings(py).borrow_mut().push_embedding(&embedding);
+            self.embeddings(py).borrow_mut().push_weighted_embedding(&embedding, weight);
         }
 
         Ok(inserted)
     }
 
+    /// Sets the weights of the embeddings (one weight per embedding).
+    def set_weights(&self, weights: Vec<f32>) -> PyResult<PyObject> {
+        self.embeddings(py).borrow_mut().set_weights(&weights).expect("Could not set weights.");
+
+        Ok(py.None())
+    }
+
+    /// Computes the common component of the embeddings of the elements and removes it from
+    /// all embeddings returned by this collection.
+    ///
+    /// Optional:
+    /// num_iterations: int
+    ///     Number of power iterations. Default: 10
+    def remove_common_component(&self, num_iterations: usize = 10) -> PyResult<PyObject> {
+        let common_component = self.embeddings(py).borrow().compute_common_component(num_iterations);
+        self.embeddings(py).borrow_mut().set_common_component(common_component);
+
+        Ok(py.None())
+    }
+
     /// Saves the embeddings (vectors) and words/labels to disk.
-    def save(&self, embeddings_path: &str, words_path: &str) -> PyResult<PyObject> {
+    def save(&self,
+             embeddings_path: &str,
+             words_path: &str,
+             weights_path: Option<String> = None,
+             common_component_path: Option<String> = None
+    ) -> PyResult<PyObject> {
         self.save_embeddings(py, embeddings_path)?;
         self.save_words(py, words_path)?;
+        if let Some(weights_path) = weights_path {
+            self.save_weights(py, &weights_path)?;
+        }
+        if let Some(common_component_path) = common_component_path {
+            self.save_common_component(py, &common_component_path)?;
+        }
+
+        Ok(py.None())
+    }
+
+    /// Saves the weights of the embeddings to disk.
+    def save_weights(&self, path: &str) -> PyResult<PyObject> {
+        let mut file = std::fs::File::create(path)
+            .expect("Could not create file!");
+        self.embeddings(py).borrow().write_weights(&mut file)
+            .expect("Could not write weights!");
+
+        Ok(py.None())
+    }
+
+    /// Saves the common component removed from the embeddings to disk.
+    def save_common_component(&self, path: &str) -> PyResult<PyObject> {
+        let mut file = std::fs::File::create(path)
+            .expect("Could not create file!");
+        self.embeddings(py).borrow().write_common_component(&mut file)
+            .expect("Could not write common component!");
 
         Ok(py.None())
     }
@@ -5,12 +5,16 @@ use cpython::{FromPyObject, PyObject, PyResult, Python};
 use granne::{self, Dist, Index};
 use std::cell::RefCell;
 use std::path::Path;
+use std::sync::Arc;
 
 mod embeddings;
 mod variants;
 
 use variants::WordDict;
 
+type F16Vectors<'a> = granne::dense::DenseVectors<'a, granne::dense::F16, granne::dense::Cosine>;
+type BF16Vectors<'a> = granne::dense::DenseVectors<'a, granne::dense::BF16, granne::dense::Cosine>;
+
 const DEFAULT_MAX_SEARCH: usize = 200;
 const DEFAULT_NUM_ELEMENTS: usize = 10;
 
@@ -49,7 +53,9 @@ py_module_initializer!(granne, initgranne, PyInit_granne, |py, m| {
                 embeddings_path: String,
                 elements_path: String,
                 output_path: String,
-                show_progress: bool = true
+                show_progress: bool = true,
+                weights_path: Option<String> = None,
+                common_component_path: Option<String> = None
             )
         ),
     )?;
@@ -62,7 +68,7 @@ py_module_initializer!(granne, initgranne, PyInit_granne, |py, m| {
 /// ----------
 /// Required:
 /// element_type: str
-///     Type of element (angular or angular_int)
+///     Type of element (angular, angular_int, angular_f16, angular_bf16 or euclidean)
 /// a: element
 ///     First element
 /// b: element
@@ -82,6 +88,26 @@ pub fn py_compute_distance(py: Python, element_type: &str, a: &PyObject, b: &PyO
 
             Ok(a.dist(&b).into_inner())
         }
+        "angular_f16" => {
+            let a = granne::dense::DenseVector::<granne::dense::F16, granne::dense::Cosine>::from(Vec::extract(py, a)?);
+            let b = granne::dense::DenseVector::<granne::dense::F16, granne::dense::Cosine>::from(Vec::extract(py, b)?);
+
+            Ok(a.dist(&b).into_inner())
+        }
+        "angular_bf16" => {
+            let a =
+                granne::dense::DenseVector::<granne::dense::BF16, granne::dense::Cosine>::from(Vec::extract(py, a)?);
+            let b =
+                granne::dense::DenseVector::<granne::dense::BF16, granne::dense::Cosine>::from(Vec::extract(py, b)?);
+
+            Ok(a.dist(&b).into_inner())
+        }
+        "euclidean" => {
+            let a = granne::euclidean::Vector::from(Vec::extract(py, a)?);
+            let b = granne::euclidean::Vector::from(Vec::extract(py, b)?);
+
+            Ok(a.dist(&b).into_inner())
+        }
         _ => panic!("Unsupported element type"),
     }
 }
@@ -129,16 +155,24 @@ pub fn py_parse_elements_and_save_to_disk(
 ///     Path where to write the vectors.
 /// show_progress: bool
 ///     Default: True
+/// weights_path: str
+///     Path to weights for the embeddings (optional)
+/// common_component_path: str
+///     Remove the common component from the vectors and write it to this path (optional)
 pub fn py_compute_embeddings_and_save_to_disk(
     py: Python,
     elements_path: String,
     embeddings_path: String,
     output_path: String,
     show_progress: bool,
+    weights_path: Option<String>,
+    common_component_path: Option<String>,
 ) -> PyResult<PyObject> {
     granne::embeddings::parsing::compute_embeddings_and_save_to_disk(
         &Path::new(&elements_path),
         &Path::new(&embeddings_path),
+        weights_path.as_ref().map(Path::new),
+        common_component_path.as_ref().map(Path::new),
         &Path::new(&output_path),
         show_progress,
     );
@@ -148,6 +182,7 @@ pub fn py_compute_embeddings_and_save_to_disk(
 
 py_class!(class Granne |py| {
     data index: RefCell<Box<dyn PyGranne + Send + Sync>>;
+    data thread_pool: RefCell<Option<(usize, Arc<rayon::ThreadPool>)>>;
 
     // Required since rust-cpython cannot add docs for "special" functions
     /// Note: This is the documentation for the `__new__` method:
@@ -159,7 +194,7 @@ py_class!(class Granne |py| {
     /// index_path: str
     ///     Path to existing index
     /// element_type : str
-    ///     Type of element (angular, angular_int or embeddings)
+    ///     Type of element (angular, angular_int, angular_f16, angular_bf16, euclidean or embeddings)
     /// elements_path: str
     ///     Path to elements
     ///
@@ -169,6 +204,12 @@ py_class!(class Granne |py| {
     /// words_path: str
     ///     Path to words
     ///
+    /// Optional (only used if `element_type == "embeddings"`):
+    /// weights_path: str
+    ///     Path to weights (one per embedding)
+    /// common_component_path: str
+    ///     Path to a common component to remove from the embeddings
+    ///
     @classmethod
     def __init__(_cls) -> PyResult<PyObject> { Ok(py.None()) }
 
@@ -177,7 +218,9 @@ py_class!(class Granne |py| {
                 element_type: String,
                 elements_path: &str,
                 embeddings_path: Option<String> = None,
-                words_path: Option<String> = None
+                words_path: Option<String> = None,
+                weights_path: Option<String> = None,
+                common_component_path: Option<String> = None
     ) -> PyResult<Granne> {
 
         let index = std::fs::File::open(index_path).expect("Could not open index file");
@@ -196,6 +239,24 @@ py_class!(class Granne |py| {
                     granne::angular_int::Vectors::from_file(&elements).expect("Could not load elements."),
                 ).expect("Could not load index.") },
             ),
+            "angular_f16" => Box::new(
+                unsafe { granne::Granne::from_file(
+                    &index,
+                    F16Vectors::from_file(&elements).expect("Could not load elements."),
+                ).expect("Could not load index.") },
+            ),
+            "angular_bf16" => Box::new(
+                unsafe { granne::Granne::from_file(
+                    &index,
+                    BF16Vectors::from_file(&elements).expect("Could not load elements."),
+                ).expect("Could not load index.") },
+            ),
+            "euclidean" => Box::new(
+                unsafe { granne::Granne::from_file(
+                    &index,
+                    granne::euclidean::Vectors::from_file(&elements).expect("Could not load elements."),
+                ).expect("Could not load index.") },
+            ),
             "embeddings" => Box::new(variants::index::WordEmbeddingsGranne::new(
                 &index,
                 &elements,
@@ -203,11 +264,13 @@ py_class!(class Granne |py| {
                     embeddings_path.expect("embeddings_path required for this element type!")
                 ).expect("Could not open embeddings file."),
                 &words_path.expect("words_path required for this element type!"),
+                weights_path.as_ref().map(String::as_str),
+                common_component_path.as_ref().map(String::as_str),
             )),
             _ => panic!("Invalid element type"),
         };
 
-        Granne::create_instance(py, RefCell::new(index))
+        Granne::create_instance(py, RefCell::new(index), RefCell::new(None))
     }
 
     /// Searches for nearest neighbors to an element. The type of element depends on the element type of this index.
@@ -232,6 +295,35 @@ py_class!(class Granne |py| {
         self.index(py).borrow().search(py, element, max_search, num_elements)
     }
 
+    /// Searches for nearest neighbors to each of the elements in parallel. The GIL is released
+    /// during the search.
+    ///
+    /// Parameters
+    /// ----------
+    /// Required:
+    /// elements: list of arrays or strs
+    ///     Search for nearest neighbors to these elements.
+    ///
+    /// Optional:
+    /// max_search: int
+    ///     `max_search` parameter to use during the search.
+    /// num_elements: int
+    ///     Maximum number of neighbors to return for each element.
+    /// num_threads: int
+    ///     Number of threads to use. Defaults to the number of CPUs. The thread pool is kept and
+    ///     reused by subsequent calls with the same number of threads.
+    ///
+    def search_batch(&self,
+                     elements: &PyObject,
+                     max_search: usize = DEFAULT_MAX_SEARCH,
+                     num_elements: usize = DEFAULT_NUM_ELEMENTS,
+                     num_threads: Option<usize> = None) -> PyResult<Vec<Vec<(usize, f32)>>>
+    {
+        let thread_pool = num_threads.map(|num_threads| get_thread_pool(self.thread_pool(py), num_threads));
+
+        self.index(py).borrow().search_batch(py, elements, max_search, num_elements, thread_pool.as_deref())
+    }
+
     /// Returns the element at index idx.
     ///
     /// Parameters
@@ -354,7 +446,7 @@ py_class!(class GranneBuilder |py| {
     /// ----------
     /// Required:
     /// element_type : str
-    ///     Type of element (angular, angular_int or embeddings)
+    ///     Type of element (angular, angular_int, angular_f16, angular_bf16, euclidean or embeddings)
     ///
     /// Optional (use keywords to specify optional parameters):
     /// elements_path: str
@@ -363,6 +455,10 @@ py_class!(class GranneBuilder |py| {
     ///     Path to embeddings
     /// words_path: str
     ///     Path to words (Required if `embeddings_path` was provided.)
+    /// weights_path: str
+    ///     Path to weights for the embeddings
+    /// common_component_path: str
+    ///     Path to a common component to remove from the embeddings
     /// index_path: str
     ///     Path to existing index
     /// layer_multiplier: f32
@@ -392,7 +488,9 @@ py_class!(class GranneBuilder |py| {
                 num_neighbors: Option<usize> = None,
                 max_search: Option<usize> = None,
                 reinsert_elements: bool = true,
-                show_progress: bool = true) -> PyResult<GranneBuilder> {
+                show_progress: bool = true,
+                weights_path: Option<String> = None,
+                common_component_path: Option<String> = None) -> PyResult<GranneBuilder> {
 
         let mut config = granne::BuildConfig::default()
             .show_progress(show_progress)
@@ -440,6 +538,45 @@ py_class!(class GranneBuilder |py| {
                 index,
                 unsafe { granne::angular_int::Vectors::from_file(elements).unwrap() },
             ).expect("Could not read index!")),
+            (None, None, "angular_f16") => Box::new(granne::GranneBuilder::new(
+                config,
+                F16Vectors::new(),
+            )),
+            (None, Some(elements), "angular_f16") => Box::new(granne::GranneBuilder::new(
+                config,
+                unsafe { F16Vectors::from_file(elements).unwrap() },
+            )),
+            (Some(index), Some(elements), "angular_f16") => Box::new(granne::GranneBuilder::from_file(
+                config,
+                index,
+                unsafe { F16Vectors::from_file(elements).unwrap() },
+            ).expect("Could not read index!")),
+            (None, None, "angular_bf16") => Box::new(granne::GranneBuilder::new(
+                config,
+                BF16Vectors::new(),
+            )),
+            (None, Some(elements), "angular_bf16") => Box::new(granne::GranneBuilder::new(
+                config,
+                unsafe { BF16Vectors::from_file(elements).unwrap() },
+            )),
+            (Some(index), Some(elements), "angular_bf16") => Box::new(granne::GranneBuilder::from_file(
+                config,
+                index,
+                unsafe { BF16Vectors::from_file(elements).unwrap() },
+            ).expect("Could not read index!")),
+            (None, None, "euclidean") => Box::new(granne::GranneBuilder::new(
+                config,
+                granne::euclidean::Vectors::new(),
+            )),
+            (None, Some(elements), "euclidean") => Box::new(granne::GranneBuilder::new(
+                config,
+                unsafe { granne::euclidean::Vectors::from_file(elements).unwrap() },
+            )),
+            (Some(index), Some(elements), "euclidean") => Box::new(granne::GranneBuilder::from_file(
+                config,
+                index,
+                unsafe { granne::euclidean::Vectors::from_file(elements).unwrap() },
+            ).expect("Could not read index!")),
             (index, elements, "embeddings") => {
                 Box::new(variants::builder::WordEmbeddingsBuilder::new(
                     config,
@@ -449,6 +586,8 @@ py_class!(class GranneBuilder |py| {
                     ).expect("Could not open embeddings file"),
                     &words_path.expect("words_path required for this element type!"),
                     index,
+                    weights_path.as_ref().map(String::as_str),
+                    common_component_path.as_ref().map(String::as_str),
                 ))
             }
             _ => panic!(),
@@ -613,12 +752,53 @@ trait PyGranne: AsIndex + Reorder + SaveIndex {
         max_search: usize,
         num_elements: usize,
     ) -> PyResult<Vec<(usize, f32)>>;
+    fn search_batch(
+        self: &Self,
+        py: Python,
+        elements: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+        thread_pool: Option<&rayon::ThreadPool>,
+    ) -> PyResult<Vec<Vec<(usize, f32)>>>;
     fn get_element(self: &Self, py: Python, idx: usize) -> PyObject;
     fn get_internal_element(self: &Self, py: Python, idx: usize) -> PyObject {
         self.get_element(py, idx)
     }
 }
 
+/// Runs `f` in `thread_pool` (or in the global thread pool).
+fn install_in_thread_pool<T: Send>(thread_pool: Option<&rayon::ThreadPool>, f: impl FnOnce() -> T + Send) -> T {
+    if let Some(thread_pool) = thread_pool {
+        thread_pool.install(f)
+    } else {
+        f()
+    }
+}
+
+/// Returns a thread pool with `num_threads` threads. The pool is cached in `cached` and only
+/// rebuilt when `num_threads` changes.
+fn get_thread_pool(
+    cached: &RefCell<Option<(usize, Arc<rayon::ThreadPool>)>>,
+    num_threads: usize,
+) -> Arc<rayon::ThreadPool> {
+    let mut cached = cached.borrow_mut();
+
+    match *cached {
+        Some((cached_num_threads, ref thread_pool)) if cached_num_threads == num_threads => thread_pool.clone(),
+        _ => {
+            let thread_pool = Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_threads)
+                    .build()
+                    .expect("Could not create thread pool"),
+            );
+            *cached = Some((num_threads, thread_pool.clone()));
+
+            thread_pool
+        }
+    }
+}
+
 trait SaveIndex {
     fn save_index(self: &Self, path: &str) -> std::io::Result<()>;
 
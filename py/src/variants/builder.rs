@@ -1,4 +1,4 @@
-use super::WordDict;
+use super::{load_sum_embeddings, HalfPrecision, WordDict};
 use crate::{AsBuilder, AsIndex, PyGranneBuilder, SaveIndex};
 use cpython::{FromPyObject, PyObject, PyResult, Python};
 use granne;
@@ -21,6 +21,26 @@ impl<'a> PyGranneBuilder for granne::GranneBuilder<granne::angular_int::Vectors<
     }
 }
 
+impl<'a> PyGranneBuilder for granne::GranneBuilder<granne::euclidean::Vectors<'a>> {
+    fn push(self: &mut Self, py: Python, element: &PyObject) -> PyResult<PyObject> {
+        let element = granne::euclidean::Vector::from(Vec::extract(py, element)?);
+        self.push(element);
+
+        Ok(py.None())
+    }
+}
+
+impl<'a, T: HalfPrecision> PyGranneBuilder
+    for granne::GranneBuilder<granne::dense::DenseVectors<'a, T, granne::dense::Cosine>>
+{
+    fn push(self: &mut Self, py: Python, element: &PyObject) -> PyResult<PyObject> {
+        let element = granne::dense::DenseVector::<T, granne::dense::Cosine>::from(Vec::extract(py, element)?);
+        self.push(element);
+
+        Ok(py.None())
+    }
+}
+
 pub struct WordEmbeddingsBuilder {
     builder: granne::GranneBuilder<granne::embeddings::SumEmbeddings<'static>>,
     words: WordDict,
@@ -33,18 +53,16 @@ impl WordEmbeddingsBuilder {
         embeddings: &std::fs::File,
         words: &str,
         index: Option<&std::fs::File>,
+        weights_path: Option<&str>,
+        common_component_path: Option<&str>,
     ) -> Self {
         let words = WordDict::new(words);
+        let elements = unsafe { load_sum_embeddings(embeddings, elements, weights_path, common_component_path) };
 
         let builder = if let Some(index) = index {
-            granne::GranneBuilder::from_file(config, index, unsafe {
-                granne::embeddings::SumEmbeddings::from_files(embeddings, elements).expect("Could not load elements.")
-            })
-            .expect("Could not read index.")
+            granne::GranneBuilder::from_file(config, index, elements).expect("Could not read index.")
         } else {
-            granne::GranneBuilder::new(config, unsafe {
-                granne::embeddings::SumEmbeddings::from_files(embeddings, elements).expect("Could not load elements.")
-            })
+            granne::GranneBuilder::new(config, elements)
         };
 
         Self { builder, words }
@@ -1,7 +1,7 @@
 use cpython::{FromPyObject, PyObject, PyResult, Python, PythonObject, ToPyObject};
 
-use super::WordDict;
-use crate::{AsIndex, PyGranne};
+use super::{load_sum_embeddings, HalfPrecision, WordDict};
+use crate::{install_in_thread_pool, AsIndex, PyGranne};
 use granne::{self, Index};
 
 impl<'a> PyGranne for granne::Granne<'a, granne::angular::Vectors<'a>> {
@@ -16,6 +16,24 @@ impl<'a> PyGranne for granne::Granne<'a, granne::angular::Vectors<'a>> {
         Ok(self.search(&element, max_search, num_elements))
     }
 
+    fn search_batch(
+        self: &Self,
+        py: Python,
+        elements: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+        thread_pool: Option<&rayon::ThreadPool>,
+    ) -> PyResult<Vec<Vec<(usize, f32)>>> {
+        let elements: Vec<granne::angular::Vector> = Vec::<Vec<f32>>::extract(py, elements)?
+            .into_iter()
+            .map(granne::angular::Vector::from)
+            .collect();
+
+        Ok(py.allow_threads(|| {
+            install_in_thread_pool(thread_pool, || self.search_batch(&elements, max_search, num_elements))
+        }))
+    }
+
     fn get_element(self: &Self, py: Python, idx: usize) -> PyObject {
         self.get_element(idx).into_vec().into_py_object(py).into_object()
     }
@@ -29,33 +47,144 @@ impl<'a> PyGranne for granne::Granne<'a, granne::angular_int::Vectors<'a>> {
         max_search: usize,
         num_elements: usize,
     ) -> PyResult<Vec<(usize, f32)>> {
-        let element = granne::angular_int::Vector::from(Vec::extract(py, element)?);
+        // the query is not quantized
+        let element = granne::angular::Vector::from(Vec::extract(py, element)?);
         Ok(self.search(&element, max_search, num_elements))
     }
 
+    fn search_batch(
+        self: &Self,
+        py: Python,
+        elements: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+        thread_pool: Option<&rayon::ThreadPool>,
+    ) -> PyResult<Vec<Vec<(usize, f32)>>> {
+        // the queries are not quantized
+        let elements: Vec<granne::angular::Vector> = Vec::<Vec<f32>>::extract(py, elements)?
+            .into_iter()
+            .map(granne::angular::Vector::from)
+            .collect();
+
+        Ok(py.allow_threads(|| {
+            install_in_thread_pool(thread_pool, || self.search_batch(&elements, max_search, num_elements))
+        }))
+    }
+
     fn get_element(self: &Self, py: Python, idx: usize) -> PyObject {
         self.get_element(idx).into_vec().into_py_object(py).into_object()
     }
 }
 
+impl<'a> PyGranne for granne::Granne<'a, granne::euclidean::Vectors<'a>> {
+    fn search(
+        self: &Self,
+        py: Python,
+        element: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+    ) -> PyResult<Vec<(usize, f32)>> {
+        let element = granne::euclidean::Vector::from(Vec::extract(py, element)?);
+        Ok(self.search(&element, max_search, num_elements))
+    }
+
+    fn search_batch(
+        self: &Self,
+        py: Python,
+        elements: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+        thread_pool: Option<&rayon::ThreadPool>,
+    ) -> PyResult<Vec<Vec<(usize, f32)>>> {
+        let elements: Vec<granne::euclidean::Vector> = Vec::<Vec<f32>>::extract(py, elements)?
+            .into_iter()
+            .map(granne::euclidean::Vector::from)
+            .collect();
+
+        Ok(py.allow_threads(|| {
+            install_in_thread_pool(thread_pool, || self.search_batch(&elements, max_search, num_elements))
+        }))
+    }
+
+    fn get_element(self: &Self, py: Python, idx: usize) -> PyObject {
+        self.get_element(idx).into_vec().into_py_object(py).into_object()
+    }
+}
+
+impl<'a, T: HalfPrecision> PyGranne for granne::Granne<'a, granne::dense::DenseVectors<'a, T, granne::dense::Cosine>> {
+    fn search(
+        self: &Self,
+        py: Python,
+        element: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+    ) -> PyResult<Vec<(usize, f32)>> {
+        let element = granne::dense::DenseVector::<T, granne::dense::Cosine>::from(Vec::extract(py, element)?);
+        Ok(self.search(&element, max_search, num_elements))
+    }
+
+    fn search_batch(
+        self: &Self,
+        py: Python,
+        elements: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+        thread_pool: Option<&rayon::ThreadPool>,
+    ) -> PyResult<Vec<Vec<(usize, f32)>>> {
+        let elements: Vec<granne::dense::DenseVector<T, granne::dense::Cosine>> =
+            Vec::<Vec<f32>>::extract(py, elements)?
+                .into_iter()
+                .map(granne::dense::DenseVector::from)
+                .collect();
+
+        Ok(py.allow_threads(|| {
+            install_in_thread_pool(thread_pool, || self.search_batch(&elements, max_search, num_elements))
+        }))
+    }
+
+    fn get_element(self: &Self, py: Python, idx: usize) -> PyObject {
+        let element: Vec<f32> = self.get_element(idx).as_slice().iter().map(|&x| x.to_f32()).collect();
+
+        element.into_py_object(py).into_object()
+    }
+}
+
 pub struct WordEmbeddingsGranne {
     index: granne::Granne<'static, granne::embeddings::SumEmbeddings<'static>>,
     words: WordDict,
 }
 
 impl WordEmbeddingsGranne {
-    pub fn new(index: &std::fs::File, elements: &std::fs::File, embeddings: &std::fs::File, words: &str) -> Self {
+    pub fn new(
+        index: &std::fs::File,
+        elements: &std::fs::File,
+        embeddings: &std::fs::File,
+        words: &str,
+        weights_path: Option<&str>,
+        common_component_path: Option<&str>,
+    ) -> Self {
         let words = WordDict::new(words);
 
-        let elements = unsafe {
-            granne::embeddings::SumEmbeddings::from_files(embeddings, Some(elements))
-                .expect("Could not open embeddings/elements")
-        };
+        let elements = unsafe { load_sum_embeddings(embeddings, Some(elements), weights_path, common_component_path) };
 
         let index = unsafe { granne::Granne::from_file(index, elements).expect("Could not load index.") };
 
         Self { index, words }
     }
+
+    /// Converts `element`, either a vector or a string of words, into a query vector.
+    fn get_query(self: &Self, py: Python, element: &PyObject) -> PyResult<granne::angular::Vector<'static>> {
+        let data: Vec<f32> = if let Ok(data) = Vec::<f32>::extract(py, element) {
+            data
+        } else {
+            let words = String::extract(py, element)?;
+            let ids = self.words.get_word_ids(&words);
+
+            self.index.get_elements().create_embedding(&ids)
+        };
+
+        Ok(granne::angular::Vector::from(data))
+    }
 }
 
 impl AsIndex for WordEmbeddingsGranne {
@@ -95,18 +224,28 @@ impl PyGranne for WordEmbeddingsGranne {
         max_search: usize,
         num_elements: usize,
     ) -> PyResult<Vec<(usize, f32)>> {
-        let data: Vec<f32> = if let Ok(data) = Vec::<f32>::extract(py, element) {
-            data
-        } else {
-            let words = String::extract(py, element)?;
-            let ids = self.words.get_word_ids(&words);
+        let element = self.get_query(py, element)?;
 
-            self.index.get_elements().create_embedding(&ids)
-        };
+        Ok(self.index.search(&element, max_search, num_elements))
+    }
 
-        let element = granne::angular::Vector::from(data);
+    fn search_batch(
+        self: &Self,
+        py: Python,
+        elements: &PyObject,
+        max_search: usize,
+        num_elements: usize,
+        thread_pool: Option<&rayon::ThreadPool>,
+    ) -> PyResult<Vec<Vec<(usize, f32)>>> {
+        let elements = Vec::<PyObject>::extract(py, elements)?
+            .iter()
+            .map(|element| self.get_query(py, element))
+            .collect::<PyResult<Vec<_>>>()?;
 
-        Ok(self.index.search(&element, max_search, num_elements))
+        let index = &self.index;
+        Ok(py.allow_threads(|| {
+            install_in_thread_pool(thread_pool, || index.search_batch(&elements, max_search, num_elements))
+        }))
     }
 
     fn get_element(self: &Self, py: Python, idx: usize) -> PyObject {
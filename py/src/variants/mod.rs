@@ -5,6 +5,39 @@ use std::io::Write;
 pub mod builder;
 pub mod index;
 
+/// The half-precision scalar types, which are exposed as the `angular_f16` and `angular_bf16`
+/// element types.
+pub trait HalfPrecision: granne::dense::Scalar {}
+
+impl HalfPrecision for granne::dense::F16 {}
+impl HalfPrecision for granne::dense::BF16 {}
+
+/// Loads memory-mapped `SumEmbeddings` together with the optional weights and common component.
+unsafe fn load_sum_embeddings(
+    embeddings: &std::fs::File,
+    elements: Option<&std::fs::File>,
+    weights_path: Option<&str>,
+    common_component_path: Option<&str>,
+) -> granne::embeddings::SumEmbeddings<'static> {
+    let mut embeddings =
+        granne::embeddings::SumEmbeddings::from_files(embeddings, elements).expect("Could not load elements.");
+
+    if let Some(weights_path) = weights_path {
+        let weights = std::fs::File::open(weights_path).expect("Could not open weights file");
+        embeddings.load_weights(&weights).expect("Could not load weights.");
+    }
+
+    if let Some(common_component_path) = common_component_path {
+        let common_component =
+            std::fs::File::open(common_component_path).expect("Could not open common component file");
+        embeddings
+            .load_common_component(&common_component)
+            .expect("Could not load common component.");
+    }
+
+    embeddings
+}
+
 #[derive(Default)]
 pub struct WordDict {
     word_to_id: HashMap<String, usize>,
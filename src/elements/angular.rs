@@ -1,7 +1,13 @@
 /*!
 This module contains element types for angular vectors using `f32` as scalars.
 
-The vectors are normalized.
+The vectors are normalized. `angular::Vectors` is a type alias for
+[`DenseVectors`](../dense/struct.DenseVectors.html) using the [`Cosine`](../dense/struct.Cosine.html)
+metric.
+
+Since version 0.6.0, `angular::Vector` is a type alias as well and can no longer be constructed or
+matched as a tuple struct: use `angular::Vector::new(data)` instead of `angular::Vector(data)` and
+`vector.as_slice()` instead of patterns like `&angular::Vector(ref x)`.
 
 # Example
 This example shows how to read [GloVe](https://github.com/stanfordnlp/GloVe) vectors into `angular::Vectors`.
@@ -40,44 +46,22 @@ for line in file.lines() {
 ```
 */
 
-use super::{Dist, ElementContainer, ExtendableElementContainer};
-use crate::{io, math, slice_vector::FixedWidthSliceVector};
+use super::dense::{Cosine, DenseVector, DenseVectors};
 
 use ordered_float::NotNan;
 use std::cmp;
 
-use std::borrow::Cow;
-use std::io::{Result, Write};
-use std::iter::FromIterator;
-
-dense_vector!(f32);
-
-impl From<Vec<f32>> for Vector<'static> {
-    fn from(mut v: Vec<f32>) -> Self {
-        math::normalize_f32(&mut v);
-
-        Self(Cow::from(v))
-    }
-}
+/// A vector element.
+pub type Vector<'a> = DenseVector<'a, f32, Cosine>;
 
-impl<'a, 'b> Dist<Vector<'b>> for Vector<'a> {
-    fn dist(self: &Self, other: &Vector<'b>) -> NotNan<f32> {
-        let &Vector(ref x) = self;
-        let &Vector(ref y) = other;
-
-        let r = math::dot_product_f32(x, y);
-
-        let d = NotNan::new(1.0f32 - r).unwrap();
-
-        cmp::max(0.0f32.into(), d)
-    }
-}
+/// A collection of `Vector`s.
+pub type Vectors<'a> = DenseVectors<'a, f32, Cosine>;
 
 #[doc(hidden)]
 #[allow(unused)]
 pub fn angular_reference_dist(first: &Vector, second: &Vector) -> NotNan<f32> {
-    let &Vector(ref x) = first;
-    let &Vector(ref y) = second;
+    let x = first.as_slice();
+    let y = second.as_slice();
 
     let r: f32 = x.iter().zip(y.iter()).map(|(&xi, &yi)| xi as f32 * yi as f32).sum();
 
@@ -92,7 +76,7 @@ pub fn angular_reference_dist(first: &Vector, second: &Vector) -> NotNan<f32> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helper;
+    use crate::{test_helper, Dist};
 
     const DIST_EPSILON: f32 = 10.0 * ::std::f32::EPSILON;
 
@@ -1,60 +1,119 @@
 //! This module contains element types for quantized angular vectors using `i8` as scalars.
 //!
-//! An [`angular::Vector`](../angular/struct.Vector.html) is converted into an
-//! [`angular_int::Vector`](struct.Vector.html) by mapping each dimension (originally stored as
+//! An [`angular::Vector`](../angular/type.Vector.html) is converted into an
+//! [`angular_int::Vector`](type.Vector.html) by mapping each dimension (originally stored as
 //! `f32`) into the range [-127, 127], which is then stored as `i8`, saving 3 bytes per dimension.
+//! `angular_int::Vectors` is a type alias for [`DenseVectors`](../dense/struct.DenseVectors.html)
+//! using `i8` as scalars and the [`Cosine`](../dense/struct.Cosine.html) metric.
+//!
+//! `angular_int::Vectors` can also be searched using (full precision)
+//! [`angular::Vector`](../angular/type.Vector.html)s as queries, which avoids quantizing the
+//! query (see [`QueryDist`](../trait.QueryDist.html)).
+//!
+//! The norms of the vectors in [`angular_int::Vectors`](type.Vectors.html) are computed once
+//! when the vectors are pushed and kept in memory, so that each distance computation only requires
+//! a dot product. The norms are not part of the serialized format, so vectors that are loaded
+//! compute the norms as part of each distance computation.
+//!
+//! Since version 0.6.0, `angular_int::Vector` and `angular_int::Vectors` are type aliases and
+//! `angular_int::Vector` can no longer be constructed or matched as a tuple struct: use
+//! `angular_int::Vector::new(data)` and `vector.as_slice()` instead.
 
-use super::{Dist, ElementContainer, ExtendableElementContainer};
-use crate::{io, math, slice_vector::FixedWidthSliceVector};
+use super::dense::{Cosine, DenseVector, DenseVectors};
+use super::{angular, Dist, QueryDist};
+use crate::math;
 
 use ordered_float::NotNan;
 use std::cmp;
 
-use std::borrow::Cow;
-use std::io::{Result, Write};
-use std::iter::FromIterator;
+/// A vector element.
+pub type Vector<'a> = DenseVector<'a, i8, Cosine>;
 
-dense_vector!(i8);
+/// A collection of `Vector`s.
+pub type Vectors<'a> = DenseVectors<'a, i8, Cosine>;
 
-impl From<Vec<f32>> for Vector<'static> {
-    fn from(v: Vec<f32>) -> Self {
-        Self::quantize(&v)
-    }
+/// Computes the cosine distance given the dot product `r` and the norm `norm` of the quantized
+/// vector (the other vector is normalized).
+#[inline(always)]
+fn cosine_dist(r: f32, norm: f32) -> NotNan<f32> {
+    let r = NotNan::new(r / norm).unwrap_or_else(|_| NotNan::new(0.0).unwrap());
+    let d = NotNan::new(1.0f32).unwrap() - r;
+
+    cmp::max(NotNan::new(0.0f32).unwrap(), d)
 }
 
-const MAX_QVALUE: f32 = 127.0;
+impl<'a, 'b> Dist<angular::Vector<'b>> for Vector<'a> {
+    fn dist(self: &Self, other: &angular::Vector<'b>) -> NotNan<f32> {
+        let x = self.as_slice();
+        let norm = (math::dot_product_i8(x, x) as f32).sqrt();
 
-impl Vector<'static> {
-    fn quantize(s: &[f32]) -> Self {
-        let max_value = s
-            .iter()
-            .map(|s| NotNan::new(s.abs()).unwrap())
-            .max()
-            .unwrap_or_else(|| NotNan::new(MAX_QVALUE).unwrap());
+        // other is normalized
+        cosine_dist(math::dot_product_i8_f32(x, other.as_slice()), norm)
+    }
+}
 
-        let mut v = Vec::with_capacity(s.len());
+impl<'a, 'b> QueryDist<angular::Vector<'b>> for Vectors<'a> {
+    fn dist_to_query(self: &Self, idx: usize, query: &angular::Vector<'b>) -> NotNan<f32> {
+        // query is normalized
+        let r = math::dot_product_i8_f32(self.vectors.get(idx), query.as_slice());
 
-        for x in s {
-            let vi = x * MAX_QVALUE / *max_value;
-            debug_assert!(-MAX_QVALUE - 0.0001 <= vi && vi <= MAX_QVALUE + 0.0001);
-            v.push(vi as i8);
-        }
+        cosine_dist(r, self.norm(idx))
+    }
+}
 
-        Self(Cow::from(v))
+impl<'a, 'b, 'c> QueryDist<angular::Vector<'b>> for &'c Vectors<'a> {
+    fn dist_to_query(self: &Self, idx: usize, query: &angular::Vector<'b>) -> NotNan<f32> {
+        Vectors::dist_to_query(self, idx, query)
     }
 }
 
-impl<'a, 'b> Dist<Vector<'b>> for Vector<'a> {
-    fn dist(self: &Self, other: &Vector<'b>) -> NotNan<f32> {
-        let &Vector(ref x) = self;
-        let &Vector(ref y) = other;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io, test_helper, ElementContainer, Permutable};
+    use rand::seq::SliceRandom;
+
+    const DIST_EPSILON: f32 = 10.0 * ::std::f32::EPSILON;
 
-        let (r, dx, dy) = math::dot_product_and_squared_norms_i8(x, y);
-        let (r, dx, dy) = (r as f32, dx as f32, dy as f32);
+    #[test]
+    fn dist_with_precomputed_norms() {
+        let vectors: Vec<Vector> = (0..100).map(|_| test_helper::random_vector(50)).collect();
+        let elements: Vectors = vectors.iter().cloned().collect();
+        let all: Vec<usize> = (0..vectors.len()).collect();
 
-        let r = NotNan::new(r / (dx.sqrt() * dy.sqrt())).unwrap_or_else(|_| NotNan::new(0.0).unwrap());
-        let d = NotNan::new(1.0f32).unwrap() - r;
+        for i in 0..vectors.len() {
+            for j in 0..vectors.len() {
+                let expected = vectors[i].dist(&vectors[j]).into_inner();
 
-        cmp::max(NotNan::new(0.0f32).unwrap(), d)
+                assert!((expected - elements.dist(i, j).into_inner()).abs() < DIST_EPSILON);
+                assert!((expected - elements.dist_to_element(i, &vectors[j]).into_inner()).abs() < DIST_EPSILON);
+            }
+
+            for (j, d) in elements.dists_to_element(&vectors[i], &all).into_iter().enumerate() {
+                assert!((vectors[j].dist(&vectors[i]).into_inner() - d.into_inner()).abs() < DIST_EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn norms_after_load_and_permute() {
+        let elements: Vectors = test_helper::random_vectors(50, 100);
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&elements, &mut buffer).unwrap();
+        let mut loaded = Vectors::from_bytes(&buffer);
+
+        for i in 0..elements.len() {
+            assert_eq!(elements.norm(i), loaded.norm(i));
+        }
+
+        let mut permutation: Vec<usize> = (0..elements.len()).collect();
+        permutation.shuffle(&mut rand::thread_rng());
+        loaded.permute(&permutation);
+
+        for (i, &j) in permutation.iter().enumerate() {
+            assert_eq!(elements.norm(j), loaded.norm(i));
+            assert_eq!(elements.get_element(j).as_slice(), loaded.get_element(i).as_slice());
+        }
     }
 }
@@ -0,0 +1,769 @@
+/*!
+This module contains generic element types for dense vectors, where the type of the scalars
+([`Scalar`](trait.Scalar.html)) and the distance function ([`Metric`](trait.Metric.html)) can be
+combined freely.
+
+The element types in [`angular`](../angular/index.html) and
+[`euclidean`](../euclidean/index.html) are type aliases for `f32` vectors using the metrics
+[`Cosine`](struct.Cosine.html) and [`SquaredEuclidean`](struct.SquaredEuclidean.html),
+respectively.
+
+Half-precision vectors are available using [`F16`](struct.F16.html) or [`BF16`](struct.BF16.html)
+as scalars, saving 2 bytes per dimension compared to `f32`. The distances are computed in `f32`.
+With the `Cosine` metric, the norms of the (rounded) vectors are kept in memory by
+`DenseVectors`, so that each distance computation only requires a dot product.
+
+# Example
+
+```
+use granne::{dense, Dist};
+
+type Vector<'a> = dense::DenseVector<'a, f32, dense::Manhattan>;
+
+let x: Vector = vec![1.0f32, 2.0, 3.0].into();
+let y: Vector = vec![2.0f32, 2.0, 5.0].into();
+
+assert_eq!(3.0, x.dist(&y).into_inner());
+
+// the same vectors stored using i8 as scalars, compared using the squared euclidean distance
+type IntVector<'a> = dense::DenseVector<'a, i8, dense::SquaredEuclidean>;
+
+let x: IntVector = vec![1.0f32, 2.0, 3.0].into();
+let y: IntVector = vec![2.0f32, 2.0, 5.0].into();
+
+assert_eq!(5.0, x.dist(&y).into_inner());
+```
+*/
+
+use super::{Dist, ElementContainer, ExtendableElementContainer, Permutable};
+use crate::{io, math, slice_vector::FixedWidthSliceVector};
+
+use ordered_float::NotNan;
+use rayon::prelude::*;
+use std::cmp;
+
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+/// A trait for scalar types that can be used in [`DenseVector`](struct.DenseVector.html)s.
+///
+/// The provided default implementations compute the distances using `f32` arithmetic.
+pub trait Scalar: Copy + Send + Sync + 'static {
+    /// `true` if the vectors converted by `normalize` are only approximately normalized (e.g.
+    /// due to quantization). [`DenseVectors`](struct.DenseVectors.html) using the `Cosine`
+    /// metric keeps the norms of such vectors in memory.
+    const APPROXIMATELY_NORMALIZED: bool = false;
+
+    /// Converts `x` into `Self`. Values out of range are saturated.
+    fn from_f32(x: f32) -> Self;
+
+    /// Converts `self` into `f32`.
+    fn to_f32(self: Self) -> f32;
+
+    /// Converts `vector` into a vector of `Self` with the same direction, e.g., by normalizing
+    /// it. Used by the [`Cosine`](struct.Cosine.html) metric.
+    fn normalize(vector: Vec<f32>) -> Vec<Self>;
+
+    /// Returns the dot product of `x` and `y`.
+    fn dot_product(x: &[Self], y: &[Self]) -> f32 {
+        x.iter().zip(y).map(|(xi, yi)| xi.to_f32() * yi.to_f32()).sum()
+    }
+
+    /// Returns the squared euclidean distance between `x` and `y`.
+    fn squared_euclidean_distance(x: &[Self], y: &[Self]) -> f32 {
+        x.iter()
+            .zip(y)
+            .map(|(xi, yi)| {
+                let diff = xi.to_f32() - yi.to_f32();
+                diff * diff
+            })
+            .sum()
+    }
+
+    /// Returns the manhattan distance between `x` and `y`.
+    fn manhattan_distance(x: &[Self], y: &[Self]) -> f32 {
+        x.iter().zip(y).map(|(xi, yi)| (xi.to_f32() - yi.to_f32()).abs()).sum()
+    }
+
+    /// Returns the cosine distance, i.e., `1 - cos(x, y)`, between `x` and `y`.
+    fn cosine_distance(x: &[Self], y: &[Self]) -> f32 {
+        let r = Self::dot_product(x, y);
+        let norms = (Self::dot_product(x, x) * Self::dot_product(y, y)).sqrt();
+
+        if norms > 0.0 {
+            1.0 - r / norms
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Scalar for f32 {
+    fn from_f32(x: f32) -> Self {
+        x
+    }
+
+    fn to_f32(self: Self) -> f32 {
+        self
+    }
+
+    fn normalize(mut vector: Vec<f32>) -> Vec<Self> {
+        math::normalize_f32(&mut vector);
+        vector
+    }
+
+    fn dot_product(x: &[Self], y: &[Self]) -> f32 {
+        math::dot_product_f32(x, y)
+    }
+
+    fn squared_euclidean_distance(x: &[Self], y: &[Self]) -> f32 {
+        math::squared_euclidean_distance_f32(x, y)
+    }
+
+    fn manhattan_distance(x: &[Self], y: &[Self]) -> f32 {
+        math::manhattan_distance_f32(x, y)
+    }
+
+    /// The vectors are assumed to be normalized.
+    fn cosine_distance(x: &[Self], y: &[Self]) -> f32 {
+        1.0 - math::dot_product_f32(x, y)
+    }
+}
+
+impl Scalar for f64 {
+    fn from_f32(x: f32) -> Self {
+        f64::from(x)
+    }
+
+    fn to_f32(self: Self) -> f32 {
+        self as f32
+    }
+
+    fn normalize(vector: Vec<f32>) -> Vec<Self> {
+        let mut vector: Vec<f64> = vector.into_iter().map(f64::from).collect();
+        let norm = dot_product_f64(&vector, &vector).sqrt();
+
+        if norm > 0.0 {
+            for x in &mut vector {
+                *x /= norm;
+            }
+        }
+
+        vector
+    }
+
+    fn dot_product(x: &[Self], y: &[Self]) -> f32 {
+        dot_product_f64(x, y) as f32
+    }
+
+    fn squared_euclidean_distance(x: &[Self], y: &[Self]) -> f32 {
+        x.iter().zip(y).map(|(xi, yi)| (xi - yi) * (xi - yi)).sum::<f64>() as f32
+    }
+
+    fn manhattan_distance(x: &[Self], y: &[Self]) -> f32 {
+        x.iter().zip(y).map(|(xi, yi)| (xi - yi).abs()).sum::<f64>() as f32
+    }
+
+    /// The vectors are assumed to be normalized.
+    fn cosine_distance(x: &[Self], y: &[Self]) -> f32 {
+        (1.0 - dot_product_f64(x, y)) as f32
+    }
+}
+
+fn dot_product_f64(x: &[f64], y: &[f64]) -> f64 {
+    x.iter().zip(y).map(|(xi, yi)| xi * yi).sum()
+}
+
+const MAX_I8_VALUE: f32 = 127.0;
+
+impl Scalar for i8 {
+    const APPROXIMATELY_NORMALIZED: bool = true;
+
+    fn from_f32(x: f32) -> Self {
+        x.clamp(-MAX_I8_VALUE, MAX_I8_VALUE) as i8
+    }
+
+    fn to_f32(self: Self) -> f32 {
+        f32::from(self)
+    }
+
+    /// Scales `vector` such that the largest absolute value is mapped to 127.
+    fn normalize(vector: Vec<f32>) -> Vec<Self> {
+        let max_value = vector.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        let scale = if max_value > 0.0 { MAX_I8_VALUE / max_value } else { 0.0 };
+
+        vector.into_iter().map(|x| Self::from_f32(scale * x)).collect()
+    }
+
+    fn dot_product(x: &[Self], y: &[Self]) -> f32 {
+        math::dot_product_i8(x, y) as f32
+    }
+
+    fn cosine_distance(x: &[Self], y: &[Self]) -> f32 {
+        let (r, dx, dy) = math::dot_product_and_squared_norms_i8(x, y);
+        let norms = (dx as f32).sqrt() * (dy as f32).sqrt();
+
+        if norms > 0.0 {
+            1.0 - r as f32 / norms
+        } else {
+            1.0
+        }
+    }
+}
+
+/// An IEEE 754 half-precision float (stored as `u16`). `F16` has 11 bits of precision, but a
+/// limited range, which is not a problem for normalized vectors.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct F16(pub u16);
+
+/// A bfloat16 (stored as `u16`). `BF16` keeps the exponent range of `f32` but only has 8 bits of
+/// precision.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BF16(pub u16);
+
+macro_rules! half_precision_scalar {
+    ($scalar_type:ident, $to_half:path, $from_half:path, $dot_product:path) => {
+        impl $scalar_type {
+            fn as_bits(x: &[Self]) -> &[u16] {
+                // safe since the type is a transparent wrapper around u16
+                unsafe { &*(x as *const [Self] as *const [u16]) }
+            }
+        }
+
+        impl Scalar for $scalar_type {
+            // the rounding changes the norms of normalized vectors slightly
+            const APPROXIMATELY_NORMALIZED: bool = true;
+
+            fn from_f32(x: f32) -> Self {
+                Self($to_half(x))
+            }
+
+            fn to_f32(self: Self) -> f32 {
+                $from_half(self.0)
+            }
+
+            fn normalize(mut vector: Vec<f32>) -> Vec<Self> {
+                math::normalize_f32(&mut vector);
+                vector.into_iter().map(Self::from_f32).collect()
+            }
+
+            fn dot_product(x: &[Self], y: &[Self]) -> f32 {
+                $dot_product(Self::as_bits(x), Self::as_bits(y))
+            }
+        }
+    };
+}
+
+half_precision_scalar!(F16, math::f32_to_f16, math::f16_to_f32, math::dot_product_f16);
+half_precision_scalar!(BF16, math::f32_to_bf16, math::bf16_to_f32, math::dot_product_bf16);
+
+/// A trait for distance functions between dense vectors.
+pub trait Metric: Copy + Default + Send + Sync + 'static {
+    /// Converts `vector` into a vector of scalars of type `T`.
+    fn convert<T: Scalar>(vector: Vec<f32>) -> Vec<T> {
+        vector.into_iter().map(T::from_f32).collect()
+    }
+
+    /// Returns the distance between `x` and `y`.
+    fn dist<T: Scalar>(x: &[T], y: &[T]) -> NotNan<f32>;
+
+    /// Returns `true` if the distances between vectors of scalars of type `T` are computed using
+    /// their norms, in which case [`DenseVectors`](struct.DenseVectors.html) keeps the norms of
+    /// the vectors in memory.
+    fn uses_norms<T: Scalar>() -> bool {
+        false
+    }
+
+    /// Returns the distance between `x` and `y` given their norms. Only used if `uses_norms` is
+    /// `true`.
+    fn dist_with_norms<T: Scalar>(x: &[T], y: &[T], _norm_x: f32, _norm_y: f32) -> NotNan<f32> {
+        Self::dist(x, y)
+    }
+}
+
+/// The cosine distance, `1 - cos(x, y)`. Vectors are normalized when converted from `f32`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cosine;
+
+impl Metric for Cosine {
+    fn convert<T: Scalar>(vector: Vec<f32>) -> Vec<T> {
+        T::normalize(vector)
+    }
+
+    fn dist<T: Scalar>(x: &[T], y: &[T]) -> NotNan<f32> {
+        let d = NotNan::new(T::cosine_distance(x, y)).unwrap();
+
+        cmp::max(0.0f32.into(), d)
+    }
+
+    fn uses_norms<T: Scalar>() -> bool {
+        T::APPROXIMATELY_NORMALIZED
+    }
+
+    fn dist_with_norms<T: Scalar>(x: &[T], y: &[T], norm_x: f32, norm_y: f32) -> NotNan<f32> {
+        let norms = norm_x * norm_y;
+        let d = if norms > 0.0 {
+            1.0 - T::dot_product(x, y) / norms
+        } else {
+            1.0
+        };
+
+        cmp::max(0.0f32.into(), NotNan::new(d).unwrap())
+    }
+}
+
+/// The squared euclidean (L2) distance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SquaredEuclidean;
+
+impl Metric for SquaredEuclidean {
+    fn dist<T: Scalar>(x: &[T], y: &[T]) -> NotNan<f32> {
+        NotNan::new(T::squared_euclidean_distance(x, y)).unwrap()
+    }
+}
+
+/// The dot product distance, `1 - <x, y>`.
+///
+/// The vectors are not normalized, but the distance is only non-negative for vectors with norms
+/// of at most one. For maximum inner product search among vectors with arbitrary norms, see
+/// [`inner_product`](../inner_product/index.html).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DotProduct;
+
+impl Metric for DotProduct {
+    fn dist<T: Scalar>(x: &[T], y: &[T]) -> NotNan<f32> {
+        NotNan::new(1.0 - T::dot_product(x, y)).unwrap()
+    }
+}
+
+/// The manhattan (L1) distance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Manhattan;
+
+impl Metric for Manhattan {
+    fn dist<T: Scalar>(x: &[T], y: &[T]) -> NotNan<f32> {
+        NotNan::new(T::manhattan_distance(x, y)).unwrap()
+    }
+}
+
+/// A vector element with scalars of type `T`, using the metric `M`.
+#[derive(Clone)]
+pub struct DenseVector<'a, T: Scalar, M: Metric>(pub Cow<'a, [T]>, PhantomData<M>);
+
+impl<'a, T: Scalar, M: Metric> DenseVector<'a, T, M> {
+    /// Creates a vector from `data` as is, i.e., without any conversion (e.g. normalization).
+    pub fn new<D: Into<Cow<'a, [T]>>>(data: D) -> Self {
+        Self(data.into(), PhantomData)
+    }
+
+    /// Returns the number of elements in this `Vector`.
+    pub fn len(self: &Self) -> usize {
+        self.0.len()
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> DenseVector<'static, T, M> {
+        DenseVector(self.0.into_owned().into(), PhantomData)
+    }
+
+    /// Converts this `Vector` into a `Vec`.
+    pub fn into_vec(self: Self) -> Vec<T> {
+        self.0.into_owned()
+    }
+
+    /// Returns a reference to the underlying slice.
+    pub fn as_slice(self: &Self) -> &[T] {
+        &self.0[..]
+    }
+}
+
+impl<T: Scalar, M: Metric> From<Vec<f32>> for DenseVector<'static, T, M> {
+    fn from(v: Vec<f32>) -> Self {
+        Self::new(M::convert::<T>(v))
+    }
+}
+
+impl<T: Scalar, M: Metric> FromIterator<f32> for DenseVector<'static, T, M> {
+    fn from_iter<I: IntoIterator<Item = f32>>(iter: I) -> Self {
+        let v: Vec<f32> = iter.into_iter().collect();
+        Self::from(v)
+    }
+}
+
+impl<'a, 'b, T: Scalar, M: Metric> Dist<DenseVector<'b, T, M>> for DenseVector<'a, T, M> {
+    fn dist(self: &Self, other: &DenseVector<'b, T, M>) -> NotNan<f32> {
+        M::dist(&self.0, &other.0)
+    }
+}
+
+/// Computes the norm of `x`.
+fn compute_norm<T: Scalar>(x: &[T]) -> f32 {
+    T::dot_product(x, x).sqrt()
+}
+
+/// A collection of `DenseVector`s.
+///
+/// If the metric uses the norms of the vectors (see [`Metric::uses_norms`](trait.Metric.html)),
+/// the norms of vectors pushed into an owned collection are computed once and kept in memory.
+/// The norms are not part of the serialized format and are not computed when loading, i.e.,
+/// loaded collections compute the norms as part of each distance computation.
+#[derive(Clone)]
+pub struct DenseVectors<'a, T: Scalar, M: Metric> {
+    pub(super) vectors: FixedWidthSliceVector<'a, T>,
+    pub(super) norms: Cow<'a, [f32]>,
+    metric: PhantomData<M>,
+}
+
+impl<'a, T: Scalar, M: Metric> DenseVectors<'a, T, M> {
+    /// Creates a new collection vector. The dimension will be set once the first vector is
+    /// pushed into the collection.
+    pub fn new() -> Self {
+        Self::from_slice_vector(FixedWidthSliceVector::new(), Vec::new())
+    }
+
+    /// Loads a collection of vectors from a `u8` buffer.
+    /// `buffer` needs to contain data in a compatible format (e.g. written with
+    /// `Vectors::write`).
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        Self::from_slice_vector(FixedWidthSliceVector::from_bytes(buffer), Vec::new())
+    }
+
+    /// Loads a memory-mapped a collection of vectors from a file.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the file is not modified
+    /// while being memory-mapped.
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        Ok(Self::from_slice_vector(
+            FixedWidthSliceVector::from_file(file)?,
+            Vec::new(),
+        ))
+    }
+
+    /// Creates a collection of vectors with dimension `dim` from a slice.
+    ///
+    /// `dim` needs to be non-zero and divide the length of `vec`.
+    pub fn from_slice(slice: &'a [T], dim: usize) -> Self {
+        Self::from_slice_vector(FixedWidthSliceVector::with_data(slice, dim), Vec::new())
+    }
+
+    /// Creates a collection of vectors with dimension `dim` from a `Vec`.
+    ///
+    /// `dim` needs to be non-zero and divide the length of `vec`.
+    pub fn from_vec(vec: Vec<T>, dim: usize) -> Self {
+        let vectors = FixedWidthSliceVector::with_data(vec, dim);
+        let norms: Vec<f32> = if M::uses_norms::<T>() {
+            vectors.par_iter().map(compute_norm).collect()
+        } else {
+            Vec::new()
+        };
+
+        Self::from_slice_vector(vectors, norms)
+    }
+
+    fn from_slice_vector(vectors: FixedWidthSliceVector<'a, T>, norms: Vec<f32>) -> Self {
+        Self {
+            vectors,
+            norms: norms.into(),
+            metric: PhantomData,
+        }
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> DenseVectors<'a, T, M> {
+        Self {
+            vectors: self.vectors.borrow(),
+            norms: Cow::Borrowed(&self.norms),
+            metric: PhantomData,
+        }
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> DenseVectors<'static, T, M> {
+        DenseVectors {
+            vectors: self.vectors.into_owned(),
+            norms: self.norms.into_owned().into(),
+            metric: PhantomData,
+        }
+    }
+
+    /// Extends `Vectors` with the elements from `vec`.
+    pub fn extend(self: &mut Self, vec: DenseVectors<'_, T, M>) {
+        if self.has_norms() {
+            let norms: Vec<f32> = (0..vec.len()).map(|i| vec.norm(i)).collect();
+            self.norms.to_mut().extend_from_slice(&norms);
+        }
+
+        self.vectors.extend_from_slice_vector(&vec.vectors);
+    }
+
+    /// Pushes `vec` onto the collection
+    pub fn push(self: &mut Self, vec: &DenseVector<'_, T, M>) {
+        if self.has_norms() {
+            self.norms.to_mut().push(compute_norm(&vec.0));
+        }
+
+        self.vectors.push(&vec.0[..]);
+    }
+
+    /// Returns whether the norms of all vectors are kept in memory, which is the case for
+    /// metrics using norms unless the vectors were loaded.
+    fn has_norms(self: &Self) -> bool {
+        M::uses_norms::<T>() && self.norms.len() == self.vectors.len()
+    }
+
+    /// Returns the number of vectors in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns the dimension of each vector in this collection.
+    pub fn dim(self: &Self) -> usize {
+        self.vectors.width()
+    }
+
+    /// Returns a reference to the vector at `index`.
+    pub fn get_element(self: &'a Self, index: usize) -> DenseVector<'a, T, M> {
+        DenseVector(Cow::Borrowed(self.vectors.get(index)), PhantomData)
+    }
+
+    /// Returns a reference to the underlying slice.
+    pub fn as_slice(self: &Self) -> &[T] {
+        self.vectors.as_slice()
+    }
+
+    /// Returns the norm of the vector at `index` (precomputed if kept in memory).
+    pub fn norm(self: &Self, index: usize) -> f32 {
+        if self.has_norms() {
+            self.norms[index]
+        } else {
+            compute_norm(self.vectors.get(index))
+        }
+    }
+}
+
+impl<'a, T: Scalar, M: Metric> Default for DenseVectors<'a, T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Scalar, M: Metric> FromIterator<DenseVector<'a, T, M>> for DenseVectors<'static, T, M> {
+    fn from_iter<I: IntoIterator<Item = DenseVector<'a, T, M>>>(iter: I) -> Self {
+        let mut vecs = DenseVectors::new();
+        for vec in iter {
+            vecs.push(&vec);
+        }
+
+        vecs
+    }
+}
+
+impl<'a, T: Scalar, M: Metric> io::Writeable for DenseVectors<'a, T, M> {
+    /// Writes `Vectors` to a `buffer`. The norms are not written.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        self.vectors.write(buffer)
+    }
+}
+
+impl<'a, T: Scalar, M: Metric> ElementContainer for DenseVectors<'a, T, M> {
+    type Element = DenseVector<'static, T, M>;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        self.get_element(idx).into_owned()
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        if self.has_norms() {
+            M::dist_with_norms(
+                self.vectors.get(idx),
+                &element.0,
+                self.norms[idx],
+                compute_norm(&element.0),
+            )
+        } else {
+            M::dist(self.vectors.get(idx), &element.0)
+        }
+    }
+
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        if self.has_norms() {
+            M::dist_with_norms(self.vectors.get(i), self.vectors.get(j), self.norms[i], self.norms[j])
+        } else {
+            M::dist(self.vectors.get(i), self.vectors.get(j))
+        }
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        others.iter().map(|&j| self.dist(idx, j)).collect()
+    }
+
+    fn dists_to_element(self: &Self, element: &Self::Element, others: &[usize]) -> Vec<NotNan<f32>> {
+        if self.has_norms() {
+            // the norm of element is only computed once
+            let norm = compute_norm(&element.0);
+
+            others
+                .iter()
+                .map(|&j| M::dist_with_norms(self.vectors.get(j), &element.0, self.norms[j], norm))
+                .collect()
+        } else {
+            others
+                .iter()
+                .map(|&j| M::dist(self.vectors.get(j), &element.0))
+                .collect()
+        }
+    }
+}
+
+impl<'a, T: Scalar, M: Metric> ExtendableElementContainer for DenseVectors<'a, T, M> {
+    type InternalElement = Self::Element;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(&element)
+    }
+}
+
+impl<'a, T: Scalar, M: Metric> Permutable for DenseVectors<'a, T, M> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        self.vectors.permute(permutation);
+
+        if self.has_norms() {
+            let norms: Vec<f32> = permutation.iter().map(|&i| self.norms[i]).collect();
+            self.norms = norms.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper;
+
+    const DIST_EPSILON: f32 = 10.0 * ::std::f32::EPSILON;
+
+    fn reference_dists<T: Scalar, M: Metric>(x: &[f32], y: &[f32]) -> (f32, f32) {
+        let expected = M::dist(x, y).into_inner();
+
+        let x: DenseVector<T, M> = x.to_vec().into();
+        let y: DenseVector<T, M> = y.to_vec().into();
+
+        (expected, x.dist(&y).into_inner())
+    }
+
+    #[test]
+    fn f64_same_as_f32() {
+        for _ in 0..100 {
+            let x: Vec<f32> = test_helper::random_floats().take(100).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(100).collect();
+
+            let (expected, d) = reference_dists::<f64, SquaredEuclidean>(&x, &y);
+            assert!((expected - d).abs() < 100.0 * DIST_EPSILON);
+
+            let (expected, d) = reference_dists::<f64, Manhattan>(&x, &y);
+            assert!((expected - d).abs() < 100.0 * DIST_EPSILON);
+
+            let x = f32::normalize(x);
+            let y = f32::normalize(y);
+
+            let (expected, d) = reference_dists::<f64, Cosine>(&x, &y);
+            assert!((expected - d).abs() < DIST_EPSILON);
+
+            let (expected, d) = reference_dists::<f64, DotProduct>(&x, &y);
+            assert!((expected - d).abs() < DIST_EPSILON);
+        }
+    }
+
+    #[test]
+    fn i8_cosine_close_to_f32() {
+        for _ in 0..100 {
+            let x: Vec<f32> = test_helper::random_floats().take(100).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(100).collect();
+
+            let x = f32::normalize(x);
+            let y = f32::normalize(y);
+
+            let (expected, d) = reference_dists::<i8, Cosine>(&x, &y);
+            assert!((expected - d).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn half_precision_cosine_close_to_f32() {
+        for _ in 0..100 {
+            let x: Vec<f32> = test_helper::random_floats().take(100).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(100).collect();
+
+            let x = f32::normalize(x);
+            let y = f32::normalize(y);
+
+            let (expected, d) = reference_dists::<F16, Cosine>(&x, &y);
+            assert!((expected - d).abs() < 0.01);
+
+            let (expected, d) = reference_dists::<BF16, Cosine>(&x, &y);
+            assert!((expected - d).abs() < 0.01);
+
+            let x: DenseVector<BF16, Cosine> = x.into();
+            assert!(x.dist(&x).into_inner() < 0.01);
+        }
+    }
+
+    #[test]
+    fn i8_saturates() {
+        let x: DenseVector<i8, SquaredEuclidean> = vec![-300.0f32, -1.4, 0.6, 300.0].into();
+
+        assert_eq!(&[-127i8, -1, 0, 127], x.as_slice());
+    }
+
+    #[test]
+    fn manhattan() {
+        let x: DenseVector<f32, Manhattan> = vec![1.0f32, -2.0, 3.0].into();
+        let y: DenseVector<f32, Manhattan> = vec![2.0f32, 2.0, 3.5].into();
+
+        assert_eq!(5.5, x.dist(&y).into_inner());
+    }
+
+    #[test]
+    fn dists_same_as_dist() {
+        let vectors: DenseVectors<f32, Manhattan> = test_helper::random_vectors(10, 50);
+        let others: Vec<usize> = (0..vectors.len()).collect();
+
+        for i in 0..vectors.len() {
+            let dists = vectors.dists(i, &others);
+
+            for &j in &others {
+                assert_eq!(vectors.get(i).dist(&vectors.get(j)), dists[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn write_and_load() {
+        let vectors: DenseVectors<i8, SquaredEuclidean> = (0..50)
+            .map(|_| {
+                test_helper::random_floats()
+                    .take(10)
+                    .map(|x| 100.0 * x)
+                    .collect::<DenseVector<_, _>>()
+            })
+            .collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        let loaded = DenseVectors::<i8, SquaredEuclidean>::from_bytes(&buffer);
+
+        assert_eq!(vectors.len(), loaded.len());
+        assert_eq!(10, loaded.dim());
+        assert_eq!(vectors.as_slice(), loaded.as_slice());
+    }
+}
@@ -0,0 +1,342 @@
+//! Storage for the elements in `SumEmbeddings`, i.e., lists of embedding ids, using 3, 4 or 5
+//! bytes per embedding id.
+//!
+//! Elements with a width other than the default 3 bytes are written with a header recording the id
+//! width. Files without a header (including all files written by earlier versions) use 3 bytes per
+//! id.
+
+use super::ElementOffset;
+use crate::slice_vector::VariableWidthSliceVector;
+use crate::{FiveByteInt, FourByteInt, ThreeByteInt};
+
+use std::convert::TryFrom;
+use std::io::{Result, Write};
+
+/// Magic bytes at the start of the header, followed by a single byte containing the id width.
+/// Interpreted as the number of elements (the first field in files without header), the header
+/// would correspond to a number larger than `2^56`.
+const HEADER_MAGIC: &[u8; 7] = b"granne\x01";
+const HEADER_LEN: usize = 8;
+
+/// The number of bytes used for storing each embedding id in
+/// [`SumEmbeddings`](struct.SumEmbeddings.html). Defaults to 3 bytes, the width used by earlier
+/// versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbeddingIdWidth {
+    /// 3 bytes, i.e., at most `2^24` embeddings.
+    Three,
+    /// 4 bytes, i.e., at most `2^32` embeddings.
+    Four,
+    /// 5 bytes, i.e., at most `2^40` embeddings.
+    Five,
+}
+
+impl Default for EmbeddingIdWidth {
+    fn default() -> Self {
+        Self::Three
+    }
+}
+
+impl EmbeddingIdWidth {
+    /// Returns the smallest width able to represent the ids of `num_embeddings` embeddings.
+    pub fn for_num_embeddings(num_embeddings: usize) -> Self {
+        [Self::Three, Self::Four, Self::Five]
+            .iter()
+            .cloned()
+            .find(|width| num_embeddings <= width.max_num_embeddings())
+            .expect("Too many embeddings")
+    }
+
+    /// Returns the number of bytes used for each id.
+    pub fn num_bytes(self: Self) -> usize {
+        match self {
+            Self::Three => 3,
+            Self::Four => 4,
+            Self::Five => 5,
+        }
+    }
+
+    /// Returns the maximum number of embeddings that can be referenced using this width (saturated
+    /// at `usize::MAX`).
+    pub fn max_num_embeddings(self: Self) -> usize {
+        usize::try_from(1u64 << (8 * self.num_bytes())).unwrap_or(usize::MAX)
+    }
+
+    fn from_num_bytes(num_bytes: u8) -> Self {
+        match num_bytes {
+            3 => Self::Three,
+            4 => Self::Four,
+            5 => Self::Five,
+            _ => panic!("Unsupported embedding id width: {}", num_bytes),
+        }
+    }
+}
+
+/// Evaluates `$body` with `$ids` bound to the underlying `VariableWidthSliceVector` of `$elements`.
+macro_rules! with_ids {
+    ($elements:expr, $ids:ident => $body:expr) => {
+        match $elements {
+            Elements::Three($ids) => $body,
+            Elements::Four($ids) => $body,
+            Elements::Five($ids) => $body,
+        }
+    };
+}
+
+/// Lists of embedding ids.
+#[derive(Clone)]
+pub enum Elements<'a> {
+    Three(VariableWidthSliceVector<'a, ThreeByteInt, ElementOffset>),
+    Four(VariableWidthSliceVector<'a, FourByteInt, ElementOffset>),
+    Five(VariableWidthSliceVector<'a, FiveByteInt, ElementOffset>),
+}
+
+impl<'a> Default for Elements<'a> {
+    fn default() -> Self {
+        Self::new(EmbeddingIdWidth::default())
+    }
+}
+
+impl<'a> Elements<'a> {
+    pub fn new(width: EmbeddingIdWidth) -> Self {
+        match width {
+            EmbeddingIdWidth::Three => Elements::Three(VariableWidthSliceVector::new()),
+            EmbeddingIdWidth::Four => Elements::Four(VariableWidthSliceVector::new()),
+            EmbeddingIdWidth::Five => Elements::Five(VariableWidthSliceVector::new()),
+        }
+    }
+
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        match read_header(buffer) {
+            Some(EmbeddingIdWidth::Three) => {
+                Elements::Three(VariableWidthSliceVector::from_bytes(&buffer[HEADER_LEN..]))
+            }
+            Some(EmbeddingIdWidth::Four) => Elements::Four(VariableWidthSliceVector::from_bytes(&buffer[HEADER_LEN..])),
+            Some(EmbeddingIdWidth::Five) => Elements::Five(VariableWidthSliceVector::from_bytes(&buffer[HEADER_LEN..])),
+            None => Elements::Three(VariableWidthSliceVector::from_bytes(buffer)),
+        }
+    }
+
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        let mmap = memmap::Mmap::map(file)?;
+        let width = read_header(&mmap);
+        let start = if width.is_some() { HEADER_LEN } else { 0 };
+
+        Ok(match width.unwrap_or_default() {
+            EmbeddingIdWidth::Three => Elements::Three(VariableWidthSliceVector::from_mmap_at(mmap, start)?),
+            EmbeddingIdWidth::Four => Elements::Four(VariableWidthSliceVector::from_mmap_at(mmap, start)?),
+            EmbeddingIdWidth::Five => Elements::Five(VariableWidthSliceVector::from_mmap_at(mmap, start)?),
+        })
+    }
+
+    pub fn width(self: &Self) -> EmbeddingIdWidth {
+        match self {
+            Elements::Three(_) => EmbeddingIdWidth::Three,
+            Elements::Four(_) => EmbeddingIdWidth::Four,
+            Elements::Five(_) => EmbeddingIdWidth::Five,
+        }
+    }
+
+    pub fn borrow(self: &'a Self) -> Elements<'a> {
+        match self {
+            Elements::Three(ids) => Elements::Three(ids.borrow()),
+            Elements::Four(ids) => Elements::Four(ids.borrow()),
+            Elements::Five(ids) => Elements::Five(ids.borrow()),
+        }
+    }
+
+    pub fn len(self: &Self) -> usize {
+        with_ids!(self, ids => ids.len())
+    }
+
+    /// Pushes an element consisting of `embedding_ids`. Panics if any of the ids does not fit in
+    /// the id width.
+    pub fn push(self: &mut Self, embedding_ids: &[usize]) {
+        let max_num_embeddings = self.width().max_num_embeddings();
+        if let Some(&id) = embedding_ids.iter().find(|&&id| id >= max_num_embeddings) {
+            panic!("Embedding id {} does not fit in {} bytes", id, self.width().num_bytes());
+        }
+
+        with_ids!(self, ids => {
+            let data: Vec<_> = embedding_ids.iter().map(|&id| id.into()).collect();
+            ids.push(&data)
+        })
+    }
+
+    /// Returns the embedding ids of the element at `idx`.
+    pub fn get_terms(self: &Self, idx: usize) -> Vec<usize> {
+        with_ids!(self, ids => ids.get(idx).iter().map(|&id| id.into()).collect())
+    }
+
+    /// Extends with the elements in `other`.
+    pub fn extend(self: &mut Self, other: &Elements<'_>) {
+        match (self, other) {
+            (Elements::Three(ids), Elements::Three(other)) => ids.extend_from_slice_vector(other),
+            (Elements::Four(ids), Elements::Four(other)) => ids.extend_from_slice_vector(other),
+            (Elements::Five(ids), Elements::Five(other)) => ids.extend_from_slice_vector(other),
+            (elements, other) => {
+                for i in 0..other.len() {
+                    elements.push(&other.get_terms(i));
+                }
+            }
+        }
+    }
+
+    /// Returns the elements with ids in `idxs` (in that order).
+    pub fn select(self: &Self, idxs: &[usize]) -> Elements<'static> {
+        match self {
+            Elements::Three(ids) => Elements::Three(select(ids, idxs)),
+            Elements::Four(ids) => Elements::Four(select(ids, idxs)),
+            Elements::Five(ids) => Elements::Five(select(ids, idxs)),
+        }
+    }
+
+    pub fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        let header_len = write_header(self.width(), buffer)?;
+
+        with_ids!(self, ids => ids.write(buffer)).map(|len| header_len + len)
+    }
+
+    pub fn write_range<B: Write>(self: &Self, buffer: &mut B, begin: usize, end: usize) -> Result<usize> {
+        let header_len = write_header(self.width(), buffer)?;
+
+        with_ids!(self, ids => ids.write_range(buffer, begin, end)).map(|len| header_len + len)
+    }
+}
+
+fn select<Id: Copy>(
+    ids: &VariableWidthSliceVector<'_, Id, ElementOffset>,
+    idxs: &[usize],
+) -> VariableWidthSliceVector<'static, Id, ElementOffset> {
+    let mut selected = VariableWidthSliceVector::new();
+    for &idx in idxs {
+        selected.push(ids.get(idx));
+    }
+
+    selected
+}
+
+fn read_header(buffer: &[u8]) -> Option<EmbeddingIdWidth> {
+    if buffer.len() >= HEADER_LEN && &buffer[..HEADER_MAGIC.len()] == HEADER_MAGIC {
+        Some(EmbeddingIdWidth::from_num_bytes(buffer[HEADER_MAGIC.len()]))
+    } else {
+        None
+    }
+}
+
+/// Writes the header for `width`. Nothing is written for the default width, keeping such files
+/// readable by earlier versions.
+fn write_header<B: Write>(width: EmbeddingIdWidth, buffer: &mut B) -> Result<usize> {
+    if width == EmbeddingIdWidth::default() {
+        return Ok(0);
+    }
+
+    buffer.write_all(HEADER_MAGIC)?;
+    buffer.write_all(&[width.num_bytes() as u8])?;
+
+    Ok(HEADER_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_for_num_embeddings() {
+        assert_eq!(EmbeddingIdWidth::Three, EmbeddingIdWidth::for_num_embeddings(0));
+        assert_eq!(EmbeddingIdWidth::Three, EmbeddingIdWidth::for_num_embeddings(1 << 24));
+        assert_eq!(
+            EmbeddingIdWidth::Four,
+            EmbeddingIdWidth::for_num_embeddings((1 << 24) + 1)
+        );
+        assert_eq!(
+            EmbeddingIdWidth::Five,
+            EmbeddingIdWidth::for_num_embeddings((1 << 32) + 1)
+        );
+    }
+
+    #[test]
+    fn write_and_load() {
+        for &width in &[EmbeddingIdWidth::Three, EmbeddingIdWidth::Four, EmbeddingIdWidth::Five] {
+            let max_id = std::cmp::min(width.max_num_embeddings() - 1, 1 << 36);
+
+            let mut elements = Elements::new(width);
+            elements.push(&[0, 1, max_id]);
+            elements.push(&[]);
+            elements.push(&[max_id / 2]);
+
+            let mut buffer = Vec::new();
+            elements.write(&mut buffer).unwrap();
+            let loaded = Elements::from_bytes(&buffer);
+
+            assert_eq!(width, loaded.width());
+            assert_eq!(3, loaded.len());
+            assert_eq!(vec![0, 1, max_id], loaded.get_terms(0));
+            assert!(loaded.get_terms(1).is_empty());
+            assert_eq!(vec![max_id / 2], loaded.get_terms(2));
+        }
+    }
+
+    #[test]
+    fn load_without_header() {
+        let mut ids = VariableWidthSliceVector::<ThreeByteInt, ElementOffset>::new();
+        ids.push(&[5.into(), 7.into()]);
+
+        let mut buffer = Vec::new();
+        ids.write(&mut buffer).unwrap();
+        let loaded = Elements::from_bytes(&buffer);
+
+        assert_eq!(EmbeddingIdWidth::Three, loaded.width());
+        assert_eq!(vec![5, 7], loaded.get_terms(0));
+    }
+
+    #[test]
+    fn default_width_is_written_without_header() {
+        let mut elements = Elements::new(EmbeddingIdWidth::Three);
+        elements.push(&[5, 7]);
+
+        let mut buffer = Vec::new();
+        elements.write(&mut buffer).unwrap();
+
+        let mut ids = VariableWidthSliceVector::<ThreeByteInt, ElementOffset>::new();
+        ids.push(&[5.into(), 7.into()]);
+        let mut expected = Vec::new();
+        ids.write(&mut expected).unwrap();
+
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn load_file() {
+        let mut elements = Elements::new(EmbeddingIdWidth::Four);
+        elements.push(&[1 << 30, 3]);
+
+        let mut file = tempfile::tempfile().unwrap();
+        elements.write(&mut file).unwrap();
+        let loaded = unsafe { Elements::from_file(&file).unwrap() };
+
+        assert_eq!(EmbeddingIdWidth::Four, loaded.width());
+        assert_eq!(vec![1 << 30, 3], loaded.get_terms(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn overflow() {
+        let mut elements = Elements::new(EmbeddingIdWidth::Three);
+        elements.push(&[1 << 24]);
+    }
+
+    #[test]
+    fn extend_with_different_width() {
+        let mut elements = Elements::new(EmbeddingIdWidth::Five);
+        elements.push(&[1 << 33]);
+
+        let mut other = Elements::new(EmbeddingIdWidth::Three);
+        other.push(&[1, 2]);
+
+        elements.extend(&other);
+
+        assert_eq!(2, elements.len());
+        assert_eq!(vec![1, 2], elements.get_terms(1));
+    }
+}
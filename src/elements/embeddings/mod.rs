@@ -4,24 +4,29 @@ use ordered_float::NotNan;
 
 use super::{angular, Dist, ElementContainer, ExtendableElementContainer};
 use crate::io;
-use crate::slice_vector::{FixedWidthSliceVector, VariableWidthSliceVector};
-use crate::{math, FiveByteInt, ThreeByteInt};
+use crate::slice_vector::FixedWidthSliceVector;
+use crate::{math, FiveByteInt};
+use std::borrow::Cow;
 use std::io::{Result, Write};
 
+#[macro_use]
+mod ids;
+
 #[doc(hidden)]
 pub mod parsing;
 
 mod reorder;
 
+pub use ids::EmbeddingIdWidth;
 pub use reorder::compute_keys_for_reordering;
 
+use ids::Elements;
+
 type Embeddings<'a> = FixedWidthSliceVector<'a, f32>;
+type Weights<'a> = FixedWidthSliceVector<'a, f32>;
 
-type EmbeddingId = ThreeByteInt;
 type ElementOffset = FiveByteInt;
 
-type Elements<'a> = VariableWidthSliceVector<'a, EmbeddingId, ElementOffset>;
-
 /// A data structure containing elements that can be embedded into a vector space.
 /// `SumEmbeddings` consists of `embeddings` and `elements`. The vector for each
 /// element is created by summing a subset of the vectors from `embeddings`.
@@ -37,32 +42,50 @@ type Elements<'a> = VariableWidthSliceVector<'a, EmbeddingId, ElementOffset>;
 ///
 /// and the element of interest is `hello world`, then its representation in `elements` would be
 /// `[0, 2]`.
+///
+/// # Weights
+///
+/// Each embedding can optionally be given a weight (e.g. IDF or
+/// [SIF](https://openreview.net/forum?id=SyK00v5xx) weights, see
+/// [`sif_weights`](fn.sif_weights.html)), in which case the vector for an element is the weighted
+/// sum `w_0 * v_0 + w_1 * v_1 + ...`. Without weights, all embeddings have weight `1`.
+///
+/// Optionally, a common component `u` (e.g. computed with
+/// [`compute_common_component`](struct.SumEmbeddings.html#method.compute_common_component)) can
+/// be removed from each vector, i.e., `v - <u, v> * u`.
 #[derive(Clone, Default)]
 pub struct SumEmbeddings<'a> {
     embeddings: Embeddings<'a>,
     elements: Elements<'a>,
+    weights: Weights<'a>,
+    common_component: Cow<'a, [f32]>,
 }
 
 impl<'a> SumEmbeddings<'a> {
     /// Constructs empty `SumEmbeddings` with no embeddings nor elements.
     pub fn new() -> Self {
-        Self {
-            embeddings: Embeddings::new(),
-            elements: Elements::new(),
-        }
+        Self::with_embedding_id_width(EmbeddingIdWidth::default())
+    }
+
+    /// Constructs empty `SumEmbeddings` using `width` for storing embedding ids. The width limits
+    /// the number of embeddings that can be referenced by the elements.
+    pub fn with_embedding_id_width(width: EmbeddingIdWidth) -> Self {
+        Self::from_parts(Embeddings::new(), Elements::new(width))
     }
 
     /// Loads `SumEmbeddings` from a buffer `elements`.
     pub fn from_bytes(embeddings: Embeddings<'a>, elements: &'a [u8]) -> Self {
-        Self {
-            embeddings,
-            elements: Elements::from_bytes(elements),
-        }
+        Self::from_parts(embeddings, Elements::from_bytes(elements))
     }
 
     /// Constructs `SumEmbeddings` with `elements`.
     pub(crate) fn from_parts(embeddings: Embeddings<'a>, elements: Elements<'a>) -> Self {
-        Self { embeddings, elements }
+        Self {
+            embeddings,
+            elements,
+            weights: Weights::new(),
+            common_component: Vec::new().into(),
+        }
     }
 
     /// Loads a memory-mapped `SumEmbeddings` from `embeddings` (and optionally `elements`).
@@ -76,13 +99,55 @@ impl<'a> SumEmbeddings<'a> {
         let elements = if let Some(elements) = elements {
             Elements::from_file(elements)?
         } else {
-            Elements::new()
+            Elements::new(EmbeddingIdWidth::default())
         };
 
-        Ok(Self {
-            embeddings: Embeddings::from_file(embeddings)?,
-            elements,
-        })
+        Ok(Self::from_parts(Embeddings::from_file(embeddings)?, elements))
+    }
+
+    /// Loads memory-mapped weights (one per embedding) from `weights`, e.g. written with
+    /// `SumEmbeddings::write_weights`.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in undefined
+    /// behavior. The caller needs to guarantee that the file is not modified while being
+    /// memory-mapped.
+    pub unsafe fn load_weights(self: &mut Self, weights: &std::fs::File) -> std::io::Result<()> {
+        let weights = Weights::from_file(weights)?;
+        self.check_num_weights(weights.len())?;
+
+        self.weights = weights;
+
+        Ok(())
+    }
+
+    /// Loads the common component to be removed from each embedding from `common_component`, e.g.
+    /// written with `SumEmbeddings::write_common_component`.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in undefined
+    /// behavior. The caller needs to guarantee that the file is not modified while being
+    /// memory-mapped.
+    pub unsafe fn load_common_component(self: &mut Self, common_component: &std::fs::File) -> std::io::Result<()> {
+        let common_component = Weights::from_file(common_component)?;
+        let dim = common_component.as_slice().len();
+
+        if dim > 0 && dim != self.embeddings.width() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "The common component has dimension {}, but the embeddings have dimension {}",
+                    dim,
+                    self.embeddings.width()
+                ),
+            ));
+        }
+
+        self.set_common_component(common_component.as_slice().to_vec());
+
+        Ok(())
     }
 
     /// Borrows the data
@@ -90,28 +155,138 @@ impl<'a> SumEmbeddings<'a> {
         Self {
             embeddings: self.embeddings.borrow(),
             elements: self.elements.borrow(),
+            weights: self.weights.borrow(),
+            common_component: Cow::Borrowed(&self.common_component),
         }
     }
 
     /// Inserts a new element into the collection at the end.
+    ///
+    /// Panics if any of the embedding ids is too large for the embedding id width.
     pub fn push<Element: AsRef<[usize]>>(self: &mut Self, element: Element) {
-        let data: Vec<EmbeddingId> = element.as_ref().iter().map(|&id| id.into()).collect();
-        self.elements.push(&data);
+        self.elements.push(element.as_ref());
     }
 
-    /// Inserts a new element into the collection at the end.
+    /// Returns the number of bytes used for storing each embedding id.
+    pub fn embedding_id_width(self: &Self) -> EmbeddingIdWidth {
+        self.elements.width()
+    }
+
+    /// Inserts a new embedding into the collection at the end.
     pub fn push_embedding(self: &mut Self, embedding: &[f32]) {
+        if !self.weights.is_empty() {
+            self.weights.push(&[1.0]);
+        }
+
+        self.embeddings.push(embedding)
+    }
+
+    /// Inserts a new embedding with weight `weight` into the collection at the end.
+    pub fn push_weighted_embedding(self: &mut Self, embedding: &[f32], weight: f32) {
+        if self.weights.is_empty() {
+            self.weights = Weights::with_data(vec![1.0; self.num_embeddings()], 1);
+        }
+
+        self.weights.push(&[weight]);
         self.embeddings.push(embedding)
     }
 
+    /// Sets the weights of the embeddings. `weights` needs to contain one weight per embedding,
+    /// otherwise an error of kind `InvalidData` is returned.
+    pub fn set_weights(self: &mut Self, weights: &[f32]) -> std::io::Result<()> {
+        self.check_num_weights(weights.len())?;
+
+        self.weights = Weights::with_data(weights.to_vec(), 1);
+
+        Ok(())
+    }
+
+    fn check_num_weights(self: &Self, num_weights: usize) -> std::io::Result<()> {
+        if num_weights == self.num_embeddings() {
+            Ok(())
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Expected one weight per embedding ({}), got {}",
+                    self.num_embeddings(),
+                    num_weights
+                ),
+            ))
+        }
+    }
+
+    /// Returns the weight of the embedding at `embedding_idx`.
+    pub fn get_weight(self: &Self, embedding_idx: usize) -> f32 {
+        if self.weights.is_empty() {
+            1.0
+        } else {
+            self.weights.get(embedding_idx)[0]
+        }
+    }
+
+    /// Sets the common component to be removed from each (non-normalized) embedding. An empty
+    /// `common_component` disables the removal.
+    pub fn set_common_component(self: &mut Self, mut common_component: Vec<f32>) {
+        if !common_component.is_empty() {
+            assert_eq!(self.embeddings.width(), common_component.len());
+            math::normalize_f32(&mut common_component);
+        }
+
+        self.common_component = common_component.into();
+    }
+
+    /// Returns the common component removed from each embedding (empty if none).
+    pub fn get_common_component(self: &Self) -> &[f32] {
+        &self.common_component
+    }
+
+    /// Computes the common component of the (weighted) element embeddings, i.e., their first
+    /// principal component (without centering), using `num_iterations` iterations of the power
+    /// method. Each iteration computes the embeddings of all elements.
+    ///
+    /// The result can be used with `set_common_component`.
+    pub fn compute_common_component(self: &Self, num_iterations: usize) -> Vec<f32> {
+        use rayon::prelude::*;
+
+        let dim = self.embeddings.width();
+        let mut component = vec![1.0f32; dim];
+        math::normalize_f32(&mut component);
+
+        for _ in 0..num_iterations {
+            component = (0..self.len())
+                .into_par_iter()
+                .fold(
+                    || vec![0.0f32; dim],
+                    |mut sum, i| {
+                        let embedding = with_ids!(&self.elements, ids => self.get_weighted_sum(ids.get(i)));
+                        let r = math::dot_product_f32(&embedding, &component);
+                        math::scaled_sum_into_f32(&mut sum, r, &embedding);
+                        sum
+                    },
+                )
+                .reduce(
+                    || vec![0.0f32; dim],
+                    |mut x, y| {
+                        math::sum_into_f32(&mut x, &y);
+                        x
+                    },
+                );
+
+            math::normalize_f32(&mut component);
+        }
+
+        component
+    }
+
     /// Returns the embedding ids for the element at `element_idx`.
     pub fn get_terms(self: &Self, element_idx: usize) -> Vec<usize> {
-        self.elements.get(element_idx).iter().map(|&id| id.into()).collect()
+        self.elements.get_terms(element_idx)
     }
 
     /// Gets the (non-normalized) embedding for the element at `element_idx`.
     pub fn get_embedding(self: &Self, element_idx: usize) -> Vec<f32> {
-        self.get_embedding_internal(self.elements.get(element_idx))
+        with_ids!(&self.elements, ids => self.get_embedding_internal(ids.get(element_idx)))
     }
 
     /// Creates a new (non-normalized) embedding for an element consisting of `embedding_ids`.
@@ -120,8 +295,20 @@ impl<'a> SumEmbeddings<'a> {
     }
 
     /// Computes a raw (non-normalized) embedding for `embedding_ids`.
-    /// Generic over `Id` in order to handle both &[usize] and &[EmbeddingId].
+    /// Generic over `Id` in order to handle both &[usize] and the stored embedding ids.
     fn get_embedding_internal<Id: Copy + Into<usize>>(self: &Self, embedding_ids: &[Id]) -> Vec<f32> {
+        let mut data = self.get_weighted_sum(embedding_ids);
+
+        if !self.common_component.is_empty() && !data.is_empty() {
+            let r = math::dot_product_f32(&data, &self.common_component);
+            math::scaled_sum_into_f32(&mut data, -r, &self.common_component);
+        }
+
+        data
+    }
+
+    /// Computes the weighted sum of the embeddings for `embedding_ids`.
+    fn get_weighted_sum<Id: Copy + Into<usize>>(self: &Self, embedding_ids: &[Id]) -> Vec<f32> {
         if embedding_ids.is_empty() {
             if self.embeddings.len() > 0 {
                 return vec![0.0f32; self.embeddings.width()];
@@ -130,16 +317,28 @@ impl<'a> SumEmbeddings<'a> {
             }
         }
 
-        let w: usize = embedding_ids[0].into();
-        let mut data: Vec<f32> = self.embeddings.get(w).to_vec();
+        if self.weights.is_empty() {
+            let w: usize = embedding_ids[0].into();
+            let mut data: Vec<f32> = self.embeddings.get(w).to_vec();
 
-        for w in embedding_ids.iter().skip(1).map(|&id| id.into()) {
-            let embedding = self.embeddings.get(w);
+            for w in embedding_ids.iter().skip(1).map(|&id| id.into()) {
+                let embedding = self.embeddings.get(w);
 
-            math::sum_into_f32(&mut data, embedding);
-        }
+                math::sum_into_f32(&mut data, embedding);
+            }
 
-        data
+            data
+        } else {
+            let mut data = vec![0.0f32; self.embeddings.width()];
+
+            for w in embedding_ids.iter().map(|&id| id.into()) {
+                let embedding = self.embeddings.get(w);
+
+                math::scaled_sum_into_f32(&mut data, self.weights.get(w)[0], embedding);
+            }
+
+            data
+        }
     }
 
     /// Returns the number of elements in this collection.
@@ -156,6 +355,35 @@ impl<'a> SumEmbeddings<'a> {
     pub fn write_embeddings<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
         self.embeddings.write(buffer)
     }
+
+    /// Writes the weights of the embeddings to `buffer` (all weights are `1` if no weights have
+    /// been set).
+    pub fn write_weights<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        if self.weights.is_empty() {
+            Weights::with_data(vec![1.0f32; self.num_embeddings()], 1).write(buffer)
+        } else {
+            self.weights.write(buffer)
+        }
+    }
+
+    /// Writes the common component removed from each embedding to `buffer` (empty if none).
+    pub fn write_common_component<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        let width = std::cmp::max(1, self.common_component.len());
+
+        Weights::with_data(&self.common_component[..], width).write(buffer)
+    }
+}
+
+/// Computes SIF (smooth inverse frequency) weights `a / (a + p(w))` from the (absolute)
+/// `frequencies` of the embeddings, where `p(w)` is the relative frequency of the embedding `w`.
+/// `a` is typically in the range `[1e-4, 1e-3]`.
+pub fn sif_weights(frequencies: &[f32], a: f32) -> Vec<f32> {
+    let total: f32 = frequencies.iter().sum();
+
+    frequencies
+        .iter()
+        .map(|&f| if total > 0.0 { a / (a + f / total) } else { 1.0 })
+        .collect()
 }
 
 impl<'a> ElementContainer for SumEmbeddings<'a> {
@@ -197,20 +425,160 @@ impl<'a> super::Permutable for SumEmbeddings<'a> {
         let chunk_size = std::cmp::max(10_000, self.len() / 400);
         let chunks: Vec<_> = permutation
             .par_chunks(chunk_size)
-            .map(|c| {
-                let mut elements = Elements::new();
-                for &id in c {
-                    elements.push(self.elements.get(id));
-                }
-                elements
-            })
+            .map(|c| self.elements.select(c))
             .collect();
 
-        let mut new_elements = Elements::new();
+        let mut new_elements = Elements::new(self.elements.width());
         for chunk in chunks {
-            new_elements.extend_from_slice_vector(&chunk);
+            new_elements.extend(&chunk);
         }
 
         self.elements = new_elements;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embeddings() -> SumEmbeddings<'static> {
+        let mut embeddings = SumEmbeddings::new();
+        embeddings.push_embedding(&[1.0, 0.0, 0.0]);
+        embeddings.push_embedding(&[0.0, 1.0, 0.0]);
+        embeddings.push_embedding(&[0.0, 0.0, 1.0]);
+
+        embeddings
+    }
+
+    #[test]
+    fn unweighted_sum() {
+        let embeddings = embeddings();
+
+        assert_eq!(vec![1.0, 1.0, 0.0], embeddings.get_embedding_internal(&[0usize, 1]));
+        assert_eq!(1.0, embeddings.get_weight(2));
+    }
+
+    #[test]
+    fn weighted_sum() {
+        let mut embeddings = embeddings();
+        embeddings.set_weights(&[0.5, 2.0, 1.0]).unwrap();
+        embeddings.push_embedding(&[1.0, 1.0, 1.0]);
+        embeddings.push_weighted_embedding(&[1.0, 0.0, 1.0], 3.0);
+
+        assert_eq!(vec![0.5, 2.0, 0.0], embeddings.get_embedding_internal(&[0usize, 1]));
+        assert_eq!(vec![4.0, 1.0, 4.0], embeddings.get_embedding_internal(&[3usize, 4]));
+        assert_eq!(5, embeddings.num_embeddings());
+    }
+
+    #[test]
+    fn push_weighted_embedding_backfills_weights() {
+        let mut embeddings = embeddings();
+        embeddings.push_weighted_embedding(&[1.0, 1.0, 1.0], 0.25);
+
+        assert_eq!(1.0, embeddings.get_weight(0));
+        assert_eq!(0.25, embeddings.get_weight(3));
+        assert_eq!(vec![1.25, 0.25, 0.25], embeddings.get_embedding_internal(&[0usize, 3]));
+    }
+
+    #[test]
+    fn write_and_load_weights() {
+        let mut embeddings = embeddings();
+
+        let mut buffer = Vec::new();
+        embeddings.write_weights(&mut buffer).unwrap();
+        assert_eq!(&[1.0, 1.0, 1.0], Weights::from_bytes(&buffer).as_slice());
+
+        embeddings.set_weights(&[0.1, 0.2, 0.3]).unwrap();
+        let mut buffer = Vec::new();
+        embeddings.write_weights(&mut buffer).unwrap();
+        assert_eq!(&[0.1, 0.2, 0.3], Weights::from_bytes(&buffer).as_slice());
+    }
+
+    #[test]
+    fn weights_with_wrong_length() {
+        let mut embeddings = embeddings();
+
+        let error = embeddings.set_weights(&[1.0, 2.0]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+
+        let mut file = tempfile::tempfile().unwrap();
+        Weights::with_data(vec![1.0, 2.0], 1).write(&mut file).unwrap();
+
+        let error = unsafe { embeddings.load_weights(&file).unwrap_err() };
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert_eq!(1.0, embeddings.get_weight(2));
+
+        let mut file = tempfile::tempfile().unwrap();
+        Weights::with_data(vec![1.0, 2.0], 2).write(&mut file).unwrap();
+
+        let error = unsafe { embeddings.load_common_component(&file).unwrap_err() };
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert!(embeddings.get_common_component().is_empty());
+    }
+
+    #[test]
+    fn remove_common_component() {
+        let mut embeddings = embeddings();
+        embeddings.set_weights(&[1.0, 1.0, 10.0]).unwrap();
+        embeddings.push(&[0, 2]);
+        embeddings.push(&[1, 2]);
+
+        let common_component = embeddings.compute_common_component(20);
+        assert!((1.0 - math::dot_product_f32(&common_component, &common_component)).abs() < 1e-5);
+        assert!(common_component[2].abs() > 0.99);
+
+        embeddings.set_common_component(common_component.clone());
+
+        for i in 0..embeddings.len() {
+            let embedding = embeddings.get_embedding_internal(embeddings.get_terms(i).as_slice());
+            assert!(math::dot_product_f32(&embedding, &common_component).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn write_and_load_common_component() {
+        let mut embeddings = embeddings();
+        let mut loaded = embeddings.clone();
+
+        let mut buffer = Vec::new();
+        embeddings.write_common_component(&mut buffer).unwrap();
+        assert!(Weights::from_bytes(&buffer).is_empty());
+
+        embeddings.set_common_component(vec![0.0, 3.0, 4.0]);
+        let mut file = tempfile::tempfile().unwrap();
+        embeddings.write_common_component(&mut file).unwrap();
+
+        unsafe { loaded.load_common_component(&file).unwrap() };
+        assert_eq!(&[0.0, 0.6, 0.8], loaded.get_common_component());
+        assert_eq!(embeddings.create_embedding(&[0, 1]), loaded.create_embedding(&[0, 1]));
+    }
+
+    #[test]
+    fn embedding_id_width_is_written() {
+        let mut embeddings = SumEmbeddings::with_embedding_id_width(EmbeddingIdWidth::Five);
+        embeddings.push(&[1 << 34, 2]);
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&embeddings, &mut buffer).unwrap();
+        let loaded = SumEmbeddings::from_bytes(Embeddings::new(), &buffer);
+
+        assert_eq!(EmbeddingIdWidth::Five, loaded.embedding_id_width());
+        assert_eq!(vec![1 << 34, 2], loaded.get_terms(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn embedding_id_overflow() {
+        let mut embeddings = SumEmbeddings::new();
+        embeddings.push(&[1 << 24]);
+    }
+
+    #[test]
+    fn sif_weights_decrease_with_frequency() {
+        let weights = sif_weights(&[1.0, 10.0, 989.0], 1e-3);
+
+        assert!(weights[0] > weights[1]);
+        assert!(weights[1] > weights[2]);
+        assert!((weights[0] - 1e-3 / (1e-3 + 1e-3)).abs() < 1e-5);
+    }
+}
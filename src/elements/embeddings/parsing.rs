@@ -99,10 +99,22 @@ pub fn parse_elements_and_save_shards_to_disk(
     elements.len()
 }
 
+/// Number of power iterations used when computing the common component of the embeddings.
+const COMMON_COMPONENT_NUM_ITERATIONS: usize = 10;
+
 // TODO: Make it possible to write i8 and f32 vectors
+/// Computes the embeddings of the elements at `elements_path` and writes them to `output_path`.
+///
+/// If `weights_path` is given, the word embeddings are weighted using the weights in that file
+/// (e.g. written with `SumEmbeddings::write_weights`). If `common_component_path` is given, the
+/// common component of all element embeddings is removed before normalization and written to that
+/// path, so that it can be removed from query embeddings as well (see
+/// `SumEmbeddings::load_common_component`).
 pub fn compute_embeddings_and_save_to_disk(
     elements_path: &Path,
     word_embeddings_path: &Path,
+    weights_path: Option<&Path>,
+    common_component_path: Option<&Path>,
     output_path: &Path,
     show_progress: bool,
 ) {
@@ -114,7 +126,22 @@ pub fn compute_embeddings_and_save_to_disk(
     let elements = unsafe { memmap::Mmap::map(&elements).unwrap() };
     let elements = Elements::from_bytes(&elements);
 
-    let elements = SumEmbeddings::from_parts(embeddings, elements);
+    let mut elements = SumEmbeddings::from_parts(embeddings, elements);
+
+    if let Some(weights_path) = weights_path {
+        let weights = File::open(weights_path).expect("Could not open weights file");
+        unsafe { elements.load_weights(&weights) }.expect("Could not load weights");
+    }
+
+    if let Some(common_component_path) = common_component_path {
+        let common_component = elements.compute_common_component(COMMON_COMPONENT_NUM_ITERATIONS);
+        elements.set_common_component(common_component);
+
+        let mut file = File::create(common_component_path).expect("Could not create common component file");
+        elements
+            .write_common_component(&mut file)
+            .expect("Could not write common component");
+    }
 
     let file = File::create(&output_path).expect("Could not create output file");
     let mut file = BufWriter::new(file);
@@ -171,6 +198,8 @@ pub fn parse_elements_in_directory_or_file(
         None
     };
 
+    let width = EmbeddingIdWidth::for_num_embeddings(word_ids.len());
+
     let query_parts: Vec<Elements> = parts
         .par_iter()
         .map(|part| {
@@ -178,9 +207,9 @@ pub fn parse_elements_in_directory_or_file(
 
             let elements = if part.to_str().unwrap().ends_with(".gz") {
                 let query_file = flate2::read::GzDecoder::new(query_file);
-                parse_file(query_file, &word_ids)
+                parse_file(query_file, &word_ids, width)
             } else {
-                parse_file(query_file, &word_ids)
+                parse_file(query_file, &word_ids, width)
             };
 
             if let Some(ref progress_bar) = progress_bar {
@@ -200,9 +229,9 @@ pub fn parse_elements_in_directory_or_file(
         None
     };
 
-    let mut elements = Elements::new();
+    let mut elements = Elements::new(width);
     for query_part in query_parts {
-        elements.extend_from_slice_vector(&query_part);
+        elements.extend(&query_part);
 
         if let Some(ref mut progress_bar) = progress_bar {
             progress_bar.inc();
@@ -216,10 +245,10 @@ pub fn parse_elements_in_directory_or_file(
     elements
 }
 
-fn parse_file<T: Read>(query_file: T, word_ids: &HashMap<String, usize>) -> Elements<'static> {
+fn parse_file<T: Read>(query_file: T, word_ids: &HashMap<String, usize>, width: EmbeddingIdWidth) -> Elements<'static> {
     let query_file = BufReader::new(query_file);
 
-    let mut elements = Elements::new();
+    let mut elements = Elements::new(width);
 
     for qs in query_file.lines() {
         let mut query_data = Vec::new();
@@ -229,7 +258,7 @@ fn parse_file<T: Read>(query_file: T, word_ids: &HashMap<String, usize>) -> Elem
 
         for word in qs.split_whitespace() {
             if let Some(&id) = word_ids.get(word) {
-                query_data.push(EmbeddingId::from(id));
+                query_data.push(id);
             }
         }
 
@@ -0,0 +1,73 @@
+/*!
+This module contains element types for euclidean vectors using `f32` as scalars.
+
+In contrast to [`angular`](../angular/index.html), the vectors are not normalized and the distance
+between two vectors is their squared euclidean (L2) distance. `euclidean::Vectors` is a type alias
+for [`DenseVectors`](../dense/struct.DenseVectors.html) using the
+[`SquaredEuclidean`](../dense/struct.SquaredEuclidean.html) metric.
+
+Since version 0.6.0, `euclidean::Vector` is a type alias as well and can no longer be constructed or
+matched as a tuple struct: use `euclidean::Vector::new(data)` and `vector.as_slice()` instead.
+
+# Example
+
+```
+use granne::{euclidean, Dist};
+
+let x: euclidean::Vector = vec![1.0f32, 2.0, 3.0].into();
+let y: euclidean::Vector = vec![2.0f32, 2.0, 5.0].into();
+
+assert_eq!(5.0, x.dist(&y).into_inner());
+```
+*/
+
+use super::dense::{DenseVector, DenseVectors, SquaredEuclidean};
+
+/// A vector element.
+pub type Vector<'a> = DenseVector<'a, f32, SquaredEuclidean>;
+
+/// A collection of `Vector`s.
+pub type Vectors<'a> = DenseVectors<'a, f32, SquaredEuclidean>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helper, Dist};
+
+    const DIST_EPSILON: f32 = 10.0 * ::std::f32::EPSILON;
+
+    #[test]
+    fn reference_dist() {
+        for _ in 0..100 {
+            let x: Vector = test_helper::random_floats().take(100).collect();
+            let y: Vector = test_helper::random_floats().take(100).collect();
+
+            let expected: f32 = x
+                .as_slice()
+                .iter()
+                .zip(y.as_slice())
+                .map(|(xi, yi)| (xi - yi) * (xi - yi))
+                .sum();
+
+            assert!((x.dist(&y).into_inner() - expected).abs() < 100.0 * DIST_EPSILON);
+        }
+    }
+
+    #[test]
+    fn dist_between_same_vector() {
+        for _ in 0..100 {
+            let x: Vector = test_helper::random_floats().take(100).collect();
+
+            assert!(x.dist(&x).into_inner() < DIST_EPSILON);
+        }
+    }
+
+    #[test]
+    fn not_normalized() {
+        let x: Vector = vec![3.0f32, 4.0].into();
+        let y: Vector = vec![6.0f32, 8.0].into();
+
+        assert_eq!(&[3.0f32, 4.0], x.as_slice());
+        assert_eq!(25.0, x.dist(&y).into_inner());
+    }
+}
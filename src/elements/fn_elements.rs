@@ -0,0 +1,184 @@
+/*!
+This module contains an adapter for indexing elements of any type using a custom distance function.
+
+[`FnElements`](struct.FnElements.html) wraps a `Vec<T>` together with a closure (or a boxed trait
+object) computing the distance between two elements. This is mostly useful for prototyping, since
+specialized `ElementContainer`s are typically both faster and more memory efficient.
+
+The distance function should return a non-negative distance, which is zero between identical
+elements (elements for which the distance to itself is larger than zero are not indexed). A `NaN`
+distance is treated as infinitely large.
+
+# Example
+
+```
+use granne::{fn_elements::FnElements, BuildConfig, Builder, GranneBuilder, Index};
+
+#[derive(Clone)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+let mut elements = FnElements::new(|a: &Point, b: &Point| (a.x - b.x).abs() + (a.y - b.y).abs());
+for i in 0..100 {
+    elements.push(Point { x: i as f32, y: (i % 10) as f32 });
+}
+
+let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+builder.build();
+
+let res = builder.get_index().search(&Point { x: 42.2, y: 2.1 }, 50, 1);
+assert_eq!(42, res[0].0);
+```
+*/
+
+use super::{ElementContainer, ExtendableElementContainer, Permutable};
+
+use ordered_float::NotNan;
+
+/// A collection of elements of type `T` using the distance function `F`.
+#[derive(Clone)]
+pub struct FnElements<T, F> {
+    elements: Vec<T>,
+    dist: F,
+}
+
+impl<T, F> FnElements<T, F>
+where
+    F: Fn(&T, &T) -> f32,
+{
+    /// Creates an empty collection using `dist` as distance function.
+    pub fn new(dist: F) -> Self {
+        Self::with_elements(Vec::new(), dist)
+    }
+
+    /// Creates a collection containing `elements` using `dist` as distance function.
+    pub fn with_elements(elements: Vec<T>, dist: F) -> Self {
+        Self { elements, dist }
+    }
+
+    /// Pushes `element` onto the collection.
+    pub fn push(self: &mut Self, element: T) {
+        self.elements.push(element);
+    }
+
+    /// Returns the number of elements in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns `true` if the collection contains no elements.
+    pub fn is_empty(self: &Self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Returns a reference to the element at `index`.
+    pub fn get_element(self: &Self, index: usize) -> &T {
+        &self.elements[index]
+    }
+
+    /// Returns a reference to the underlying elements.
+    pub fn as_slice(self: &Self) -> &[T] {
+        &self.elements[..]
+    }
+
+    /// Converts this collection into a `Vec` of its elements.
+    pub fn into_vec(self: Self) -> Vec<T> {
+        self.elements
+    }
+
+    fn dist_between(self: &Self, a: &T, b: &T) -> NotNan<f32> {
+        NotNan::new((self.dist)(a, b)).unwrap_or_else(|_| NotNan::new(std::f32::INFINITY).unwrap())
+    }
+}
+
+impl<T: Clone, F> ElementContainer for FnElements<T, F>
+where
+    F: Fn(&T, &T) -> f32,
+{
+    type Element = T;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        self.elements[idx].clone()
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        self.dist_between(&self.elements[idx], element)
+    }
+
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        self.dist_between(&self.elements[i], &self.elements[j])
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        let element = &self.elements[idx];
+        others
+            .iter()
+            .map(|&j| self.dist_between(element, &self.elements[j]))
+            .collect()
+    }
+}
+
+impl<T: Clone, F> ExtendableElementContainer for FnElements<T, F>
+where
+    F: Fn(&T, &T) -> f32,
+{
+    type InternalElement = T;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(element)
+    }
+}
+
+impl<T: Clone, F> Permutable for FnElements<T, F> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        assert_eq!(self.elements.len(), permutation.len());
+
+        self.elements = permutation.iter().map(|&i| self.elements[i].clone()).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nan_is_infinite() {
+        let elements = FnElements::with_elements(vec![1.0f32, 2.0, std::f32::NAN], |a: &f32, b: &f32| (a - b).abs());
+
+        assert_eq!(1.0, elements.dist(0, 1).into_inner());
+        assert_eq!(std::f32::INFINITY, elements.dist(0, 2).into_inner());
+        assert_eq!(
+            std::f32::INFINITY,
+            elements.dist_to_element(1, &std::f32::NAN).into_inner()
+        );
+    }
+
+    #[test]
+    fn boxed_dist() {
+        let dist: Box<dyn Fn(&String, &String) -> f32 + Send + Sync> =
+            Box::new(|a, b| (a.len() as f32 - b.len() as f32).abs());
+
+        let mut elements = FnElements::new(dist);
+        elements.push("a".to_string());
+        elements.push("abcd".to_string());
+
+        let dists: Vec<f32> = elements.dists(0, &[0, 1]).into_iter().map(|d| d.into_inner()).collect();
+        assert_eq!(vec![0.0, 3.0], dists);
+        assert_eq!(2.0, elements.dist_to_element(1, &"ab".to_string()).into_inner());
+    }
+
+    #[test]
+    fn permute() {
+        let mut elements = FnElements::with_elements(vec![0, 1, 2, 3], |a: &i32, b: &i32| (a - b).abs() as f32);
+
+        elements.permute(&[2, 0, 3, 1]);
+
+        assert_eq!(&[2, 0, 3, 1], elements.as_slice());
+    }
+}
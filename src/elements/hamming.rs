@@ -0,0 +1,123 @@
+/*!
+This module contains element types for binary vectors (e.g. binary hash codes) using packed `u64`
+as scalars.
+
+The distance between two vectors is their Hamming distance, i.e., the number of differing bits.
+
+A binary vector can be created either directly from its packed `u64` words or from a `Vec<f32>`,
+in which case each dimension is mapped to one bit (set if the value is positive). In the latter
+case, the number of bits is rounded up to a multiple of 64 and the padding bits are zero.
+
+# Example
+
+```
+use granne::{hamming, Dist};
+
+let x: hamming::Vector = vec![0b1011u64].into();
+let y: hamming::Vector = vec![0b0110u64].into();
+
+assert_eq!(3.0, x.dist(&y).into_inner());
+
+let z: hamming::Vector = vec![0.3f32, -0.1, 0.2, 0.7].into();
+assert_eq!(&[0b1101u64], z.as_slice());
+```
+*/
+
+use super::{Dist, ElementContainer, ExtendableElementContainer};
+use crate::{io, math, slice_vector::FixedWidthSliceVector};
+
+use ordered_float::NotNan;
+
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::iter::FromIterator;
+
+dense_vector!(u64);
+
+const BITS_PER_WORD: usize = 64;
+
+impl From<Vec<f32>> for Vector<'static> {
+    fn from(v: Vec<f32>) -> Self {
+        let mut words = vec![0u64; (v.len() + BITS_PER_WORD - 1) / BITS_PER_WORD];
+
+        for (i, _) in v.iter().enumerate().filter(|&(_, &x)| x > 0.0) {
+            words[i / BITS_PER_WORD] |= 1 << (i % BITS_PER_WORD);
+        }
+
+        Self(Cow::from(words))
+    }
+}
+
+impl From<Vec<u64>> for Vector<'static> {
+    fn from(v: Vec<u64>) -> Self {
+        Self(Cow::from(v))
+    }
+}
+
+impl<'a> Vector<'a> {
+    /// Returns the number of bits in this `Vector`.
+    pub fn num_bits(self: &Self) -> usize {
+        BITS_PER_WORD * self.len()
+    }
+}
+
+impl<'a, 'b> Dist<Vector<'b>> for Vector<'a> {
+    fn dist(self: &Self, other: &Vector<'b>) -> NotNan<f32> {
+        let &Vector(ref x) = self;
+        let &Vector(ref y) = other;
+
+        NotNan::new(math::hamming_distance_u64(x, y) as f32).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper;
+
+    #[test]
+    fn reference_dist() {
+        for _ in 0..100 {
+            let x: Vec<f32> = test_helper::random_floats().take(300).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(300).collect();
+
+            let expected = x.iter().zip(&y).filter(|&(xi, yi)| (*xi > 0.0) != (*yi > 0.0)).count();
+
+            let x: Vector = x.into();
+            let y: Vector = y.into();
+
+            assert_eq!(expected as f32, x.dist(&y).into_inner());
+        }
+    }
+
+    #[test]
+    fn dist_between_same_vector() {
+        for _ in 0..100 {
+            let x: Vector = test_helper::random_floats().take(256).collect();
+
+            assert_eq!(0.0, x.dist(&x).into_inner());
+        }
+    }
+
+    #[test]
+    fn pack_bits() {
+        let x: Vector = test_helper::random_floats().take(130).collect();
+
+        assert_eq!(3, x.len());
+        assert_eq!(192, x.num_bits());
+        assert_eq!(0, x.as_slice()[2] >> 2);
+    }
+
+    #[test]
+    fn write_and_load() {
+        let vectors: Vectors = test_helper::random_vectors(512, 50);
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        let loaded = Vectors::from_bytes(&buffer);
+
+        assert_eq!(vectors.len(), loaded.len());
+        assert_eq!(8, loaded.dim());
+        assert_eq!(vectors.as_slice(), loaded.as_slice());
+    }
+}
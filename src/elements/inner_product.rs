@@ -0,0 +1,377 @@
+/*!
+This module contains element types for maximum inner product search using `f32` as scalars.
+
+The vectors are not normalized, i.e., the norm of each vector is kept and affects the ranking.
+
+Since the (negative) inner product is not a metric, each stored vector `x` is augmented with an
+extra dimension `sqrt(M^2 - |x|^2)`, where `M` is the maximum norm of the collection (see
+[`Vectors::with_max_norm`](struct.Vectors.html#method.with_max_norm)). All stored vectors then
+have the norm `M`, which makes the graph built on them well-behaved. When loading vectors, `M` is
+the norm of the first stored vector, so vectors can only be pushed onto a loaded collection if it
+is not empty (otherwise use `Vectors::with_max_norm`). Queries are augmented with
+`0`, which means that the distance from a stored vector `x` to a query `q` is
+
+`M^2 - <x, q>`,
+
+i.e., the closest elements are the ones with the largest inner product with the query. The inner
+product can be recovered by `max_squared_norm() - dist`.
+
+# Example
+
+```
+use granne::{inner_product, ElementContainer};
+
+let vectors: Vec<inner_product::Vector> = vec![
+    vec![1.0f32, 0.0].into(),
+    vec![0.0f32, 3.0].into(),
+];
+let elements: inner_product::Vectors = vectors.into_iter().collect();
+
+let query: inner_product::Vector = vec![1.0f32, 1.0].into();
+
+// the second vector has the largest inner product with the query
+assert!(elements.dist_to_element(1, &query) < elements.dist_to_element(0, &query));
+assert_eq!(3.0, elements.max_squared_norm() - elements.dist_to_element(1, &query).into_inner());
+```
+*/
+
+use super::{Dist, ElementContainer, ExtendableElementContainer, Permutable};
+use crate::{io, math, slice_vector::FixedWidthSliceVector};
+
+use ordered_float::NotNan;
+
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::iter::FromIterator;
+
+/// A vector element. The underlying data contains one extra (augmented) dimension, which is zero
+/// for queries.
+#[derive(Clone)]
+pub struct Vector<'a>(Cow<'a, [f32]>);
+
+impl<'a> Vector<'a> {
+    /// Returns the number of elements in this `Vector` (excluding the augmented dimension).
+    pub fn len(self: &Self) -> usize {
+        self.0.len() - 1
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vector<'static> {
+        Vector(self.0.into_owned().into())
+    }
+
+    /// Converts this `Vector` into a `Vec` (excluding the augmented dimension).
+    pub fn into_vec(self: Self) -> Vec<f32> {
+        let mut v = self.0.into_owned();
+        v.pop();
+        v
+    }
+
+    /// Returns a reference to the underlying slice (excluding the augmented dimension).
+    pub fn as_slice(self: &Self) -> &[f32] {
+        &self.0[..self.len()]
+    }
+}
+
+impl From<Vec<f32>> for Vector<'static> {
+    fn from(mut v: Vec<f32>) -> Self {
+        v.push(0.0);
+
+        Self(Cow::from(v))
+    }
+}
+
+impl FromIterator<f32> for Vector<'static> {
+    fn from_iter<I: IntoIterator<Item = f32>>(iter: I) -> Self {
+        let v: Vec<f32> = iter.into_iter().collect();
+        Self::from(v)
+    }
+}
+
+impl<'a, 'b> Dist<Vector<'b>> for Vector<'a> {
+    fn dist(self: &Self, other: &Vector<'b>) -> NotNan<f32> {
+        let &Vector(ref x) = self;
+        let &Vector(ref y) = other;
+
+        NotNan::new(math::dot_product_f32(x, x) - math::dot_product_f32(x, y)).unwrap()
+    }
+}
+
+/// A collection of `Vector`s.
+#[derive(Clone)]
+pub struct Vectors<'a> {
+    vectors: FixedWidthSliceVector<'a, f32>,
+    max_squared_norm: f32,
+}
+
+impl<'a> Vectors<'a> {
+    /// Creates a new collection of vectors, in which no vector can have a norm larger than
+    /// `max_norm`. The dimension will be set once the first vector is pushed into the collection.
+    pub fn with_max_norm(max_norm: f32) -> Self {
+        Self {
+            vectors: FixedWidthSliceVector::new(),
+            max_squared_norm: max_norm * max_norm,
+        }
+    }
+
+    /// Loads a collection of vectors from a `u8` buffer.
+    /// `buffer` needs to contain data in a compatible format (e.g. written with
+    /// `Vectors::write`).
+    ///
+    /// The maximum norm is the norm of the stored vectors. Vectors cannot be pushed onto an empty
+    /// loaded collection, use [`with_max_norm`](#method.with_max_norm) instead.
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        Self::from_slice_vector(FixedWidthSliceVector::from_bytes(buffer))
+    }
+
+    /// Loads a memory-mapped a collection of vectors from a file (see
+    /// [`from_bytes`](#method.from_bytes)).
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the file is not modified
+    /// while being memory-mapped.
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        Ok(Self::from_slice_vector(FixedWidthSliceVector::from_file(file)?))
+    }
+
+    fn from_slice_vector(vectors: FixedWidthSliceVector<'a, f32>) -> Self {
+        // all stored vectors have the same (maximum) norm
+        let max_squared_norm = if vectors.is_empty() {
+            0.0
+        } else {
+            let v = vectors.get(0);
+            math::dot_product_f32(v, v)
+        };
+
+        Self {
+            vectors,
+            max_squared_norm,
+        }
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> Vectors<'a> {
+        Self {
+            vectors: self.vectors.borrow(),
+            max_squared_norm: self.max_squared_norm,
+        }
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vectors<'static> {
+        Vectors {
+            vectors: self.vectors.into_owned(),
+            max_squared_norm: self.max_squared_norm,
+        }
+    }
+
+    /// Pushes `vec` onto the collection.
+    ///
+    /// The norm of `vec` cannot be larger than the maximum norm of this collection.
+    pub fn push(self: &mut Self, vec: &Vector<'_>) {
+        let x = vec.as_slice();
+        let squared_norm = math::dot_product_f32(x, x);
+
+        assert!(
+            squared_norm <= self.max_squared_norm * (1.0 + 16.0 * std::f32::EPSILON),
+            "Cannot push a vector with a norm larger than the maximum norm of the collection."
+        );
+
+        let mut augmented = Vec::with_capacity(x.len() + 1);
+        augmented.extend_from_slice(x);
+        augmented.push((self.max_squared_norm - squared_norm).max(0.0).sqrt());
+
+        self.vectors.push(&augmented);
+    }
+
+    /// Returns the number of vectors in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns the dimension of each vector in this collection (excluding the augmented
+    /// dimension).
+    pub fn dim(self: &Self) -> usize {
+        self.vectors.width().saturating_sub(1)
+    }
+
+    /// Returns the maximum norm `M` of this collection.
+    pub fn max_norm(self: &Self) -> f32 {
+        self.max_squared_norm.sqrt()
+    }
+
+    /// Returns the squared maximum norm `M^2` of this collection.
+    pub fn max_squared_norm(self: &Self) -> f32 {
+        self.max_squared_norm
+    }
+
+    /// Returns a reference to the vector at `index`.
+    pub fn get_element(self: &'a Self, index: usize) -> Vector<'a> {
+        Vector(Cow::Borrowed(self.vectors.get(index)))
+    }
+}
+
+impl<'a> FromIterator<Vector<'a>> for Vectors<'static> {
+    /// Collects the vectors into a collection, using the largest norm among them as maximum norm.
+    fn from_iter<I: IntoIterator<Item = Vector<'a>>>(iter: I) -> Self {
+        let vecs: Vec<Vector<'a>> = iter.into_iter().collect();
+
+        let max_squared_norm = vecs
+            .iter()
+            .map(|v| math::dot_product_f32(v.as_slice(), v.as_slice()))
+            .fold(0.0f32, f32::max);
+
+        let mut vectors = Vectors::with_max_norm(max_squared_norm.sqrt());
+        vectors.max_squared_norm = max_squared_norm;
+
+        for vec in &vecs {
+            vectors.push(vec);
+        }
+
+        vectors
+    }
+}
+
+impl<'a> io::Writeable for Vectors<'a> {
+    /// Writes `Vectors` to a `buffer`.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        self.vectors.write(buffer)
+    }
+}
+
+impl<'a> ElementContainer for Vectors<'a> {
+    type Element = Vector<'static>;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        self.get_element(idx).into_owned()
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        let r = math::dot_product_f32(self.vectors.get(idx), &element.0);
+
+        NotNan::new(self.max_squared_norm - r).unwrap()
+    }
+
+    /// Returns the distance between two stored vectors: `|x - y|^2 / 2`, which is equal to
+    /// `M^2 - <x, y>` for augmented vectors.
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        let d = math::squared_euclidean_distance_f32(self.vectors.get(i), self.vectors.get(j));
+
+        NotNan::new(0.5 * d).unwrap()
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        others.iter().map(|&j| self.dist(idx, j)).collect()
+    }
+}
+
+impl<'a> ExtendableElementContainer for Vectors<'a> {
+    type InternalElement = Vector<'static>;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(&element)
+    }
+}
+
+impl<'a> Permutable for Vectors<'a> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        self.vectors.permute(permutation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helper;
+
+    fn random_vectors(dim: usize, num: usize) -> Vec<Vector<'static>> {
+        (0..num)
+            .map(|i| {
+                // vary the norms
+                let scale = 1.0 + (i % 7) as f32;
+                test_helper::random_floats().take(dim).map(|x| scale * x).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranks_by_inner_product() {
+        let vectors = random_vectors(25, 100);
+        let elements: Vectors = vectors.clone().into_iter().collect();
+
+        for _ in 0..10 {
+            let query: Vector = test_helper::random_vector(25);
+
+            for (i, v) in vectors.iter().enumerate() {
+                let r = math::dot_product_f32(v.as_slice(), query.as_slice());
+                let d = elements.dist_to_element(i, &query).into_inner();
+
+                assert!((elements.max_squared_norm() - d - r).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn augmented_vectors_have_equal_norm() {
+        let elements: Vectors = random_vectors(10, 50).into_iter().collect();
+
+        for i in 0..elements.len() {
+            let v = elements.vectors.get(i);
+            let squared_norm = math::dot_product_f32(v, v);
+            assert!((elements.max_squared_norm() - squared_norm).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn dist_between_same_vector() {
+        let elements: Vectors = random_vectors(10, 50).into_iter().collect();
+
+        for i in 0..elements.len() {
+            assert_eq!(0.0, elements.dist(i, i).into_inner());
+            assert_eq!(0.0, elements.get(i).dist(&elements.get(i)).into_inner());
+        }
+    }
+
+    #[test]
+    fn write_and_load() {
+        let elements: Vectors = random_vectors(10, 50).into_iter().collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&elements, &mut buffer).unwrap();
+        let loaded = Vectors::from_bytes(&buffer);
+
+        assert_eq!(elements.len(), loaded.len());
+        assert_eq!(elements.dim(), loaded.dim());
+        assert!((elements.max_squared_norm() - loaded.max_squared_norm()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn load_and_push() {
+        let mut elements = Vectors::with_max_norm(2.0);
+        elements.push(&vec![2.0f32, 0.0].into());
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&elements, &mut buffer).unwrap();
+        let mut loaded = Vectors::from_bytes(&buffer);
+
+        assert_eq!(4.0, loaded.max_squared_norm());
+
+        loaded.push(&vec![1.0f32, 1.0].into());
+        assert_eq!(2, loaded.len());
+        assert!((loaded.dist(0, 1).into_inner() - (4.0 - 2.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_too_large_vector() {
+        let mut elements = Vectors::with_max_norm(1.0);
+
+        elements.push(&vec![1.0f32, 1.0].into());
+    }
+}
@@ -0,0 +1,331 @@
+/*!
+This module contains element types for sets (e.g. shingle sets of documents) represented by
+[MinHash](https://en.wikipedia.org/wiki/MinHash) signatures.
+
+A signature consists of `num_hashes` values, each being the minimum of a (seeded) hash function
+over the items in the set. The fraction of positions in which two signatures agree is an estimate
+of the [Jaccard similarity](https://en.wikipedia.org/wiki/Jaccard_index) of the sets, and the
+distance between two signatures is the estimated Jaccard distance, i.e., `1 - similarity`.
+
+The hash functions only depend on the position in the signature, so signatures created
+independently (with the same `num_hashes`) are comparable. Items are hashed using
+`fxhash::FxHasher64`, which is deterministic, so signatures computed by different programs or Rust
+releases (on platforms with the same endianness) are comparable as long as the `Hash`
+implementation of the items is unchanged.
+
+# Example
+
+```
+use granne::{minhash, Dist};
+
+let x = minhash::Vector::from_set(&["a", "b", "c", "d"], 256);
+let y = minhash::Vector::from_set(&["b", "c", "d", "e"], 256);
+
+// the Jaccard distance is 1 - 3/5
+assert!((0.4 - x.dist(&y).into_inner()).abs() < 0.15);
+assert_eq!(0.0, x.dist(&x).into_inner());
+```
+*/
+
+use super::{Dist, ElementContainer, ExtendableElementContainer, Permutable};
+use crate::{io, slice_vector::FixedWidthSliceVector};
+
+use fxhash::FxHasher64;
+use ordered_float::NotNan;
+
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+use std::io::{Result, Write};
+use std::iter::FromIterator;
+
+/// A MinHash signature.
+#[derive(Clone)]
+pub struct Vector<'a>(pub Cow<'a, [u32]>);
+
+impl<'a> Vector<'a> {
+    /// Returns the number of hash values in this signature.
+    pub fn len(self: &Self) -> usize {
+        self.0.len()
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vector<'static> {
+        Vector(self.0.into_owned().into())
+    }
+
+    /// Converts this signature into a `Vec`.
+    pub fn into_vec(self: Self) -> Vec<u32> {
+        self.0.into_owned()
+    }
+
+    /// Returns a reference to the underlying slice.
+    pub fn as_slice(self: &Self) -> &[u32] {
+        &self.0[..]
+    }
+
+    /// Returns the estimated Jaccard similarity between the sets represented by `self` and
+    /// `other`.
+    pub fn jaccard_similarity(self: &Self, other: &Vector<'_>) -> f32 {
+        jaccard_similarity(&self.0, &other.0)
+    }
+}
+
+impl Vector<'static> {
+    /// Computes the MinHash signature with `num_hashes` hash values of the set `set`.
+    ///
+    /// The signature of an empty set consists of `u32::MAX` only.
+    pub fn from_set<T: Hash, I: IntoIterator<Item = T>>(set: I, num_hashes: usize) -> Self {
+        let mut signature = vec![std::u32::MAX; num_hashes];
+
+        for item in set {
+            let mut hasher = FxHasher64::default();
+            item.hash(&mut hasher);
+            let h = hasher.finish();
+
+            for (i, s) in signature.iter_mut().enumerate() {
+                *s = std::cmp::min(*s, seeded_hash(h, i as u64));
+            }
+        }
+
+        Self(Cow::from(signature))
+    }
+}
+
+impl From<Vec<u32>> for Vector<'static> {
+    /// Creates a `Vector` from a precomputed signature.
+    fn from(v: Vec<u32>) -> Self {
+        Self(Cow::from(v))
+    }
+}
+
+impl<'a, 'b> Dist<Vector<'b>> for Vector<'a> {
+    fn dist(self: &Self, other: &Vector<'b>) -> NotNan<f32> {
+        jaccard_dist(&self.0, &other.0)
+    }
+}
+
+/// Hashes `h` using the hash function with index `seed` (splitmix64 finalizer).
+#[inline(always)]
+fn seeded_hash(h: u64, seed: u64) -> u32 {
+    let mut z = h ^ seed.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    z as u32
+}
+
+#[inline(always)]
+fn jaccard_similarity(x: &[u32], y: &[u32]) -> f32 {
+    if x.is_empty() {
+        return 0.0;
+    }
+
+    let num_equal = x.iter().zip(y).filter(|(xi, yi)| xi == yi).count();
+
+    num_equal as f32 / x.len() as f32
+}
+
+#[inline(always)]
+fn jaccard_dist(x: &[u32], y: &[u32]) -> NotNan<f32> {
+    NotNan::new(1.0 - jaccard_similarity(x, y)).unwrap()
+}
+
+/// A collection of MinHash signatures.
+#[derive(Clone)]
+pub struct Vectors<'a>(FixedWidthSliceVector<'a, u32>);
+
+impl<'a> Vectors<'a> {
+    /// Creates a new collection of signatures. The number of hash values will be set once the
+    /// first signature is pushed into the collection.
+    pub fn new() -> Self {
+        Self(FixedWidthSliceVector::new())
+    }
+
+    /// Loads a collection of signatures from a `u8` buffer.
+    /// `buffer` needs to contain data in a compatible format (e.g. written with
+    /// `Vectors::write`).
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        Self(FixedWidthSliceVector::from_bytes(buffer))
+    }
+
+    /// Loads a memory-mapped a collection of signatures from a file.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the file is not modified
+    /// while being memory-mapped.
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        Ok(Self(FixedWidthSliceVector::from_file(file)?))
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> Vectors<'a> {
+        Self(self.0.borrow())
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vectors<'static> {
+        Vectors(self.0.into_owned())
+    }
+
+    /// Extends `Vectors` with the signatures from `vec`.
+    pub fn extend(self: &mut Self, vec: Vectors<'_>) {
+        self.0.extend_from_slice_vector(&vec.0)
+    }
+
+    /// Pushes `vec` onto the collection
+    pub fn push(self: &mut Self, vec: &Vector<'_>) {
+        self.0.push(&vec.0[..]);
+    }
+
+    /// Returns the number of signatures in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the number of hash values of each signature in this collection.
+    pub fn num_hashes(self: &Self) -> usize {
+        self.0.width()
+    }
+
+    /// Returns a reference to the signature at `index`.
+    pub fn get_element(self: &'a Self, index: usize) -> Vector<'a> {
+        Vector(Cow::Borrowed(self.0.get(index)))
+    }
+
+    /// Returns a reference to the underlying slice.
+    pub fn as_slice(self: &Self) -> &[u32] {
+        self.0.as_slice()
+    }
+}
+
+impl<'a> Default for Vectors<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FromIterator<Vector<'a>> for Vectors<'static> {
+    fn from_iter<I: IntoIterator<Item = Vector<'a>>>(iter: I) -> Self {
+        let mut vecs = Vectors::new();
+        for vec in iter {
+            vecs.push(&vec);
+        }
+
+        vecs
+    }
+}
+
+impl<'a> io::Writeable for Vectors<'a> {
+    /// Writes `Vectors` to a `buffer`.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        self.0.write(buffer)
+    }
+}
+
+impl<'a> ElementContainer for Vectors<'a> {
+    type Element = Vector<'static>;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        self.get_element(idx).into_owned()
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        jaccard_dist(self.0.get(idx), &element.0)
+    }
+
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        jaccard_dist(self.0.get(i), self.0.get(j))
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        let element = self.0.get(idx);
+        others.iter().map(|&j| jaccard_dist(element, self.0.get(j))).collect()
+    }
+}
+
+impl<'a> ExtendableElementContainer for Vectors<'a> {
+    type InternalElement = Vector<'static>;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(&element)
+    }
+}
+
+impl<'a> Permutable for Vectors<'a> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        self.0.permute(permutation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn exact_jaccard_similarity(x: &HashSet<u32>, y: &HashSet<u32>) -> f32 {
+        x.intersection(y).count() as f32 / x.union(y).count() as f32
+    }
+
+    #[test]
+    fn estimates_jaccard_similarity() {
+        for overlap in (0..=100).step_by(10) {
+            let x: HashSet<u32> = (0..100).collect();
+            let y: HashSet<u32> = ((100 - overlap)..(200 - overlap)).collect();
+
+            let sx = Vector::from_set(&x, 512);
+            let sy = Vector::from_set(&y, 512);
+
+            let expected = exact_jaccard_similarity(&x, &y);
+            assert!((expected - sx.jaccard_similarity(&sy)).abs() < 0.1);
+            assert!((1.0 - expected - sx.dist(&sy).into_inner()).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn order_and_duplicates_do_not_matter() {
+        let x = Vector::from_set(vec![1, 2, 3, 3, 4], 64);
+        let y = Vector::from_set(vec![4, 3, 2, 1], 64);
+
+        assert_eq!(x.as_slice(), y.as_slice());
+        assert_eq!(0.0, x.dist(&y).into_inner());
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn signatures_are_stable() {
+        let x = Vector::from_set(&["a", "b", "c"], 4);
+
+        assert_eq!(&[586652984u32, 1581164909, 1323546769, 2327962263], x.as_slice());
+    }
+
+    #[test]
+    fn empty_set() {
+        let x = Vector::from_set(Vec::<u32>::new(), 16);
+
+        assert_eq!(&[std::u32::MAX; 16], x.as_slice());
+    }
+
+    #[test]
+    fn write_and_load() {
+        let vectors: Vectors = (0..50).map(|i| Vector::from_set(i..(i + 20), 32)).collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        let loaded = Vectors::from_bytes(&buffer);
+
+        assert_eq!(vectors.len(), loaded.len());
+        assert_eq!(32, loaded.num_hashes());
+        assert_eq!(vectors.as_slice(), loaded.as_slice());
+
+        for i in 0..vectors.len() {
+            assert_eq!(vectors.dist(0, i), loaded.dist(0, i));
+        }
+    }
+}
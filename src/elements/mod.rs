@@ -5,6 +5,15 @@ mod dense_vector;
 
 pub mod angular;
 pub mod angular_int;
+pub mod dense;
+pub mod euclidean;
+pub mod fn_elements;
+pub mod hamming;
+pub mod inner_product;
+pub mod minhash;
+pub mod multi_vector;
+pub mod pq;
+pub mod sparse;
 
 pub mod embeddings;
 
@@ -38,12 +47,42 @@ pub trait ElementContainer {
         others.iter().map(|&j| self.dist_to_element(j, &element)).collect()
     }
 
+    /// Does a batch computation of distances from `element` to all elements in `others`.
+    fn dists_to_element(self: &Self, element: &Self::Element, others: &[usize]) -> Vec<NotNan<f32>> {
+        others.iter().map(|&j| self.dist_to_element(j, element)).collect()
+    }
+
     /// Returns `true` if the container contains no elements.
     fn is_empty(self: &Self) -> bool {
         self.len() == 0
     }
 }
 
+/// A trait for `ElementContainer`s that can compute distances to queries of type `Query`.
+///
+/// `Query` does not need to be the same type as the elements in the container. This allows for
+/// asymmetric distance computations, e.g., searching using a full precision query among quantized
+/// elements. It is implemented for any `ElementContainer` with `Query = Self::Element`.
+pub trait QueryDist<Query>: ElementContainer {
+    /// Returns the distance between the element at `idx` and `query`.
+    fn dist_to_query(self: &Self, idx: usize, query: &Query) -> NotNan<f32>;
+
+    /// Does a batch computation of distances from `query` to all elements in `others`.
+    fn dists_to_query(self: &Self, query: &Query, others: &[usize]) -> Vec<NotNan<f32>> {
+        others.iter().map(|&j| self.dist_to_query(j, query)).collect()
+    }
+}
+
+impl<Elements: ElementContainer> QueryDist<Elements::Element> for Elements {
+    fn dist_to_query(self: &Self, idx: usize, query: &Self::Element) -> NotNan<f32> {
+        self.dist_to_element(idx, query)
+    }
+
+    fn dists_to_query(self: &Self, query: &Self::Element, others: &[usize]) -> Vec<NotNan<f32>> {
+        self.dists_to_element(query, others)
+    }
+}
+
 /// A trait for `ElementContainer`s that can be extended with more elements
 pub trait ExtendableElementContainer: ElementContainer {
     /// Internal representation of an element (can be the same as
@@ -139,4 +178,8 @@ impl<Elements: ElementContainer> ElementContainer for &Elements {
     fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
         Elements::dists(self, idx, others)
     }
+
+    fn dists_to_element(self: &Self, element: &Self::Element, others: &[usize]) -> Vec<NotNan<f32>> {
+        Elements::dists_to_element(self, element, others)
+    }
 }
@@ -0,0 +1,546 @@
+/*!
+This module contains element types for multi-vector (late-interaction) retrieval, e.g.
+[ColBERT](https://arxiv.org/abs/2004.12832), where each element is a bag of (normalized) token
+vectors.
+
+The similarity between a query `q` and a document `d` is MaxSim, i.e., the sum over the query
+tokens of the maximum dot product with any of the document tokens. The distance is
+`1 - MaxSim(q, d) / |q|`, where `|q|` is the number of query tokens, so it is zero for identical
+elements and at most two.
+
+MaxSim is not symmetric. When searching, the query is always the first argument. Distances between
+elements in a collection (which are only used when building an index) are symmetrized, i.e., the
+average of the distances in both directions.
+
+# Example
+
+```
+use granne::{multi_vector, Dist};
+
+let doc: multi_vector::Vector = vec![vec![1.0f32, 0.0], vec![0.0, 1.0]].into();
+let query: multi_vector::Vector = vec![vec![1.0f32, 0.0]].into();
+
+assert_eq!(2, doc.num_tokens());
+assert_eq!(1.0, query.max_sim(&doc));
+assert_eq!(0.0, query.dist(&doc).into_inner());
+```
+*/
+
+use super::{Dist, ElementContainer, ExtendableElementContainer, Permutable};
+use crate::{io, math, slice_vector::VariableWidthSliceVector, FiveByteInt};
+
+use ordered_float::NotNan;
+use std::cmp;
+
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::iter::FromIterator;
+
+// offsets into the token data, supporting up to 2^40 floats in total
+type ElementOffset = FiveByteInt;
+
+// the token dimension is written as a `u64` before the vectors, which keeps the data aligned
+const HEADER_LEN: usize = std::mem::size_of::<u64>();
+
+/// Computes MaxSim between the tokens in `query` and the tokens in `document`.
+fn max_sim(query: &[f32], document: &[f32], dim: usize) -> f32 {
+    if dim == 0 || document.is_empty() {
+        return 0.0;
+    }
+
+    query
+        .chunks_exact(dim)
+        .map(|q| {
+            document
+                .chunks_exact(dim)
+                .map(|d| math::dot_product_f32(q, d))
+                .fold(std::f32::NEG_INFINITY, f32::max)
+        })
+        .sum()
+}
+
+/// Computes the (asymmetric) distance from `query` to `document`.
+#[inline(always)]
+fn dist(query: &[f32], document: &[f32], dim: usize) -> NotNan<f32> {
+    if dim == 0 || query.is_empty() {
+        return NotNan::new(1.0).unwrap();
+    }
+
+    let num_tokens = (query.len() / dim) as f32;
+    let d = NotNan::new(1.0f32 - max_sim(query, document, dim) / num_tokens).unwrap();
+
+    cmp::max(0.0f32.into(), d)
+}
+
+/// Computes the symmetrized distance between `x` and `y`.
+#[inline(always)]
+fn symmetric_dist(x: &[f32], y: &[f32], dim: usize) -> NotNan<f32> {
+    (dist(x, y, dim) + dist(y, x, dim)) / 2.0
+}
+
+/// A multi-vector element, i.e., a group of normalized token vectors of the same dimension.
+#[derive(Clone)]
+pub struct Vector<'a> {
+    data: Cow<'a, [f32]>,
+    dim: usize,
+}
+
+impl<'a> Vector<'a> {
+    /// Returns the number of tokens in this `Vector`.
+    pub fn num_tokens(self: &Self) -> usize {
+        self.data.len().checked_div(self.dim).unwrap_or(0)
+    }
+
+    /// Returns the dimension of the tokens in this `Vector`.
+    pub fn dim(self: &Self) -> usize {
+        self.dim
+    }
+
+    /// Returns a reference to the token at `index`.
+    pub fn token(self: &Self, index: usize) -> &[f32] {
+        &self.data[index * self.dim..(index + 1) * self.dim]
+    }
+
+    /// Returns a reference to the (concatenated) tokens.
+    pub fn as_slice(self: &Self) -> &[f32] {
+        &self.data[..]
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vector<'static> {
+        Vector {
+            data: self.data.into_owned().into(),
+            dim: self.dim,
+        }
+    }
+
+    /// Returns MaxSim between `self` (as query) and `other`.
+    pub fn max_sim(self: &Self, other: &Vector<'_>) -> f32 {
+        assert!(self.num_tokens() == 0 || other.num_tokens() == 0 || self.dim == other.dim);
+
+        max_sim(&self.data, &other.data, self.dim)
+    }
+}
+
+impl Vector<'static> {
+    /// Creates a `Vector` from the concatenated tokens in `data`, each of dimension `dim`. The
+    /// tokens are normalized.
+    pub fn from_flat(mut data: Vec<f32>, dim: usize) -> Self {
+        assert!(dim > 0 || data.is_empty());
+
+        if dim > 0 {
+            assert_eq!(0, data.len() % dim);
+
+            for token in data.chunks_exact_mut(dim) {
+                if math::dot_product_f32(token, token) > 0.0 {
+                    math::normalize_f32(token);
+                }
+            }
+        }
+
+        Self { data: data.into(), dim }
+    }
+}
+
+impl From<Vec<Vec<f32>>> for Vector<'static> {
+    /// Creates a `Vector` from a list of tokens. The tokens are normalized.
+    fn from(tokens: Vec<Vec<f32>>) -> Self {
+        let dim = tokens.first().map_or(0, Vec::len);
+        assert!(
+            tokens.iter().all(|t| t.len() == dim),
+            "All tokens need to have the same dimension"
+        );
+
+        Self::from_flat(tokens.concat(), dim)
+    }
+}
+
+impl<'a, 'b> Dist<Vector<'b>> for Vector<'a> {
+    /// Returns the distance from `self` (as query) to `other`.
+    fn dist(self: &Self, other: &Vector<'b>) -> NotNan<f32> {
+        // only empty vectors (created from an empty list of tokens) may have dimension 0
+        if self.dim > 0 && other.dim > 0 {
+            assert_eq!(self.dim, other.dim);
+        }
+
+        dist(&self.data, &other.data, cmp::max(self.dim, other.dim))
+    }
+}
+
+/// A collection of multi-vector elements.
+#[derive(Clone)]
+pub struct Vectors<'a> {
+    vectors: VariableWidthSliceVector<'a, f32, ElementOffset>,
+    dim: usize,
+}
+
+impl<'a> Vectors<'a> {
+    /// Creates a new collection of vectors. The dimension will be set once the first non-empty
+    /// vector is pushed into the collection.
+    pub fn new() -> Self {
+        Self {
+            vectors: VariableWidthSliceVector::new(),
+            dim: 0,
+        }
+    }
+
+    /// Loads a collection of vectors from a `u8` buffer. `buffer` needs to contain data in a
+    /// compatible format (e.g. written with `Vectors::write`).
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        Self::load(
+            VariableWidthSliceVector::from_bytes(&buffer[HEADER_LEN..]),
+            read_dim(buffer),
+        )
+    }
+
+    /// Loads a memory-mapped a collection of vectors from a file.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the file is not modified
+    /// while being memory-mapped.
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        let mmap = memmap::Mmap::map(file)?;
+        let dim = read_dim(&mmap);
+
+        Ok(Self::load(
+            VariableWidthSliceVector::from_mmap_at(mmap, HEADER_LEN)?,
+            dim,
+        ))
+    }
+
+    /// Checks the token dimension read from the header against the total number of floats.
+    fn load(vectors: VariableWidthSliceVector<'a, f32, ElementOffset>, dim: usize) -> Self {
+        let num_values = vectors.num_values();
+        assert!(
+            num_values == 0 || (dim > 0 && num_values % dim == 0),
+            "The vectors are not compatible with the token dimension {}",
+            dim
+        );
+
+        Self { vectors, dim }
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> Vectors<'a> {
+        Self {
+            vectors: self.vectors.borrow(),
+            dim: self.dim,
+        }
+    }
+
+    /// Extends `Vectors` with the elements from `vec`.
+    pub fn extend(self: &mut Self, vec: Vectors<'_>) {
+        if self.dim == 0 {
+            self.dim = vec.dim;
+        }
+        assert!(vec.is_empty() || vec.dim == 0 || vec.dim == self.dim);
+
+        self.vectors.extend_from_slice_vector(&vec.vectors)
+    }
+
+    /// Pushes `vec` onto the collection
+    pub fn push(self: &mut Self, vec: &Vector<'_>) {
+        if vec.num_tokens() > 0 {
+            if self.dim == 0 {
+                self.dim = vec.dim;
+            }
+            assert_eq!(self.dim, vec.dim);
+        }
+
+        self.vectors.push(&vec.data[..]);
+    }
+
+    /// Returns the number of vectors in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns `true` if the collection contains no vectors.
+    pub fn is_empty(self: &Self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Returns the dimension of the tokens in this collection.
+    pub fn dim(self: &Self) -> usize {
+        self.dim
+    }
+
+    /// Returns a reference to the vector at `index`.
+    pub fn get_element(self: &'a Self, index: usize) -> Vector<'a> {
+        Vector {
+            data: Cow::Borrowed(self.vectors.get(index)),
+            dim: self.dim,
+        }
+    }
+}
+
+impl<'a> Default for Vectors<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FromIterator<Vector<'a>> for Vectors<'static> {
+    fn from_iter<I: IntoIterator<Item = Vector<'a>>>(iter: I) -> Self {
+        let mut vecs = Vectors::new();
+        for vec in iter {
+            vecs.push(&vec);
+        }
+
+        vecs
+    }
+}
+
+/// Reads the token dimension from the header in `buffer`.
+fn read_dim(buffer: &[u8]) -> usize {
+    let mut dim = [0x0; HEADER_LEN];
+    dim.copy_from_slice(&buffer[..HEADER_LEN]);
+
+    u64::from_le_bytes(dim) as usize
+}
+
+impl<'a> io::Writeable for Vectors<'a> {
+    /// Writes `Vectors` (including the token dimension) to a `buffer`.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        buffer.write_all(&(self.dim as u64).to_le_bytes())?;
+
+        self.vectors.write(buffer).map(|len| HEADER_LEN + len)
+    }
+}
+
+impl<'a> ElementContainer for Vectors<'a> {
+    type Element = Vector<'static>;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        Vector {
+            data: Cow::Owned(self.vectors.get(idx).to_vec()),
+            dim: self.dim,
+        }
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        // only empty vectors (created from an empty list of tokens) may have dimension 0
+        if element.dim > 0 && self.dim > 0 {
+            assert_eq!(self.dim, element.dim);
+        }
+
+        dist(&element.data, self.vectors.get(idx), cmp::max(self.dim, element.dim))
+    }
+
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        symmetric_dist(self.vectors.get(i), self.vectors.get(j), self.dim)
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        let element = self.vectors.get(idx);
+        others
+            .iter()
+            .map(|&j| symmetric_dist(element, self.vectors.get(j), self.dim))
+            .collect()
+    }
+}
+
+impl<'a> ExtendableElementContainer for Vectors<'a> {
+    type InternalElement = Self::Element;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(&element)
+    }
+}
+
+impl<'a> Permutable for Vectors<'a> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        assert_eq!(self.len(), permutation.len());
+
+        let mut vectors = VariableWidthSliceVector::new();
+        for &i in permutation {
+            vectors.push(self.vectors.get(i));
+        }
+
+        self.vectors = vectors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn random_vector(dim: usize, num_tokens: usize) -> Vector<'static> {
+        let mut rng = rand::thread_rng();
+        let data: Vec<f32> = (0..dim * num_tokens).map(|_| rng.gen::<f32>() - 0.5).collect();
+
+        Vector::from_flat(data, dim)
+    }
+
+    fn brute_force_max_sim(query: &Vector, document: &Vector) -> f32 {
+        (0..query.num_tokens())
+            .map(|i| {
+                (0..document.num_tokens())
+                    .map(|j| {
+                        query
+                            .token(i)
+                            .iter()
+                            .zip(document.token(j))
+                            .map(|(x, y)| x * y)
+                            .sum::<f32>()
+                    })
+                    .fold(std::f32::NEG_INFINITY, f32::max)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn max_sim_same_as_brute_force() {
+        for _ in 0..50 {
+            let query = random_vector(16, 4);
+            let document = random_vector(16, 9);
+
+            assert!((brute_force_max_sim(&query, &document) - query.max_sim(&document)).abs() < 1e-4);
+
+            let expected = 1.0 - query.max_sim(&document) / 4.0;
+            assert!((expected - query.dist(&document).into_inner()).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn dist_to_self_is_zero() {
+        let vectors: Vectors = (1..20).map(|n| random_vector(8, n)).collect();
+
+        for i in 0..vectors.len() {
+            assert!(vectors.dist(i, i).into_inner() < 1e-5);
+            assert!(vectors.dist_to_element(i, &vectors.get(i)).into_inner() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn element_dists_are_symmetric() {
+        let vectors: Vectors = (1..20).map(|n| random_vector(8, n)).collect();
+
+        for i in 0..vectors.len() {
+            for j in 0..vectors.len() {
+                assert_eq!(vectors.dist(i, j), vectors.dist(j, i));
+            }
+        }
+    }
+
+    #[test]
+    fn empty_vectors() {
+        let mut vectors = Vectors::new();
+        vectors.push(&Vector::from(Vec::<Vec<f32>>::new()));
+        vectors.push(&random_vector(4, 3));
+
+        assert_eq!(4, vectors.dim());
+        assert_eq!(0, vectors.get_element(0).num_tokens());
+        assert_eq!(1.0, vectors.dist(0, 1).into_inner());
+        assert_eq!(1.0, vectors.dist_to_element(1, &vectors.get(0)).into_inner());
+    }
+
+    #[test]
+    fn write_and_load() {
+        let vectors: Vectors = (1..30).map(|n| random_vector(8, n % 5 + 1)).collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        let loaded = Vectors::from_bytes(&buffer);
+
+        assert_eq!(vectors.dim(), loaded.dim());
+        assert_eq!(vectors.len(), loaded.len());
+        for i in 0..vectors.len() {
+            assert_eq!(vectors.get_element(i).as_slice(), loaded.get_element(i).as_slice());
+            assert_eq!(vectors.dist(0, i), loaded.dist(0, i));
+        }
+    }
+
+    #[test]
+    fn offsets_are_written_as_five_bytes() {
+        let vectors: Vectors = (1..4).map(|n| random_vector(4, n)).collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+
+        // header, number of vectors, 4 offsets (no padding needed) and 6 tokens of dimension 4
+        assert_eq!(8 + 8 + 4 * 5 + 6 * 4 * 4, buffer.len());
+
+        let offsets: Vec<u64> = buffer[16..36]
+            .chunks_exact(5)
+            .map(|bytes| {
+                let mut offset = [0x0; 8];
+                offset[..5].copy_from_slice(bytes);
+                u64::from_le_bytes(offset)
+            })
+            .collect();
+        assert_eq!(vec![0, 4, 12, 24], offsets);
+
+        let loaded = Vectors::from_bytes(&buffer);
+        assert_eq!(vectors.get_element(2).as_slice(), loaded.get_element(2).as_slice());
+
+        // 3 offsets are padded with one byte to keep the tokens aligned
+        let vectors: Vectors = (1..3).map(|n| random_vector(4, n)).collect();
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        assert_eq!(8 + 8 + 3 * 5 + 1 + 3 * 4 * 4, buffer.len());
+        assert_eq!(0, (buffer.len() - 3 * 4 * 4) % std::mem::align_of::<f32>());
+
+        let loaded = Vectors::from_bytes(&buffer);
+        assert_eq!(vectors.get_element(1).as_slice(), loaded.get_element(1).as_slice());
+    }
+
+    #[test]
+    fn load_file() {
+        let vectors: Vectors = (1..10).map(|n| random_vector(4, n)).collect();
+
+        let mut file = tempfile::tempfile().unwrap();
+        io::Writeable::write(&vectors, &mut file).unwrap();
+        let loaded = unsafe { Vectors::from_file(&file).unwrap() };
+
+        assert_eq!(4, loaded.dim());
+        for i in 0..vectors.len() {
+            assert_eq!(vectors.get_element(i).as_slice(), loaded.get_element(i).as_slice());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn load_with_wrong_dim() {
+        let vectors: Vectors = (1..10).map(|n| random_vector(4, n)).collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        buffer[..HEADER_LEN].copy_from_slice(&7u64.to_le_bytes());
+
+        Vectors::from_bytes(&buffer);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dist_with_different_dims() {
+        random_vector(4, 2).dist(&random_vector(8, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn dist_to_element_with_different_dims() {
+        let vectors: Vectors = (1..5).map(|n| random_vector(4, n)).collect();
+
+        vectors.dist_to_element(0, &random_vector(8, 1));
+    }
+
+    #[test]
+    fn permute() {
+        let mut vectors: Vectors = (1..5).map(|n| random_vector(4, n)).collect();
+        let original = vectors.clone();
+
+        vectors.permute(&[2, 0, 3, 1]);
+
+        for (i, &j) in [2, 0, 3, 1].iter().enumerate() {
+            assert_eq!(original.get_element(j).as_slice(), vectors.get_element(i).as_slice());
+        }
+    }
+}
@@ -0,0 +1,562 @@
+/*!
+This module contains element types for product-quantized angular vectors.
+
+Each vector is split into `num_subspaces` subvectors of equal dimension. Each subvector is
+replaced by the id of its closest centroid among `NUM_CENTROIDS` (256) centroids trained for that
+subspace, i.e., each vector is stored as `num_subspaces` bytes (its code).
+
+Distances are computed asymmetrically: a query vector is kept in full precision and a lookup
+table with the inner products between each query subvector and all centroids is computed once per
+query (see [`Query`](struct.Query.html)). The distance to a code is then computed using
+`num_subspaces` table lookups. Distances between two codes (e.g. when building an index) use
+the inner products between all pairs of centroids within each subspace, which are precomputed
+when the codebook is created (`NUM_CENTROIDS * NUM_CENTROIDS` floats per subspace). For the same
+reason, the elements returned by `ElementContainer::get` only contain their code and no lookup
+table is computed for them.
+
+The centroids ([`Codebook`](struct.Codebook.html)) and the codes are stored in separate files,
+both of which can be memory-mapped.
+
+# Example
+
+```
+use granne::{angular, pq, ElementContainer};
+
+// a sample of vectors with dimension 32
+# let vectors: angular::Vectors = granne::test_helper::random_vectors(32, 1000);
+
+// train a codebook with 8 subspaces, i.e., 8 bytes per vector, using 10 iterations of k-means
+let codebook = pq::Codebook::train(&vectors, 8, 10);
+
+let mut elements = pq::Vectors::new(codebook);
+for i in 0..vectors.len() {
+    elements.push(&vectors.get_element(i));
+}
+
+let query = elements.create_query(&vectors.get_element(0));
+assert!(elements.dist_to_element(0, &query).into_inner() < 0.05);
+```
+*/
+
+use super::{angular, ElementContainer, ExtendableElementContainer, Permutable};
+use crate::{io, math, slice_vector::FixedWidthSliceVector};
+
+use ordered_float::NotNan;
+use rand::Rng;
+use rayon::prelude::*;
+
+use std::cmp;
+use std::io::{Result, Write};
+
+/// The number of centroids per subspace. Each subvector is encoded using one byte.
+pub const NUM_CENTROIDS: usize = 256;
+
+/// The centroids used for quantizing vectors.
+///
+/// The centroids for subspace `m` are stored at offsets `m * NUM_CENTROIDS..(m + 1) *
+/// NUM_CENTROIDS`.
+#[derive(Clone)]
+pub struct Codebook<'a> {
+    centroids: FixedWidthSliceVector<'a, f32>,
+    squared_norms: Vec<f32>,
+    // the inner products between centroid `k` and all centroids in the same subspace `m` are
+    // stored at index `m * NUM_CENTROIDS + k`
+    inner_products: FixedWidthSliceVector<'a, f32>,
+}
+
+impl<'a> Codebook<'a> {
+    /// Trains a codebook with `num_subspaces` subspaces on `sample`, using `num_iterations`
+    /// iterations of k-means per subspace.
+    ///
+    /// The dimension of the vectors in `sample` must be divisible by `num_subspaces`.
+    pub fn train(sample: &angular::Vectors, num_subspaces: usize, num_iterations: usize) -> Codebook<'static> {
+        assert!(sample.len() > 0, "Cannot train a codebook on an empty sample.");
+        assert!(num_subspaces > 0 && sample.dim() % num_subspaces == 0);
+
+        let subspace_dim = sample.dim() / num_subspaces;
+
+        let centroids: Vec<Vec<f32>> = (0..num_subspaces)
+            .into_par_iter()
+            .map(|m| {
+                let range = m * subspace_dim..(m + 1) * subspace_dim;
+                let subvectors: Vec<&[f32]> = sample
+                    .as_slice()
+                    .chunks_exact(sample.dim())
+                    .map(|v| &v[range.clone()])
+                    .collect();
+
+                kmeans(&subvectors, subspace_dim, num_iterations)
+            })
+            .collect();
+
+        Codebook::from_slice_vector(FixedWidthSliceVector::with_data(centroids.concat(), subspace_dim))
+    }
+
+    /// Loads a codebook from a `u8` buffer.
+    /// `buffer` needs to contain data in a compatible format (e.g. written with
+    /// `Codebook::write`).
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        Self::from_slice_vector(FixedWidthSliceVector::from_bytes(buffer))
+    }
+
+    /// Loads a memory-mapped codebook from a file.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the file is not modified
+    /// while being memory-mapped.
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        Ok(Self::from_slice_vector(FixedWidthSliceVector::from_file(file)?))
+    }
+
+    fn from_slice_vector(centroids: FixedWidthSliceVector<'a, f32>) -> Self {
+        assert_eq!(0, centroids.len() % NUM_CENTROIDS);
+
+        let squared_norms = centroids.iter().map(|c| math::dot_product_f32(c, c)).collect();
+
+        let inner_products: Vec<f32> = centroids
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(i, centroid)| {
+                let offset = i - i % NUM_CENTROIDS;
+                let centroids = &centroids;
+                (0..NUM_CENTROIDS).map(move |k| math::dot_product_f32(centroid, centroids.get(offset + k)))
+            })
+            .collect();
+
+        Self {
+            centroids,
+            squared_norms,
+            inner_products: FixedWidthSliceVector::with_data(inner_products, NUM_CENTROIDS),
+        }
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> Codebook<'a> {
+        Self {
+            centroids: self.centroids.borrow(),
+            squared_norms: self.squared_norms.clone(),
+            inner_products: self.inner_products.borrow(),
+        }
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Codebook<'static> {
+        Codebook {
+            centroids: self.centroids.into_owned(),
+            squared_norms: self.squared_norms,
+            inner_products: self.inner_products.into_owned(),
+        }
+    }
+
+    /// Returns the number of subspaces, i.e., the number of bytes in each code.
+    pub fn num_subspaces(self: &Self) -> usize {
+        self.centroids.len() / NUM_CENTROIDS
+    }
+
+    /// Returns the dimension of the vectors.
+    pub fn dim(self: &Self) -> usize {
+        self.num_subspaces() * self.centroids.width()
+    }
+
+    /// Encodes `vector` by replacing each subvector with its closest centroid.
+    pub fn encode(self: &Self, vector: &[f32]) -> Vec<u8> {
+        assert_eq!(self.dim(), vector.len());
+
+        vector
+            .chunks_exact(self.centroids.width())
+            .enumerate()
+            .map(|(m, subvector)| {
+                let offset = m * NUM_CENTROIDS;
+                closest_centroid(subvector, (0..NUM_CENTROIDS).map(|k| self.centroids.get(offset + k))) as u8
+            })
+            .collect()
+    }
+
+    /// Decodes `code` into a (non-normalized) vector.
+    pub fn decode(self: &Self, code: &[u8]) -> Vec<f32> {
+        let mut vector = Vec::with_capacity(self.dim());
+        for (m, &k) in code.iter().enumerate() {
+            vector.extend_from_slice(self.centroids.get(m * NUM_CENTROIDS + k as usize));
+        }
+
+        vector
+    }
+
+    /// Computes the distance lookup table for `vector`.
+    pub fn create_query(self: &Self, vector: &angular::Vector) -> Query {
+        assert_eq!(self.dim(), vector.len());
+
+        let table = vector
+            .0
+            .chunks_exact(self.centroids.width())
+            .enumerate()
+            .flat_map(|(m, subvector)| {
+                let offset = m * NUM_CENTROIDS;
+                (0..NUM_CENTROIDS).map(move |k| math::dot_product_f32(subvector, self.centroids.get(offset + k)))
+            })
+            .collect();
+
+        Query(QueryRepr::Table(table))
+    }
+
+    fn dist(self: &Self, code: &[u8], query: &Query) -> NotNan<f32> {
+        let table = match &query.0 {
+            QueryRepr::Table(table) => table,
+            QueryRepr::Code(other) => return self.code_dist(code, other),
+        };
+
+        let mut r = 0.0f32;
+        let mut squared_norm = 0.0f32;
+        for (m, &k) in code.iter().enumerate() {
+            let idx = m * NUM_CENTROIDS + k as usize;
+            r += table[idx];
+            squared_norm += self.squared_norms[idx];
+        }
+
+        let r = NotNan::new(r / squared_norm.sqrt()).unwrap_or_else(|_| NotNan::new(0.0).unwrap());
+        let d = NotNan::new(1.0f32).unwrap() - r;
+
+        cmp::max(NotNan::new(0.0f32).unwrap(), d)
+    }
+
+    /// Computes the distance between two codes using the precomputed inner products between the
+    /// centroids.
+    fn code_dist(self: &Self, a: &[u8], b: &[u8]) -> NotNan<f32> {
+        let mut r = 0.0f32;
+        let mut squared_norm_a = 0.0f32;
+        let mut squared_norm_b = 0.0f32;
+        for (m, (&ka, &kb)) in a.iter().zip(b).enumerate() {
+            let idx = m * NUM_CENTROIDS + ka as usize;
+            r += self.inner_products.get(idx)[kb as usize];
+            squared_norm_a += self.squared_norms[idx];
+            squared_norm_b += self.squared_norms[m * NUM_CENTROIDS + kb as usize];
+        }
+
+        let r = NotNan::new(r / (squared_norm_a * squared_norm_b).sqrt()).unwrap_or_else(|_| NotNan::new(0.0).unwrap());
+        let d = NotNan::new(1.0f32).unwrap() - r;
+
+        cmp::max(NotNan::new(0.0f32).unwrap(), d)
+    }
+}
+
+impl<'a> io::Writeable for Codebook<'a> {
+    /// Writes the `Codebook` to a `buffer`.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        self.centroids.write(buffer)
+    }
+}
+
+/// A query with a precomputed lookup table containing the inner products between the query and
+/// all centroids in a [`Codebook`](struct.Codebook.html) (see
+/// [`Vectors::create_query`](struct.Vectors.html#method.create_query)), or an element of
+/// [`Vectors`](struct.Vectors.html) represented by its code.
+#[derive(Clone)]
+pub struct Query(QueryRepr);
+
+#[derive(Clone)]
+enum QueryRepr {
+    Table(Vec<f32>),
+    Code(Vec<u8>),
+}
+
+/// A collection of product-quantized vectors.
+#[derive(Clone)]
+pub struct Vectors<'a> {
+    codebook: Codebook<'a>,
+    codes: FixedWidthSliceVector<'a, u8>,
+}
+
+impl<'a> Vectors<'a> {
+    /// Creates an empty collection of vectors quantized using `codebook`.
+    pub fn new(codebook: Codebook<'a>) -> Self {
+        let num_subspaces = codebook.num_subspaces();
+
+        Self {
+            codebook,
+            codes: FixedWidthSliceVector::with_width(num_subspaces),
+        }
+    }
+
+    /// Loads a collection of vectors from a `u8` buffer `codes`.
+    /// `codes` needs to contain data in a compatible format (e.g. written with
+    /// `Vectors::write`).
+    pub fn from_bytes(codebook: Codebook<'a>, codes: &'a [u8]) -> Self {
+        let codes = FixedWidthSliceVector::from_bytes(codes);
+        assert_eq!(codebook.num_subspaces(), codes.width());
+
+        Self { codebook, codes }
+    }
+
+    /// Loads a memory-mapped collection of vectors from `codebook` (and optionally `codes`).
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying files can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the files are not modified while
+    /// being memory-mapped.
+    pub unsafe fn from_files(codebook: &std::fs::File, codes: Option<&std::fs::File>) -> std::io::Result<Self> {
+        let codebook = Codebook::from_file(codebook)?;
+
+        if let Some(codes) = codes {
+            let codes = FixedWidthSliceVector::from_file(codes)?;
+            assert_eq!(codebook.num_subspaces(), codes.width());
+
+            Ok(Self { codebook, codes })
+        } else {
+            Ok(Self::new(codebook))
+        }
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> Vectors<'a> {
+        Self {
+            codebook: self.codebook.borrow(),
+            codes: self.codes.borrow(),
+        }
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vectors<'static> {
+        Vectors {
+            codebook: self.codebook.into_owned(),
+            codes: self.codes.into_owned(),
+        }
+    }
+
+    /// Quantizes `vector` and pushes it onto the collection.
+    pub fn push(self: &mut Self, vector: &angular::Vector) {
+        let code = self.codebook.encode(vector.as_slice());
+        self.codes.push(&code);
+    }
+
+    /// Returns the number of vectors in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.codes.len()
+    }
+
+    /// Returns the codebook used for quantizing the vectors in this collection.
+    pub fn codebook(self: &Self) -> &Codebook<'a> {
+        &self.codebook
+    }
+
+    /// Returns the code for the vector at `index`.
+    pub fn get_code(self: &Self, index: usize) -> &[u8] {
+        self.codes.get(index)
+    }
+
+    /// Creates a query for searching among the vectors in this collection.
+    pub fn create_query(self: &Self, vector: &angular::Vector) -> Query {
+        self.codebook.create_query(vector)
+    }
+
+    /// Writes the codebook to `buffer`.
+    pub fn write_codebook<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        io::Writeable::write(&self.codebook, buffer)
+    }
+}
+
+impl<'a> io::Writeable for Vectors<'a> {
+    /// Writes the codes to a `buffer`.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        self.codes.write(buffer)
+    }
+}
+
+impl<'a> ElementContainer for Vectors<'a> {
+    type Element = Query;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        Query(QueryRepr::Code(self.get_code(idx).to_vec()))
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        self.codebook.dist(self.get_code(idx), element)
+    }
+
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        self.codebook.code_dist(self.get_code(i), self.get_code(j))
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        let code = self.get_code(idx);
+
+        others
+            .iter()
+            .map(|&j| self.codebook.code_dist(code, self.get_code(j)))
+            .collect()
+    }
+}
+
+impl<'a> ExtendableElementContainer for Vectors<'a> {
+    type InternalElement = angular::Vector<'static>;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(&element)
+    }
+}
+
+impl<'a> Permutable for Vectors<'a> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        self.codes.permute(permutation);
+    }
+}
+
+fn closest_centroid<'b>(vector: &[f32], centroids: impl Iterator<Item = &'b [f32]>) -> usize {
+    centroids
+        .map(|c| NotNan::new(math::squared_euclidean_distance_f32(vector, c)).unwrap())
+        .enumerate()
+        .min_by_key(|&(_, d)| d)
+        .unwrap()
+        .0
+}
+
+/// Computes `NUM_CENTROIDS` centroids for `vectors` using k-means. Returns the centroids
+/// concatenated.
+fn kmeans(vectors: &[&[f32]], dim: usize, num_iterations: usize) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+
+    // initialize the centroids with randomly chosen vectors
+    let mut centroids: Vec<f32> = if vectors.len() >= NUM_CENTROIDS {
+        rand::seq::index::sample(&mut rng, vectors.len(), NUM_CENTROIDS)
+            .iter()
+            .flat_map(|i| vectors[i].iter().cloned())
+            .collect()
+    } else {
+        (0..NUM_CENTROIDS)
+            .flat_map(|_| vectors[rng.gen_range(0, vectors.len())].iter().cloned())
+            .collect()
+    };
+
+    for _ in 0..num_iterations {
+        let assignments: Vec<usize> = vectors
+            .par_iter()
+            .map(|v| closest_centroid(v, centroids.chunks_exact(dim)))
+            .collect();
+
+        let mut sums = vec![0.0f32; NUM_CENTROIDS * dim];
+        let mut counts = vec![0usize; NUM_CENTROIDS];
+
+        for (v, &k) in vectors.iter().zip(&assignments) {
+            math::sum_into_f32(&mut sums[k * dim..(k + 1) * dim], v);
+            counts[k] += 1;
+        }
+
+        // empty clusters keep their previous centroid
+        for (k, &count) in counts.iter().enumerate().filter(|&(_, &count)| count > 0) {
+            for (c, s) in centroids[k * dim..(k + 1) * dim]
+                .iter_mut()
+                .zip(&sums[k * dim..(k + 1) * dim])
+            {
+                *c = s / count as f32;
+            }
+        }
+    }
+
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helper, Dist};
+
+    fn train(dim: usize, num_subspaces: usize) -> (angular::Vectors<'static>, Vectors<'static>) {
+        let vectors: angular::Vectors = test_helper::random_vectors(dim, 2000);
+        let codebook = Codebook::train(&vectors, num_subspaces, 10);
+
+        let mut elements = Vectors::new(codebook);
+        for i in 0..vectors.len() {
+            elements.push(&vectors.get_element(i));
+        }
+
+        (vectors, elements)
+    }
+
+    #[test]
+    fn train_codebook() {
+        let (vectors, elements) = train(32, 8);
+
+        assert_eq!(8, elements.codebook().num_subspaces());
+        assert_eq!(32, elements.codebook().dim());
+        assert_eq!(vectors.len(), elements.len());
+        assert_eq!(8, elements.get_code(0).len());
+    }
+
+    #[test]
+    fn approximates_angular_dist() {
+        let (vectors, elements) = train(32, 16);
+
+        let mut total_error = 0.0f32;
+        for _ in 0..100 {
+            let query: angular::Vector = test_helper::random_vector(32);
+            let pq_query = elements.create_query(&query);
+
+            for i in 0..100 {
+                let expected = vectors.get_element(i).dist(&query).into_inner();
+                total_error += (expected - elements.dist_to_element(i, &pq_query).into_inner()).abs();
+            }
+        }
+
+        assert!(total_error / 10_000.0 < 0.1);
+    }
+
+    #[test]
+    fn dist_between_same_vector() {
+        let (_, elements) = train(32, 8);
+
+        for i in 0..100 {
+            assert!(elements.dist(i, i).into_inner() < 100.0 * std::f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn code_dist_equals_dist_to_element() {
+        let (_, elements) = train(32, 8);
+
+        for i in 0..50 {
+            let element = elements.get(i);
+            let others: Vec<usize> = (0..100).collect();
+
+            for (j, d) in others.iter().zip(elements.dists(i, &others)) {
+                assert_eq!(d, elements.dist_to_element(*j, &element));
+                assert_eq!(d, elements.dist(i, *j));
+            }
+
+            // a query created from the decoded vector uses a lookup table instead
+            let vector: angular::Vector = elements.codebook().decode(elements.get_code(i)).into();
+            let query = elements.create_query(&vector);
+            for &j in &others {
+                assert!((elements.dist_to_element(j, &query) - elements.dist(i, j)).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn write_and_load() {
+        let (_, elements) = train(32, 8);
+
+        let mut codebook = Vec::new();
+        elements.write_codebook(&mut codebook).unwrap();
+        let mut codes = Vec::new();
+        io::Writeable::write(&elements, &mut codes).unwrap();
+
+        let loaded = Vectors::from_bytes(Codebook::from_bytes(&codebook), &codes);
+
+        assert_eq!(elements.len(), loaded.len());
+        assert_eq!(elements.codebook().dim(), loaded.codebook().dim());
+        for i in 0..elements.len() {
+            assert_eq!(elements.get_code(i), loaded.get_code(i));
+        }
+
+        let query = elements.get(0);
+        for i in 0..elements.len() {
+            assert_eq!(elements.dist_to_element(i, &query), loaded.dist_to_element(i, &query));
+        }
+    }
+}
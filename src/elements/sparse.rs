@@ -0,0 +1,385 @@
+/*!
+This module contains element types for sparse vectors, e.g. TF-IDF or BM25 term-weight vectors.
+
+A sparse vector is stored as a list of [`Entry`](struct.Entry.html)s, i.e., `(index, weight)`
+pairs sorted by index. The distance between two vectors is computed from their dot product by
+merge-joining the two lists of indices, using either the [`Cosine`](../dense/struct.Cosine.html)
+(default, the vectors are normalized) or the [`DotProduct`](../dense/struct.DotProduct.html)
+metric.
+
+# Example
+
+```
+use granne::{sparse, Dist};
+
+let x: sparse::Vector = vec![(3, 1.0f32), (17, 1.0)].into();
+let y: sparse::Vector = vec![(17, 2.0f32), (2, 2.0)].into();
+
+assert!((0.5 - x.dist(&y).into_inner()).abs() < 1e-6);
+
+// entries are sorted by index
+assert_eq!(2, y.as_slice()[0].index);
+```
+*/
+
+use super::dense::{Cosine, DotProduct};
+use super::{Dist, ElementContainer, ExtendableElementContainer, Permutable};
+use crate::{io, slice_vector::VariableWidthSliceVector};
+
+use ordered_float::NotNan;
+use std::cmp;
+
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+// offsets into the entries of all vectors
+type ElementOffset = u64;
+
+/// A non-zero entry of a sparse vector.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    /// The index (dimension) of this entry.
+    pub index: u32,
+    /// The weight of this entry.
+    pub weight: f32,
+}
+
+/// A trait for metrics that can be used with sparse vectors, i.e., metrics that can be computed
+/// from the dot product of two vectors.
+pub trait SparseMetric: Copy + Default + Send + Sync + 'static {
+    /// Prepares the entries of a vector, e.g., by normalizing them.
+    fn prepare(_entries: &mut [Entry]) {}
+
+    /// Returns the distance given the dot product `r` of two vectors.
+    fn dist_from_dot_product(r: f32) -> NotNan<f32>;
+}
+
+impl SparseMetric for Cosine {
+    fn prepare(entries: &mut [Entry]) {
+        let norm = dot_product(entries, entries).sqrt();
+
+        if norm > 0.0 {
+            for entry in entries {
+                entry.weight /= norm;
+            }
+        }
+    }
+
+    fn dist_from_dot_product(r: f32) -> NotNan<f32> {
+        let d = NotNan::new(1.0f32 - r).unwrap();
+
+        cmp::max(0.0f32.into(), d)
+    }
+}
+
+impl SparseMetric for DotProduct {
+    fn dist_from_dot_product(r: f32) -> NotNan<f32> {
+        NotNan::new(1.0f32 - r).unwrap()
+    }
+}
+
+/// Computes the dot product of two sparse vectors by merge-joining their (sorted) indices.
+fn dot_product(x: &[Entry], y: &[Entry]) -> f32 {
+    let mut r = 0.0f32;
+    let (mut i, mut j) = (0, 0);
+
+    while i < x.len() && j < y.len() {
+        match x[i].index.cmp(&y[j].index) {
+            cmp::Ordering::Less => i += 1,
+            cmp::Ordering::Greater => j += 1,
+            cmp::Ordering::Equal => {
+                r += x[i].weight * y[j].weight;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    r
+}
+
+/// A sparse vector element.
+#[derive(Clone)]
+pub struct Vector<'a, M: SparseMetric = Cosine>(Cow<'a, [Entry]>, PhantomData<M>);
+
+impl<'a, M: SparseMetric> Vector<'a, M> {
+    /// Returns the number of non-zero entries in this `Vector`.
+    pub fn len(self: &Self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if this `Vector` has no non-zero entries.
+    pub fn is_empty(self: &Self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Clones the underlying data if not already owned.
+    pub fn into_owned(self: Self) -> Vector<'static, M> {
+        Vector(self.0.into_owned().into(), PhantomData)
+    }
+
+    /// Returns a reference to the entries of this `Vector` (sorted by index).
+    pub fn as_slice(self: &Self) -> &[Entry] {
+        &self.0[..]
+    }
+}
+
+impl<M: SparseMetric> From<Vec<(u32, f32)>> for Vector<'static, M> {
+    /// Creates a `Vector` from `(index, weight)` pairs. The pairs do not need to be sorted, the
+    /// weights of duplicate indices are summed and zero weights are removed.
+    fn from(mut v: Vec<(u32, f32)>) -> Self {
+        v.sort_by_key(|&(index, _)| index);
+
+        let mut entries: Vec<Entry> = Vec::with_capacity(v.len());
+        for (index, weight) in v {
+            match entries.last_mut() {
+                Some(last) if last.index == index => last.weight += weight,
+                _ => entries.push(Entry { index, weight }),
+            }
+        }
+        entries.retain(|entry| entry.weight != 0.0);
+
+        M::prepare(&mut entries);
+
+        Self(Cow::from(entries), PhantomData)
+    }
+}
+
+impl<M: SparseMetric> FromIterator<(u32, f32)> for Vector<'static, M> {
+    fn from_iter<I: IntoIterator<Item = (u32, f32)>>(iter: I) -> Self {
+        let v: Vec<(u32, f32)> = iter.into_iter().collect();
+        Self::from(v)
+    }
+}
+
+impl<'a, 'b, M: SparseMetric> Dist<Vector<'b, M>> for Vector<'a, M> {
+    fn dist(self: &Self, other: &Vector<'b, M>) -> NotNan<f32> {
+        M::dist_from_dot_product(dot_product(&self.0, &other.0))
+    }
+}
+
+/// A collection of sparse `Vector`s.
+#[derive(Clone)]
+pub struct Vectors<'a, M: SparseMetric = Cosine> {
+    vectors: VariableWidthSliceVector<'a, Entry, ElementOffset>,
+    metric: PhantomData<M>,
+}
+
+impl<'a, M: SparseMetric> Vectors<'a, M> {
+    /// Creates a new collection of sparse vectors.
+    pub fn new() -> Self {
+        Self::from_slice_vector(VariableWidthSliceVector::new())
+    }
+
+    /// Loads a collection of vectors from a `u8` buffer.
+    /// `buffer` needs to contain data in a compatible format (e.g. written with
+    /// `Vectors::write`).
+    pub fn from_bytes(buffer: &'a [u8]) -> Self {
+        Self::from_slice_vector(VariableWidthSliceVector::from_bytes(buffer))
+    }
+
+    /// Loads a memory-mapped a collection of vectors from a file.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying file can be modified, which would result in
+    /// undefined behavior. The caller needs to guarantee that the file is not modified
+    /// while being memory-mapped.
+    pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
+        Ok(Self::from_slice_vector(VariableWidthSliceVector::from_file(file)?))
+    }
+
+    fn from_slice_vector(vectors: VariableWidthSliceVector<'a, Entry, ElementOffset>) -> Self {
+        Self {
+            vectors,
+            metric: PhantomData,
+        }
+    }
+
+    /// Borrows the data.
+    pub fn borrow(self: &'a Self) -> Vectors<'a, M> {
+        Self::from_slice_vector(self.vectors.borrow())
+    }
+
+    /// Extends `Vectors` with the elements from `vec`.
+    pub fn extend(self: &mut Self, vec: Vectors<'_, M>) {
+        self.vectors.extend_from_slice_vector(&vec.vectors)
+    }
+
+    /// Pushes `vec` onto the collection
+    pub fn push(self: &mut Self, vec: &Vector<'_, M>) {
+        self.vectors.push(&vec.0[..]);
+    }
+
+    /// Returns the number of vectors in this collection.
+    pub fn len(self: &Self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Returns `true` if the collection contains no vectors.
+    pub fn is_empty(self: &Self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Returns a reference to the vector at `index`.
+    pub fn get_element(self: &'a Self, index: usize) -> Vector<'a, M> {
+        Vector(Cow::Borrowed(self.vectors.get(index)), PhantomData)
+    }
+}
+
+impl<'a, M: SparseMetric> Default for Vectors<'a, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, M: SparseMetric> FromIterator<Vector<'a, M>> for Vectors<'static, M> {
+    fn from_iter<I: IntoIterator<Item = Vector<'a, M>>>(iter: I) -> Self {
+        let mut vecs = Vectors::new();
+        for vec in iter {
+            vecs.push(&vec);
+        }
+
+        vecs
+    }
+}
+
+impl<'a, M: SparseMetric> io::Writeable for Vectors<'a, M> {
+    /// Writes `Vectors` to a `buffer`.
+    fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
+        self.vectors.write(buffer)
+    }
+}
+
+impl<'a, M: SparseMetric> ElementContainer for Vectors<'a, M> {
+    type Element = Vector<'static, M>;
+
+    fn get(self: &Self, idx: usize) -> Self::Element {
+        Vector(Cow::Owned(self.vectors.get(idx).to_vec()), PhantomData)
+    }
+
+    fn len(self: &Self) -> usize {
+        self.len()
+    }
+
+    fn dist_to_element(self: &Self, idx: usize, element: &Self::Element) -> NotNan<f32> {
+        M::dist_from_dot_product(dot_product(self.vectors.get(idx), &element.0))
+    }
+
+    fn dist(self: &Self, i: usize, j: usize) -> NotNan<f32> {
+        M::dist_from_dot_product(dot_product(self.vectors.get(i), self.vectors.get(j)))
+    }
+
+    fn dists(self: &Self, idx: usize, others: &[usize]) -> Vec<NotNan<f32>> {
+        let element = self.vectors.get(idx);
+        others
+            .iter()
+            .map(|&j| M::dist_from_dot_product(dot_product(element, self.vectors.get(j))))
+            .collect()
+    }
+}
+
+impl<'a, M: SparseMetric> ExtendableElementContainer for Vectors<'a, M> {
+    type InternalElement = Self::Element;
+
+    fn push(self: &mut Self, element: Self::InternalElement) {
+        self.push(&element)
+    }
+}
+
+impl<'a, M: SparseMetric> Permutable for Vectors<'a, M> {
+    fn permute(self: &mut Self, permutation: &[usize]) {
+        assert_eq!(self.len(), permutation.len());
+
+        let mut vectors = VariableWidthSliceVector::new();
+        for &i in permutation {
+            vectors.push(self.vectors.get(i));
+        }
+
+        self.vectors = vectors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    fn random_sparse_vector<M: SparseMetric>(dim: u32, num_entries: usize) -> Vector<'static, M> {
+        let mut rng = rand::thread_rng();
+        (0..num_entries)
+            .map(|_| (rng.gen_range(0, dim), rng.gen::<f32>()))
+            .collect()
+    }
+
+    fn to_dense(v: &[Entry], dim: usize) -> Vec<f32> {
+        let mut dense = vec![0.0f32; dim];
+        for entry in v {
+            dense[entry.index as usize] = entry.weight;
+        }
+        dense
+    }
+
+    #[test]
+    fn same_as_dense() {
+        for _ in 0..100 {
+            let x: Vector<DotProduct> = random_sparse_vector(100, 20);
+            let y: Vector<DotProduct> = random_sparse_vector(100, 20);
+
+            let expected: f32 = to_dense(x.as_slice(), 100)
+                .iter()
+                .zip(to_dense(y.as_slice(), 100))
+                .map(|(xi, yi)| xi * yi)
+                .sum();
+
+            assert!((1.0 - expected - x.dist(&y).into_inner()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn normalized_and_sorted() {
+        let x: Vector = vec![(5, 3.0f32), (1, 4.0), (5, 0.0), (7, 1.0), (7, -1.0)].into();
+
+        assert_eq!(
+            &[Entry { index: 1, weight: 0.8 }, Entry { index: 5, weight: 0.6 }],
+            x.as_slice()
+        );
+        assert!(x.dist(&x).into_inner() < 1e-6);
+    }
+
+    #[test]
+    fn write_and_load() {
+        let vectors: Vectors = (0..100).map(|i| random_sparse_vector(1000, i % 13)).collect();
+
+        let mut buffer = Vec::new();
+        io::Writeable::write(&vectors, &mut buffer).unwrap();
+        let loaded = Vectors::<Cosine>::from_bytes(&buffer);
+
+        // number of vectors, u64 offsets and 8 byte entries
+        let num_entries: usize = (0..vectors.len()).map(|i| vectors.get_element(i).len()).sum();
+        assert_eq!(8 + (vectors.len() + 1) * 8 + num_entries * 8, buffer.len());
+
+        assert_eq!(vectors.len(), loaded.len());
+        for i in 0..vectors.len() {
+            assert_eq!(vectors.get_element(i).as_slice(), loaded.get_element(i).as_slice());
+        }
+    }
+
+    #[test]
+    fn permute() {
+        let mut vectors: Vectors = (0..50).map(|_| random_sparse_vector(1000, 10)).collect();
+        let original = vectors.clone();
+
+        let mut permutation: Vec<usize> = (0..vectors.len()).collect();
+        permutation.shuffle(&mut rand::thread_rng());
+        vectors.permute(&permutation);
+
+        for (i, &j) in permutation.iter().enumerate() {
+            assert_eq!(original.get_element(j).as_slice(), vectors.get_element(i).as_slice());
+        }
+    }
+}
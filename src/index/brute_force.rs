@@ -0,0 +1,169 @@
+use super::Search;
+use crate::{max_size_heap::MaxSizeHeap, ElementContainer, QueryDist};
+
+use ordered_float::NotNan;
+#[cfg(not(feature = "singlethreaded"))]
+use rayon::prelude::*;
+
+/// The number of elements whose distances are computed in one batch.
+const CHUNK_SIZE: usize = 1024;
+
+/** Exact nearest neighbor search by computing the distance to every element.
+
+`BruteForce` implements [`Search`](trait.Search.html), i.e., it has the same `search` signature
+and result type as [`Granne`](struct.Granne.html), and can be used for computing ground truth when measuring the recall of an index, or instead of
+an index for collections too small to benefit from one.
+
+# Examples
+```
+# use granne::*;
+# let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+# let element = elements.get_element(123).into_owned();
+let mut builder = GranneBuilder::new(BuildConfig::default(), elements.borrow());
+builder.build();
+let index = builder.get_index();
+
+let exact = BruteForce::new(&elements);
+assert_eq!(exact.search(&element, 0, 1), index.search(&element, 200, 1));
+```
+ */
+pub struct BruteForce<Elements> {
+    elements: Elements,
+}
+
+impl<Elements: ElementContainer> BruteForce<Elements> {
+    /// Creates a `BruteForce` searcher over `elements`.
+    pub fn new(elements: Elements) -> Self {
+        Self { elements }
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to `element`. Returns a `Vec` containing
+    /// the id and distance from `element`.
+    ///
+    /// The elements are scanned in parallel (in chunks of consecutive ids) on the current rayon
+    /// thread pool. `max_search` is ignored and only exists for compatibility with
+    /// [`Granne::search`](struct.Granne.html#method.search).
+    pub fn search<Query>(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query> + Sync,
+        Query: Sync,
+    {
+        let _ = max_search;
+
+        if num_neighbors == 0 {
+            return Vec::new();
+        }
+
+        let num_chunks = (self.elements.len() + CHUNK_SIZE - 1) / CHUNK_SIZE;
+
+        let search_chunk = |mut heap: MaxSizeHeap<(NotNan<f32>, usize)>, chunk: usize| {
+            let begin = chunk * CHUNK_SIZE;
+            let end = std::cmp::min(begin + CHUNK_SIZE, self.elements.len());
+            let ids: Vec<usize> = (begin..end).collect();
+
+            for (d, idx) in self.elements.dists_to_query(element, &ids).into_iter().zip(ids) {
+                heap.push((d, idx));
+            }
+
+            heap
+        };
+
+        #[cfg(feature = "singlethreaded")]
+        let heap = (0..num_chunks).fold(MaxSizeHeap::new(num_neighbors), search_chunk);
+        #[cfg(not(feature = "singlethreaded"))]
+        let heap = (0..num_chunks)
+            .into_par_iter()
+            .fold(|| MaxSizeHeap::new(num_neighbors), search_chunk)
+            .reduce(
+                || MaxSizeHeap::new(num_neighbors),
+                |mut heap, other| {
+                    for x in other.into_sorted_vec() {
+                        heap.push(x);
+                    }
+                    heap
+                },
+            );
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(d, idx)| (idx, d.into_inner()))
+            .collect()
+    }
+
+    /// Returns the number of elements.
+    pub fn len(self: &Self) -> usize {
+        self.elements.len()
+    }
+
+    /// Returns the element at `index`.
+    pub fn get_element(self: &Self, index: usize) -> Elements::Element {
+        self.elements.get(index)
+    }
+
+    /// Returns a reference to the elements.
+    pub fn get_elements(self: &Self) -> &Elements {
+        &self.elements
+    }
+}
+
+impl<Elements: ElementContainer + QueryDist<Query> + Sync, Query: Sync> Search<Query> for BruteForce<Elements> {
+    fn search(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)> {
+        BruteForce::search(self, element, max_search, num_neighbors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{angular, test_helper, BuildConfig, Builder, GranneBuilder};
+
+    #[test]
+    fn exact_search() {
+        let elements: angular::Vectors = test_helper::random_vectors(5, 3 * CHUNK_SIZE + 17);
+        let brute_force = BruteForce::new(elements.borrow());
+
+        for _ in 0..10 {
+            let query: angular::Vector = test_helper::random_vector(5);
+
+            let mut expected: Vec<_> = (0..elements.len())
+                .map(|i| (i, elements.dist_to_element(i, &query).into_inner()))
+                .collect();
+            expected.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            expected.truncate(25);
+
+            assert_eq!(expected, brute_force.search(&query, 0, 25));
+        }
+
+        let query: angular::Vector = test_helper::random_vector(5);
+        assert!(brute_force.search(&query, 0, 0).is_empty());
+        assert_eq!(elements.len(), brute_force.search(&query, 0, 2 * elements.len()).len());
+        assert!(BruteForce::new(angular::Vectors::new())
+            .search(&query, 0, 10)
+            .is_empty());
+    }
+
+    #[test]
+    fn recall() {
+        let elements: angular::Vectors = test_helper::random_vectors(5, 2000);
+        let brute_force = BruteForce::new(elements.borrow());
+
+        let mut builder = GranneBuilder::new(
+            BuildConfig::default().num_neighbors(20).max_search(20),
+            elements.borrow(),
+        );
+        builder.build();
+        let index = builder.get_index();
+
+        let mut num_found = 0;
+        for _ in 0..100 {
+            let query: angular::Vector = test_helper::random_vector(5);
+
+            let exact = brute_force.search(&query, 50, 10);
+            let approximate = index.search(&query, 50, 10);
+
+            num_found += approximate.iter().filter(|res| exact.contains(res)).count();
+        }
+
+        assert!(900 < num_found);
+    }
+}
@@ -6,6 +6,8 @@ use std::io::{Error, ErrorKind};
 
 const METADATA_LEN: usize = 1024;
 const SERIALIZATION_VERSION: usize = 2;
+/// Indexes with neighbor ids wider than 4 bytes are not readable by earlier versions.
+const WIDE_SERIALIZATION_VERSION: usize = 3;
 const LIBRARY_STR: &str = "granne";
 
 pub(super) fn write_index(layers: &Layers, buffer: impl Write + Seek) -> Result<()> {
@@ -24,14 +26,20 @@ pub(super) fn write_index(layers: &Layers, buffer: impl Write + Seek) -> Result<
     };
     let layer_counts: Vec<usize> = (0..layers.len()).map(|i| layers.as_graph(i).len()).collect();
 
+    // neighbor ids are stored either as u32 or as u64 (wide)
+    let wide = layers.is_wide();
+    let neighbor_id_bytes = if wide { 8 } else { 4 };
+
     let mut layer_sizes = Vec::new();
     match layers {
         Layers::FixWidth(layers) => {
             for layer in layers {
-                let layer_size = layer.write_as_multi_set_vector(&mut buffer, |&x| x != UNUSED)?;
+                let layer_size = layer.write_as_multi_set_vector(&mut buffer, |&x| x != u32::UNUSED)?;
                 layer_sizes.push(layer_size);
             }
         }
+        Layers::FixWidth40(layers) => layer_sizes.extend(write_wide_layers(layers, &mut buffer)?),
+        Layers::FixWidth64(layers) => layer_sizes.extend(write_wide_layers(layers, &mut buffer)?),
         Layers::Compressed(layers) => {
             for layer in layers {
                 let layer_size = layer.write(&mut buffer)?;
@@ -49,13 +57,14 @@ pub(super) fn write_index(layers: &Layers, buffer: impl Write + Seek) -> Result<
     metadata.push_str(
         &serde_json::to_string(&serde_json::json!({
             "granne_version": env!("CARGO_PKG_VERSION"),
-            "version": SERIALIZATION_VERSION,
+            "version": if wide { WIDE_SERIALIZATION_VERSION } else { SERIALIZATION_VERSION },
             "num_elements": *layer_counts.last().unwrap_or(&0),
             "num_layers": layer_counts.len(),
             "num_neighbors": num_neighbors,
             "layer_counts": layer_counts,
             "layer_sizes": layer_sizes,
             "compressed": true,
+            "neighbor_id_bytes": neighbor_id_bytes,
         }))
         .expect("Could not create metadata json"),
     );
@@ -69,8 +78,26 @@ pub(super) fn write_index(layers: &Layers, buffer: impl Write + Seek) -> Result<
     Ok(())
 }
 
+fn write_wide_layers<Id: NeighborId>(
+    layers: &[FixedWidthSliceVector<Id>],
+    buffer: &mut (impl Write + Seek),
+) -> Result<Vec<usize>> {
+    layers
+        .iter()
+        .map(|layer| {
+            layer.write_as_wide_multi_set_vector(buffer, |&x| {
+                if x != Id::UNUSED {
+                    Some(x.into_usize() as u64)
+                } else {
+                    None
+                }
+            })
+        })
+        .collect()
+}
+
 pub(super) fn load_layers(buffer: &'_ [u8]) -> Layers<'_> {
-    let layer_sizes = read_layer_sizes(buffer).expect("Could not read metadata");
+    let (layer_sizes, neighbor_id_bytes) = read_layer_sizes(buffer).expect("Could not read metadata");
 
     let mut start = METADATA_LEN;
 
@@ -79,14 +106,19 @@ pub(super) fn load_layers(buffer: &'_ [u8]) -> Layers<'_> {
     for size in layer_sizes {
         let end = start + size;
         let layer = &buffer[start..end];
-        layers.push(MultiSetVector::from_bytes(layer));
+        layers.push(if neighbor_id_bytes > 4 {
+            MultiSetVector::from_bytes_wide(layer)
+        } else {
+            MultiSetVector::from_bytes(layer)
+        });
         start = end;
     }
 
     Layers::Compressed(layers)
 }
 
-fn read_layer_sizes<I: Read>(index_reader: I) -> Result<Vec<usize>> {
+/// Reads the size of each layer and the number of bytes per neighbor id from the metadata.
+fn read_layer_sizes<I: Read>(index_reader: I) -> Result<(Vec<usize>, usize)> {
     let mut index_reader = index_reader.take(METADATA_LEN as u64);
 
     let mut lib_str = Vec::new();
@@ -109,5 +141,8 @@ fn read_layer_sizes<I: Read>(index_reader: I) -> Result<Vec<usize>> {
     let layer_sizes = &metadata["layer_sizes"];
     let layer_sizes: Vec<usize> = serde_json::from_value(layer_sizes.clone())?;
 
-    Ok(layer_sizes)
+    // indexes written by earlier versions always use 4 bytes per neighbor id
+    let neighbor_id_bytes = metadata["neighbor_id_bytes"].as_u64().unwrap_or(4) as usize;
+
+    Ok((layer_sizes, neighbor_id_bytes))
 }
@@ -1,19 +1,34 @@
-use fxhash::FxBuildHasher;
 use madvise::{AccessPattern, AdviseMemory};
 use ordered_float::NotNan;
 use parking_lot;
 use pbr;
 use rayon::prelude::*;
 use std::cmp;
-use std::collections::{BinaryHeap, HashSet};
-use std::convert::TryFrom;
+use std::collections::BinaryHeap;
 use std::time;
 
+/// Evaluates `$body` with `$layers` bound to the layers of `$self`, whatever their representation.
+macro_rules! with_layers {
+    ($self:expr, $layers:ident => $body:expr) => {
+        match $self {
+            Layers::FixWidth($layers) => $body,
+            Layers::FixWidth40($layers) => $body,
+            Layers::FixWidth64($layers) => $body,
+            Layers::Compressed($layers) => $body,
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests;
 
+mod brute_force;
 mod io;
+mod neighbor_id;
 pub mod reorder;
+mod search_context;
+mod search_cursor;
+mod sharded;
 
 #[cfg(feature = "rw_granne")]
 pub mod rw;
@@ -21,11 +36,18 @@ pub mod rw;
 use crate::{
     max_size_heap,
     slice_vector::{FixedWidthSliceVector, MultiSetVector},
-    {ElementContainer, ExtendableElementContainer, Permutable},
+    {ElementContainer, ExtendableElementContainer, Permutable, QueryDist},
 };
 
-type NeighborId = u32;
-const UNUSED: NeighborId = NeighborId::max_value();
+pub use brute_force::BruteForce;
+pub use neighbor_id::NeighborId;
+pub use search_context::{SearchContext, SearchStats};
+pub use search_cursor::SearchCursor;
+pub use sharded::{ShardedGranne, SHARD_MANIFEST_FILE_NAME};
+
+/// The number of nodes expanded by [`Granne::search_with_filter`](struct.Granne.html#method.search_with_filter)
+/// is at most this factor times `max_search`.
+const FILTERED_SEARCH_EXPANSION_FACTOR: usize = 10;
 
 /** An index for fast approximate nearest neighbor search.
  The index is built by using [`GranneBuilder`](struct.GranneBuilder.html) and can be stored to
@@ -103,6 +125,46 @@ impl<'a, Elements: ElementContainer> Index for Granne<'a, Elements> {
     }
 }
 
+/// This trait contains the search method shared by [`Granne`](struct.Granne.html),
+/// [`ShardedGranne`](struct.ShardedGranne.html) and [`BruteForce`](struct.BruteForce.html), which
+/// allows code that only searches, e.g. for evaluating the recall of an index, to be written once
+/// for all of them.
+///
+/// # Examples
+/// ```
+/// # use granne::*;
+/// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+/// # let queries: Vec<angular::Vector> = (0..100).map(|_| test_helper::random_vector(3)).collect();
+/// fn recall<Q>(index: &impl Search<Q>, exact: &impl Search<Q>, queries: &[Q]) -> f32 {
+///     let num_found: usize = queries
+///         .iter()
+///         .map(|query| {
+///             let expected = exact.search(query, 0, 10);
+///             index.search(query, 200, 10).iter().filter(|res| expected.contains(res)).count()
+///         })
+///         .sum();
+///
+///     num_found as f32 / (10 * queries.len()) as f32
+/// }
+///
+/// let mut builder = GranneBuilder::new(BuildConfig::default(), elements.borrow());
+/// builder.build();
+///
+/// assert!(0.9 < recall(&builder.get_index(), &BruteForce::new(&elements), &queries));
+/// ```
+pub trait Search<Query> {
+    /// Searches for the `num_neighbors` neighbors closest to `element`, using `max_search` to
+    /// control how extensive the search is (if applicable). Returns a `Vec` containing the id and
+    /// distance from `element`, sorted by distance.
+    fn search(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)>;
+}
+
+impl<'a, Elements: ElementContainer + QueryDist<Query>, Query> Search<Query> for Granne<'a, Elements> {
+    fn search(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)> {
+        Granne::search(self, element, max_search, num_neighbors)
+    }
+}
+
 impl<'a, Elements: ElementContainer> Granne<'a, Elements> {
     /// Loads this index from bytes.
     pub fn from_bytes(index: &'a [u8], elements: Elements) -> Self {
@@ -137,16 +199,395 @@ impl<'a, Elements: ElementContainer> Granne<'a, Elements> {
     /// Searches for the `num_neighbors` neighbors closest to `element` in this index.
     /// `max_search` controls the number of nodes visited during the search. Returns a
     /// `Vec` containing the id and distance from `element`.
-    pub fn search(
+    ///
+    /// `element` is typically of type `Elements::Element`, but can be of any type `Query` for
+    /// which `Elements` implements [`QueryDist<Query>`](trait.QueryDist.html), e.g., an
+    /// `angular::Vector` when searching among `angular_int::Vectors`.
+    pub fn search<Query>(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+    {
+        with_layers!(self.layers.load(), layers => {
+            let mut context = SearchContext::for_single_search(max_search);
+            self.search_internal(&layers, element, max_search, num_neighbors, |_| true, usize::MAX, &mut context)
+        })
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to `element` in this index (see
+    /// [`search`](#method.search)), using `context` as scratch space instead of allocating new
+    /// buffers for the search.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let queries: Vec<angular::Vector> = (0..100).map(|_| test_helper::random_vector(3)).collect();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let mut context = SearchContext::new();
+    /// for query in &queries {
+    ///     let res = index.search_with_context(query, 200, 10, &mut context);
+    ///     assert_eq!(index.search(query, 200, 10), res);
+    /// }
+    /// ```
+    pub fn search_with_context<Query>(
+        self: &Self,
+        element: &Query,
+        max_search: usize,
+        num_neighbors: usize,
+        context: &mut SearchContext,
+    ) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+    {
+        with_layers!(self.layers.load(), layers => {
+            self.search_internal(&layers, element, max_search, num_neighbors, |_| true, usize::MAX, context)
+        })
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to `element` in this index (see
+    /// [`search`](#method.search)) and returns the result together with statistics about the
+    /// search, e.g., the number of distance computations. If `trace` is `true`, the ids of all
+    /// expanded nodes are included in the statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let element = elements.get_element(123).into_owned();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let (res, stats) = index.search_with_stats(&element, 200, 10, true);
+    /// assert_eq!(index.search(&element, 200, 10), res);
+    /// assert_eq!(index.num_layers(), stats.hops_per_layer.len());
+    ///
+    /// // the search starts at the entrypoint of the bottom layer
+    /// let trace = stats.trace.unwrap();
+    /// assert_eq!(stats.entrypoint, trace[index.num_layers() - 1][0]);
+    /// ```
+    pub fn search_with_stats<Query>(
+        self: &Self,
+        element: &Query,
+        max_search: usize,
+        num_neighbors: usize,
+        trace: bool,
+    ) -> (Vec<(usize, f32)>, SearchStats)
+    where
+        Elements: QueryDist<Query>,
+    {
+        let mut context = SearchContext::for_single_search(max_search);
+        context.stats = Some(SearchStats::new(trace));
+
+        let res = with_layers!(self.layers.load(), layers => {
+            self.search_internal(&layers, element, max_search, num_neighbors, |_| true, usize::MAX, &mut context)
+        });
+
+        (res, context.stats.take().unwrap())
+    }
+
+    /// Returns a [`SearchCursor`](struct.SearchCursor.html) yielding the elements closest to
+    /// `element` lazily, in roughly increasing distance. The search state is kept in the cursor,
+    /// i.e., fetching more results continues the search instead of starting over.
+    ///
+    /// The results are found in pages of `max_search` elements, where the first page contains the
+    /// same elements as `search` with the same `max_search`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let element = elements.get_element(123).into_owned();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let mut cursor = index.search_iter(&element, 50);
+    /// let first_page: Vec<_> = cursor.by_ref().take(10).collect();
+    /// let second_page: Vec<_> = cursor.by_ref().take(10).collect();
+    ///
+    /// assert_eq!(123, first_page[0].0);
+    /// assert_eq!(10, second_page.len());
+    /// ```
+    pub fn search_iter<'b, Query>(
+        self: &'b Self,
+        element: &'b Query,
+        max_search: usize,
+    ) -> SearchCursor<'b, Elements, Query>
+    where
+        Elements: QueryDist<Query>,
+    {
+        SearchCursor::new(self, element, max_search)
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to `element` among the elements for
+    /// which `filter` returns `true`. Returns a `Vec` containing the id and distance from
+    /// `element`.
+    ///
+    /// The graph is traversed through all nodes, but only elements passing `filter` are included
+    /// in the result. When few elements pass `filter`, the search visits more nodes before
+    /// terminating, but at most `10 * max_search` nodes are expanded, so fewer than
+    /// `num_neighbors` results may be returned.
+    ///
+    /// For very selective filters, it is typically better to compute the exact result among the
+    /// passing elements directly, e.g. using a [`BruteForce`](struct.BruteForce.html) searcher
+    /// over a container of only those elements.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let element = elements.get_element(123).into_owned();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let res = index.search_with_filter(&element, 200, 10, |id| id % 2 == 0);
+    /// assert_eq!(10, res.len());
+    /// assert!(res.iter().all(|&(id, _)| id % 2 == 0));
+    /// ```
+    pub fn search_with_filter<Query, Filter>(
+        self: &Self,
+        element: &Query,
+        max_search: usize,
+        num_neighbors: usize,
+        filter: Filter,
+    ) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+        Filter: Fn(usize) -> bool,
+    {
+        let mut context = SearchContext::for_single_search(max_search);
+        self.search_with_filter_and_context(element, max_search, num_neighbors, filter, &mut context)
+    }
+
+    fn search_with_filter_and_context<Query, Filter>(
+        self: &Self,
+        element: &Query,
+        max_search: usize,
+        num_neighbors: usize,
+        filter: Filter,
+        context: &mut SearchContext,
+    ) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+        Filter: Fn(usize) -> bool,
+    {
+        with_layers!(self.layers.load(), layers => {
+            let max_expansions = max_search.saturating_mul(FILTERED_SEARCH_EXPANSION_FACTOR);
+            self.search_internal(&layers, element, max_search, num_neighbors, &filter, max_expansions, context)
+        })
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to each of the `elements` in this index
+    /// (see [`search`](#method.search)). The queries are processed in parallel on the current
+    /// rayon thread pool, reusing a [`SearchContext`](struct.SearchContext.html) between queries on
+    /// the same thread.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let queries: Vec<angular::Vector> = (0..100).map(|_| test_helper::random_vector(3)).collect();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let res = index.search_batch(&queries, 200, 10);
+    /// assert_eq!(queries.len(), res.len());
+    ///
+    /// // the number of threads is configured by running in a custom thread pool
+    /// let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+    /// assert_eq!(res, pool.install(|| index.search_batch(&queries, 200, 10)));
+    /// ```
+    pub fn search_batch<Query>(
+        self: &Self,
+        elements: &[Query],
+        max_search: usize,
+        num_neighbors: usize,
+    ) -> Vec<Vec<(usize, f32)>>
+    where
+        Elements: QueryDist<Query> + Sync,
+        Query: Sync,
+    {
+        with_layers!(self.layers.load(), layers => {
+            #[cfg(feature = "singlethreaded")]
+            let elements_iter = elements.iter().map({
+                let mut context = SearchContext::new();
+                move |element| self.search_internal(&layers, element, max_search, num_neighbors, |_| true, usize::MAX, &mut context)
+            });
+            #[cfg(not(feature = "singlethreaded"))]
+            let elements_iter = elements.par_iter().map_init(SearchContext::new, |context, element| {
+                self.search_internal(&layers, element, max_search, num_neighbors, |_| true, usize::MAX, context)
+            });
+
+            elements_iter.collect()
+        })
+    }
+
+    /// Searches for all elements within distance `max_dist` from `element` in this index. Returns
+    /// a `Vec` containing the id and distance from `element`, sorted by distance.
+    ///
+    /// All nodes within `max_dist` that are reachable from the entrypoint are visited, so the
+    /// number of results is not bounded. In addition, `max_search` controls the number of nodes
+    /// outside of `max_dist` that are visited while looking for nodes within `max_dist`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let element = elements.get_element(123).into_owned();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let res = index.search_within(&element, 0.01, 50);
+    /// assert!(res.iter().all(|&(_, d)| d <= 0.01));
+    /// assert_eq!(123, res[0].0);
+    /// ```
+    pub fn search_within<Query>(self: &Self, element: &Query, max_dist: f32, max_search: usize) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+    {
+        with_layers!(self.layers.load(), layers => {
+            if let Some((bottom_layer, top_layers)) = layers.split_last() {
+                let mut context = SearchContext::for_single_search(max_search);
+                let entrypoint = find_entrypoint(top_layers, &self.elements, element, &mut context);
+
+                search_for_neighbors_within(bottom_layer, entrypoint, &self.elements, element, max_dist, max_search, &mut context)
+                    .into_iter()
+                    .map(|(i, d)| (i, d.into_inner()))
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        })
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to `element` in this index and re-ranks
+    /// them using `reranker`, an `ElementContainer` sharing ids with the elements of this index
+    /// (typically containing the same elements, but with higher precision).
+    ///
+    /// `num_neighbors * oversample` candidates are retrieved from this index (`max_search` is
+    /// increased if needed) and the `num_neighbors` closest ones according to `reranker` are
+    /// returned. The returned distances are computed by `reranker`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let vectors: angular::Vectors = test_helper::random_vectors(5, 1000);
+    /// # let element = vectors.get_element(123).into_owned();
+    /// # let elements: angular_int::Vectors = (0..vectors.len()).map(|i| vectors.get(i).into_vec().into()).collect();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// // search among the quantized vectors and re-rank using the full precision vectors
+    /// let res = index.search_reranked(&element, &vectors, 200, 10, 4);
+    /// assert_eq!(10, res.len());
+    /// ```
+    pub fn search_reranked<Query, Reranker>(
+        self: &Self,
+        element: &Query,
+        reranker: &Reranker,
+        max_search: usize,
+        num_neighbors: usize,
+        oversample: usize,
+    ) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+        Reranker: QueryDist<Query>,
+    {
+        debug_assert!(reranker.len() >= self.elements.len());
+
+        let num_candidates = num_neighbors * cmp::max(1, oversample);
+        let candidates = self.search(element, cmp::max(max_search, num_candidates), num_candidates);
+
+        let mut res: Vec<(usize, NotNan<f32>)> = candidates
+            .into_iter()
+            .map(|(idx, _)| (idx, reranker.dist_to_query(idx, element)))
+            .collect();
+
+        res.sort_unstable_by_key(|&(_, d)| d);
+
+        res.into_iter()
+            .take(num_neighbors)
+            .map(|(i, d)| (i, d.into_inner()))
+            .collect()
+    }
+
+    /// Searches for `num_neighbors` neighbors of `element` that are close to `element` while
+    /// being diverse, i.e., not close to each other. Returns a `Vec` containing the id and
+    /// distance from `element`, in the order the neighbors were selected.
+    ///
+    /// The `max_search` neighbors closest to `element` are retrieved as candidates, from which
+    /// neighbors are selected greedily by
+    /// [maximal marginal relevance](https://en.wikipedia.org/wiki/Maximal_marginal_relevance):
+    /// the next neighbor is the candidate minimizing
+    /// `lambda * dist(element, candidate) - (1 - lambda) * min_dist(candidate, selected)`.
+    /// `lambda = 1.0` results in the same neighbors as [`search`](#method.search), while smaller
+    /// values (between `0.0` and `1.0`) put more weight on diversity.
+    ///
+    /// # Examples
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// # let element = elements.get_element(123).into_owned();
+    /// let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+    /// builder.build();
+    /// let index = builder.get_index();
+    ///
+    /// let res = index.search_diversified(&element, 200, 10, 0.5);
+    /// assert_eq!(10, res.len());
+    /// assert_eq!(123, res[0].0);
+    /// ```
+    pub fn search_diversified<Query>(
         self: &Self,
-        element: &Elements::Element,
+        element: &Query,
         max_search: usize,
         num_neighbors: usize,
-    ) -> Vec<(usize, f32)> {
-        match self.layers.load() {
-            Layers::FixWidth(layers) => self.search_internal(&layers, element, max_search, num_neighbors),
-            Layers::Compressed(layers) => self.search_internal(&layers, element, max_search, num_neighbors),
+        lambda: f32,
+    ) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+    {
+        assert!((0.0..=1.0).contains(&lambda), "lambda needs to be between 0.0 and 1.0");
+
+        let max_search = cmp::max(max_search, num_neighbors);
+        let mut candidates = self.search(element, max_search, max_search);
+
+        // the smallest distance from each candidate to the selected neighbors
+        let mut min_dists = vec![std::f32::INFINITY; candidates.len()];
+
+        let mut res = Vec::with_capacity(num_neighbors);
+        while res.len() < num_neighbors && !candidates.is_empty() {
+            let score = |i: usize| {
+                let diversity = if res.is_empty() { 0.0 } else { min_dists[i] };
+                lambda * candidates[i].1 - (1.0 - lambda) * diversity
+            };
+
+            let best = (0..candidates.len())
+                .min_by(|&i, &j| score(i).partial_cmp(&score(j)).unwrap())
+                .unwrap();
+
+            let selected = candidates.swap_remove(best);
+            min_dists.swap_remove(best);
+
+            let candidate_ids: Vec<usize> = candidates.iter().map(|&(idx, _)| idx).collect();
+            for (min_dist, d) in min_dists
+                .iter_mut()
+                .zip(self.elements.dists(selected.0, &candidate_ids))
+            {
+                *min_dist = min_dist.min(d.into_inner());
+            }
+
+            res.push(selected);
         }
+
+        res
     }
 
     /// Returns the element at `index`.
@@ -175,6 +616,12 @@ where
     pub fn to_owned(self: &Self) -> Granne<'static, Elements::Owned> {
         let layers = match self.layers.load() {
             Layers::FixWidth(layers) => Layers::FixWidth(layers.into_iter().map(|layer| layer.into_owned()).collect()),
+            Layers::FixWidth40(layers) => {
+                Layers::FixWidth40(layers.into_iter().map(|layer| layer.into_owned()).collect())
+            }
+            Layers::FixWidth64(layers) => {
+                Layers::FixWidth64(layers.into_iter().map(|layer| layer.into_owned()).collect())
+            }
             Layers::Compressed(layers) => {
                 Layers::Compressed(layers.into_iter().map(|layer| layer.into_owned()).collect())
             }
@@ -292,9 +739,12 @@ impl BuildConfig {
 
 /// A builder for creating an index to be searched using [`Granne`](struct.Granne.html). Configured
 /// by [`BuildConfig`](struct.BuildConfig.html).
-pub struct GranneBuilder<Elements: ElementContainer> {
+///
+/// The neighbors of each node are stored as `Id`s (`u32` by default) while building, see
+/// [`NeighborId`](trait.NeighborId.html).
+pub struct GranneBuilder<Elements: ElementContainer, Id: NeighborId = u32> {
     elements: Elements,
-    layers: Vec<FixedWidthSliceVector<'static, NeighborId>>,
+    layers: Vec<FixedWidthSliceVector<'static, Id>>,
     config: BuildConfig,
 }
 
@@ -314,7 +764,7 @@ pub trait Builder: Index {
     fn num_elements(self: &Self) -> usize;
 }
 
-impl<Elements: ElementContainer + Sync> Index for GranneBuilder<Elements> {
+impl<Elements: ElementContainer + Sync, Id: NeighborId> Index for GranneBuilder<Elements, Id> {
     /// Returns the number of indexed elements.
     /// Note that it might be less than the number of elements in `elements`.
     /// # Examples
@@ -356,12 +806,12 @@ impl<Elements: ElementContainer + Sync> Index for GranneBuilder<Elements> {
     /// # Ok::<(), std::io::Error>(())
     /// ```
     fn write_index<B: std::io::Write + std::io::Seek>(self: &Self, buffer: &mut B) -> std::io::Result<()> {
-        let layers: Layers = Layers::FixWidth(self.layers.iter().map(|layer| layer.borrow()).collect());
+        let layers: Layers = Id::into_layers(self.layers.iter().map(|layer| layer.borrow()).collect());
         io::write_index(&layers, buffer)
     }
 }
 
-impl<Elements: ElementContainer + Sync> Builder for GranneBuilder<Elements> {
+impl<Elements: ElementContainer + Sync, Id: NeighborId> Builder for GranneBuilder<Elements, Id> {
     /// Builds an index for approximate nearest neighbor search.
     fn build(self: &mut Self) {
         self.build_partial(self.elements.len())
@@ -417,7 +867,37 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
     /// let mut builder = GranneBuilder::new(config, angular::Vectors::new());
     /// ```
     pub fn new(config: BuildConfig, elements: Elements) -> Self {
-        assert!(elements.len() < UNUSED as usize);
+        Self::with_neighbor_ids(config, elements)
+    }
+
+    /// Creates a `GranneBuilder` by reading an already built index from `buffer` together with
+    /// `elements`.
+    pub fn from_bytes(config: BuildConfig, buffer: &[u8], elements: Elements) -> Self {
+        Self::from_bytes_with_neighbor_ids(config, buffer, elements)
+    }
+
+    /// Creates a `GranneBuilder` by reading an already built index from `buffer` together with
+    /// `elements`.
+    pub fn from_file(config: BuildConfig, file: &std::fs::File, elements: Elements) -> std::io::Result<Self> {
+        Self::from_file_with_neighbor_ids(config, file, elements)
+    }
+}
+
+impl<Elements: ElementContainer + Sync, Id: NeighborId> GranneBuilder<Elements, Id> {
+    /// Creates a new GranneBuilder with a `BuildConfig` and `elements`, storing neighbors as `Id`s
+    /// while building. Wider ids allow for indexing more than `2^32 - 2` elements (see
+    /// [`NeighborId`](trait.NeighborId.html)).
+    /// # Examples
+    ///
+    /// ```
+    /// # use granne::*;
+    /// # let elements: angular::Vectors = test_helper::random_vectors(3, 1000);
+    /// let mut builder = GranneBuilder::<_, u64>::with_neighbor_ids(BuildConfig::default(), elements);
+    /// builder.build();
+    /// assert_eq!(1000, builder.len());
+    /// ```
+    pub fn with_neighbor_ids(config: BuildConfig, elements: Elements) -> Self {
+        assert!(elements.len() < Id::UNUSED.into_usize());
         Self {
             elements,
             layers: Vec::new(),
@@ -425,47 +905,67 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         }
     }
 
-    /// Creates a `GranneBuilder` by reading an already built index from `buffer` together with
-    /// `elements`.
-    pub fn from_bytes(config: BuildConfig, buffer: &[u8], elements: Elements) -> Self {
-        let mut builder = Self::new(config, elements);
+    /// Creates a `GranneBuilder` storing neighbors as `Id`s by reading an already built index
+    /// from `buffer` together with `elements`.
+    pub fn from_bytes_with_neighbor_ids(config: BuildConfig, buffer: &[u8], elements: Elements) -> Self {
+        Self::from_layers(config, &io::load_layers(buffer), elements)
+    }
 
-        let layers = io::load_layers(buffer);
+    /// Creates a `GranneBuilder` from `layers`. Uncompressed layers already using `Id` are copied
+    /// as they are. Other uncompressed layers need to fit in `config.num_neighbors`, while
+    /// compressed layers are cut to `config.num_neighbors` neighbors per node.
+    fn from_layers(config: BuildConfig, layers: &Layers, elements: Elements) -> Self {
+        let mut builder = Self::with_neighbor_ids(config, elements);
 
-        match layers {
-            Layers::FixWidth(layers) => {
-                builder.layers = layers.iter().map(|l| l.borrow().into_owned()).collect();
-            }
-            Layers::Compressed(layers) => {
-                for layer in layers {
-                    builder.layers.push({
-                        let mut new_layer = FixedWidthSliceVector::with_width(builder.config.num_neighbors);
-                        new_layer.reserve(layer.len());
-
-                        let mut neighbors = Vec::new();
-                        for i in 0..layer.len() {
-                            layer.get_into(i, &mut neighbors);
-                            neighbors.resize(builder.config.num_neighbors, UNUSED);
-
-                            new_layer.push(&neighbors);
-                            neighbors.clear();
-                        }
-
-                        new_layer
-                    });
-                }
-            }
+        if let Some(layers) = Id::copy_layers(layers) {
+            builder.layers = layers;
+            return builder;
         }
 
+        let compressed = if let Layers::Compressed(_) = layers {
+            true
+        } else {
+            false
+        };
+
+        with_layers!(layers, layers => {
+            for layer in layers {
+                builder.layers.push({
+                    let mut new_layer = FixedWidthSliceVector::with_width(builder.config.num_neighbors);
+                    new_layer.reserve(layer.len());
+
+                    let mut neighbors = Vec::new();
+                    for i in 0..layer.len() {
+                        neighbors.extend(layer.get_neighbors(i).into_iter().map(Id::from_usize));
+                        assert!(
+                            compressed || neighbors.len() <= builder.config.num_neighbors,
+                            "The index has more neighbors per node than num_neighbors ({})",
+                            builder.config.num_neighbors
+                        );
+                        neighbors.resize(builder.config.num_neighbors, Id::UNUSED);
+
+                        new_layer.push(&neighbors);
+                        neighbors.clear();
+                    }
+
+                    new_layer
+                });
+            }
+        });
+
         builder
     }
 
-    /// Creates a `GranneBuilder` by reading an already built index from `buffer` together with
-    /// `elements`.
-    pub fn from_file(config: BuildConfig, file: &std::fs::File, elements: Elements) -> std::io::Result<Self> {
+    /// Creates a `GranneBuilder` storing neighbors as `Id`s by reading an already built index
+    /// from `file` together with `elements`.
+    pub fn from_file_with_neighbor_ids(
+        config: BuildConfig,
+        file: &std::fs::File,
+        elements: Elements,
+    ) -> std::io::Result<Self> {
         let bytes = unsafe { memmap::Mmap::map(file)? };
 
-        Ok(Self::from_bytes(config, &bytes[..], elements))
+        Ok(Self::from_bytes_with_neighbor_ids(config, &bytes[..], elements))
     }
 
     /// Returns a searchable index from this builder.
@@ -493,7 +993,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
     }
 }
 
-impl<Elements: ElementContainer + crate::io::Writeable> GranneBuilder<Elements> {
+impl<Elements: ElementContainer + crate::io::Writeable, Id: NeighborId> GranneBuilder<Elements, Id> {
     /// Writes the elements of this builder to `buffer`.
     /// # Examples
     /// ```
@@ -509,7 +1009,7 @@ impl<Elements: ElementContainer + crate::io::Writeable> GranneBuilder<Elements>
     }
 }
 
-impl<Elements: ExtendableElementContainer> GranneBuilder<Elements> {
+impl<Elements: ExtendableElementContainer, Id: NeighborId> GranneBuilder<Elements, Id> {
     /// Push a new element into this builder. In order to insert it into the index
     /// a call to `build` or `build_partial` is required.
     /// # Examples
@@ -525,7 +1025,7 @@ impl<Elements: ExtendableElementContainer> GranneBuilder<Elements> {
     /// assert_eq!(2, builder.len());
     /// ```
     pub fn push(self: &mut Self, element: Elements::InternalElement) {
-        assert!(self.elements.len() < UNUSED as usize - 1);
+        assert!(self.elements.len() < Id::UNUSED.into_usize() - 1);
         self.elements.push(element);
     }
 }
@@ -533,17 +1033,27 @@ impl<Elements: ExtendableElementContainer> GranneBuilder<Elements> {
 // implementation
 
 trait Graph {
-    fn get_neighbors(self: &Self, idx: usize) -> Vec<usize>;
+    fn get_neighbors(self: &Self, idx: usize) -> Vec<usize> {
+        let mut neighbors = Vec::new();
+        self.get_neighbors_into(idx, &mut neighbors);
+
+        neighbors
+    }
+
+    /// Replaces the contents of `neighbors` with the neighbors of `idx`.
+    fn get_neighbors_into(self: &Self, idx: usize, neighbors: &mut Vec<usize>);
     fn len(self: &Self) -> usize;
 }
 
-impl<'a> Graph for FixedWidthSliceVector<'a, NeighborId> {
-    fn get_neighbors(self: &Self, idx: usize) -> Vec<usize> {
-        self.get(idx)
-            .iter()
-            .take_while(|&&x| x != UNUSED)
-            .map(|&x| usize::try_from(x).unwrap())
-            .collect()
+impl<'a, Id: NeighborId> Graph for FixedWidthSliceVector<'a, Id> {
+    fn get_neighbors_into(self: &Self, idx: usize, neighbors: &mut Vec<usize>) {
+        neighbors.clear();
+        neighbors.extend(
+            self.get(idx)
+                .iter()
+                .take_while(|&&x| x != Id::UNUSED)
+                .map(|&x| x.into_usize()),
+        );
     }
 
     fn len(self: &Self) -> usize {
@@ -552,8 +1062,8 @@ impl<'a> Graph for FixedWidthSliceVector<'a, NeighborId> {
 }
 
 impl<'a> Graph for MultiSetVector<'a> {
-    fn get_neighbors(self: &Self, idx: usize) -> Vec<usize> {
-        self.get(idx).iter().map(|&x| usize::try_from(x).unwrap()).collect()
+    fn get_neighbors_into(self: &Self, idx: usize, neighbors: &mut Vec<usize>) {
+        self.get_ids_into(idx, neighbors);
     }
 
     fn len(self: &Self) -> usize {
@@ -561,14 +1071,16 @@ impl<'a> Graph for MultiSetVector<'a> {
     }
 }
 
-impl<'a> Graph for [parking_lot::RwLock<&'a mut [NeighborId]>] {
-    fn get_neighbors(self: &Self, idx: usize) -> Vec<usize> {
-        self[idx]
-            .read()
-            .iter()
-            .take_while(|&&x| x != UNUSED)
-            .map(|&x| usize::try_from(x).unwrap())
-            .collect()
+impl<'a, Id: NeighborId> Graph for [parking_lot::RwLock<&'a mut [Id]>] {
+    fn get_neighbors_into(self: &Self, idx: usize, neighbors: &mut Vec<usize>) {
+        neighbors.clear();
+        neighbors.extend(
+            self[idx]
+                .read()
+                .iter()
+                .take_while(|&&x| x != Id::UNUSED)
+                .map(|&x| x.into_usize()),
+        );
     }
 
     fn len(self: &Self) -> usize {
@@ -593,23 +1105,28 @@ impl<'a> FileOrMemoryLayers<'a> {
     }
 }
 
-enum Layers<'a> {
-    FixWidth(Vec<FixedWidthSliceVector<'a, NeighborId>>),
+pub(crate) enum Layers<'a> {
+    FixWidth(Vec<FixedWidthSliceVector<'a, u32>>),
+    FixWidth40(Vec<FixedWidthSliceVector<'a, crate::FiveByteInt>>),
+    FixWidth64(Vec<FixedWidthSliceVector<'a, u64>>),
     Compressed(Vec<MultiSetVector<'a>>),
 }
 
 impl<'a> Layers<'a> {
     fn len(self: &Self) -> usize {
-        match self {
-            Self::FixWidth(layers) => layers.len(),
-            Self::Compressed(layers) => layers.len(),
-        }
+        with_layers!(self, layers => layers.len())
     }
 
     fn as_graph(self: &Self, layer: usize) -> &dyn Graph {
+        with_layers!(self, layers => &layers[layer])
+    }
+
+    /// Returns whether the neighbor ids of these layers are stored using more than 4 bytes.
+    fn is_wide(self: &Self) -> bool {
         match self {
-            Self::FixWidth(layers) => &layers[layer],
-            Self::Compressed(layers) => &layers[layer],
+            Self::FixWidth(_) => false,
+            Self::FixWidth40(_) | Self::FixWidth64(_) => true,
+            Self::Compressed(layers) => layers.iter().any(|layer| layer.is_wide()),
         }
     }
 
@@ -619,14 +1136,16 @@ impl<'a> Layers<'a> {
     {
         match self {
             Self::FixWidth(layers) => Layers::FixWidth(layers.iter().map(|l| l.borrow()).collect()),
+            Self::FixWidth40(layers) => Layers::FixWidth40(layers.iter().map(|l| l.borrow()).collect()),
+            Self::FixWidth64(layers) => Layers::FixWidth64(layers.iter().map(|l| l.borrow()).collect()),
             Self::Compressed(layers) => Layers::Compressed(layers.iter().map(|l| l.borrow()).collect()),
         }
     }
 }
 
-impl<'a> From<Vec<FixedWidthSliceVector<'a, NeighborId>>> for Layers<'a> {
-    fn from(fix_width: Vec<FixedWidthSliceVector<'a, NeighborId>>) -> Self {
-        Self::FixWidth(fix_width)
+impl<'a, Id: NeighborId> From<Vec<FixedWidthSliceVector<'a, Id>>> for Layers<'a> {
+    fn from(fix_width: Vec<FixedWidthSliceVector<'a, Id>>) -> Self {
+        Id::into_layers(fix_width)
     }
 }
 
@@ -642,7 +1161,7 @@ fn compute_num_elements_in_layer(total_num_elements: usize, layer_multiplier: f3
     )
 }
 
-impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
+impl<Elements: ElementContainer + Sync, Id: NeighborId> GranneBuilder<Elements, Id> {
     fn index_elements_in_last_layer(self: &mut Self, max_num_elements: usize) {
         let total_num_elements = self.config.expected_num_elements.unwrap_or(self.elements.len());
         let ideal_num_elements_in_layer = compute_num_elements_in_layer(
@@ -718,7 +1237,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         elements: &Elements,
         num_elements: usize,
         prev_layers: &Granne<&Elements>,
-        layer: &mut FixedWidthSliceVector<'static, NeighborId>,
+        layer: &mut FixedWidthSliceVector<'static, Id>,
         reinsert_elements: bool,
     ) {
         assert!(layer.len() <= num_elements);
@@ -727,7 +1246,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         if reinsert_elements {
             already_indexed = 0;
         } else {
-            layer.resize(num_elements, UNUSED);
+            layer.resize(num_elements, Id::UNUSED);
         }
 
         // set up progress bar
@@ -754,8 +1273,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
 
         {
             // create RwLocks for underlying nodes
-            let layer: Vec<parking_lot::RwLock<&mut [NeighborId]>> =
-                layer.iter_mut().map(parking_lot::RwLock::new).collect();
+            let layer: Vec<parking_lot::RwLock<&mut [Id]>> = layer.iter_mut().map(parking_lot::RwLock::new).collect();
 
             let insert_element = |(idx, _)| {
                 Self::index_element(config, elements, prev_layers, &layer, idx);
@@ -806,7 +1324,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         config: &BuildConfig,
         elements: &Elements,
         prev_layers: &Granne<&Elements>,
-        layer: &[parking_lot::RwLock<&mut [NeighborId]>],
+        layer: &[parking_lot::RwLock<&mut [Id]>],
         idx: usize,
     ) {
         // do not index elements that are zero
@@ -832,7 +1350,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         }
 
         // if current node is empty, initialize it with the neighbors
-        if layer[idx].read()[0] == UNUSED {
+        if layer[idx].read()[0] == Id::UNUSED {
             Self::initialize_node(&layer[idx], &neighbors[..]);
         } else {
             for &(neighbor, d) in &neighbors {
@@ -883,26 +1401,20 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
     }
 
     /// Sets neighbors for `node`.
-    fn initialize_node(node: &parking_lot::RwLock<&mut [NeighborId]>, neighbors: &[(usize, NotNan<f32>)]) {
-        debug_assert_eq!(&UNUSED, &node.read()[0]);
+    fn initialize_node(node: &parking_lot::RwLock<&mut [Id]>, neighbors: &[(usize, NotNan<f32>)]) {
+        debug_assert!(Id::UNUSED == node.read()[0]);
 
         // Write Lock!
         let mut node = node.write();
 
         for (i, &(idx, _)) in neighbors.iter().enumerate().take(node.len()) {
-            node[i] = NeighborId::try_from(idx).unwrap();
+            node[i] = Id::from_usize(idx);
         }
     }
 
     /// Tries to add `j` as a neighbor to `i`. If the neighbor list is full, uses `select_neighbors`
     /// to limit the number of neighbors.
-    fn connect_nodes(
-        elements: &Elements,
-        node: &parking_lot::RwLock<&mut [NeighborId]>,
-        i: usize,
-        j: usize,
-        d: NotNan<f32>,
-    ) {
+    fn connect_nodes(elements: &Elements, node: &parking_lot::RwLock<&mut [Id]>, i: usize, j: usize, d: NotNan<f32>) {
         if i == j {
             return;
         }
@@ -911,8 +1423,8 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         let mut node = node.write();
 
         // Do not insert duplicates
-        let j_id = NeighborId::try_from(j).unwrap();
-        if let Some(free_pos) = node.iter().position(|x| *x == UNUSED || *x == j_id) {
+        let j_id = Id::from_usize(j);
+        if let Some(free_pos) = node.iter().position(|x| *x == Id::UNUSED || *x == j_id) {
             node[free_pos] = j_id;
         } else {
             let num_neighbors = node.len();
@@ -922,7 +1434,7 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
 
     fn add_and_limit_neighbors(
         elements: &Elements,
-        node: &mut [NeighborId],
+        node: &mut [Id],
         node_id: usize,
         extra: &[(usize, NotNan<f32>)],
         num_neighbors: usize,
@@ -931,8 +1443,8 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
 
         let neighbors: Vec<usize> = node
             .iter()
-            .take_while(|&&x| x != UNUSED)
-            .map(|&x| usize::try_from(x).unwrap())
+            .take_while(|&&x| x != Id::UNUSED)
+            .map(|&x| x.into_usize())
             .collect();
 
         let dists = elements.dists(node_id, &neighbors);
@@ -949,8 +1461,8 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
         // set new neighbors and mark last positions as unused
         for (k, n) in neighbors
             .into_iter()
-            .map(|(n, _)| NeighborId::try_from(n).unwrap())
-            .chain(std::iter::repeat(UNUSED))
+            .map(|(n, _)| Id::from_usize(n))
+            .chain(std::iter::repeat(Id::UNUSED))
             .enumerate()
             .take(node.len())
         {
@@ -960,35 +1472,53 @@ impl<Elements: ElementContainer + Sync> GranneBuilder<Elements> {
 }
 
 impl<'a, Elements: ElementContainer> Granne<'a, Elements> {
-    fn search_internal(
+    #[allow(clippy::too_many_arguments)]
+    fn search_internal<Query>(
         self: &Self,
         layers: &[impl Graph],
-        element: &Elements::Element,
+        element: &Query,
         max_search: usize,
         num_neighbors: usize,
-    ) -> Vec<(usize, f32)> {
+        filter: impl Fn(usize) -> bool,
+        max_expansions: usize,
+        context: &mut SearchContext,
+    ) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query>,
+    {
         if let Some((bottom_layer, top_layers)) = layers.split_last() {
-            let entrypoint = find_entrypoint(top_layers, &self.elements, element);
+            let entrypoint = find_entrypoint(top_layers, &self.elements, element, context);
 
-            search_for_neighbors(bottom_layer, entrypoint, &self.elements, element, max_search)
-                .into_iter()
-                .take(num_neighbors)
-                .map(|(i, d)| (i, d.into_inner()))
-                .collect()
+            search_for_neighbors_with_filter(
+                bottom_layer,
+                entrypoint,
+                &self.elements,
+                element,
+                max_search,
+                filter,
+                max_expansions,
+                context,
+            )
+            .into_iter()
+            .take(num_neighbors)
+            .map(|(i, d)| (i, d.into_inner()))
+            .collect()
         } else {
             Vec::new()
         }
     }
 }
 
-fn find_entrypoint<Layer: Graph, Elements: ElementContainer>(
+fn find_entrypoint<Layer: Graph, Elements: QueryDist<Query>, Query>(
     layers: &[Layer],
     elements: &Elements,
-    element: &Elements::Element,
+    element: &Query,
+    context: &mut SearchContext,
 ) -> usize {
     let mut entrypoint = 0;
     for layer in layers {
-        let res = search_for_neighbors(layer, entrypoint, elements, element, 1);
+        let res =
+            search_for_neighbors_with_filter(layer, entrypoint, elements, element, 1, |_| true, usize::MAX, context);
 
         entrypoint = res[0].0;
     }
@@ -996,42 +1526,198 @@ fn find_entrypoint<Layer: Graph, Elements: ElementContainer>(
     entrypoint
 }
 
-fn search_for_neighbors<Layer: Graph + ?Sized, Elements: ElementContainer>(
+fn search_for_neighbors<Layer: Graph + ?Sized, Elements: QueryDist<Query>, Query>(
     layer: &Layer,
     entrypoint: usize,
     elements: &Elements,
-    goal: &Elements::Element,
+    goal: &Query,
     max_search: usize,
 ) -> Vec<(usize, NotNan<f32>)> {
-    let mut res: max_size_heap::MaxSizeHeap<(NotNan<f32>, usize)> = max_size_heap::MaxSizeHeap::new(max_search); // TODO: should this really be max_search or num_neighbors?
-    let mut pq: BinaryHeap<cmp::Reverse<_>> = BinaryHeap::new();
+    search_for_neighbors_with_filter(
+        layer,
+        entrypoint,
+        elements,
+        goal,
+        max_search,
+        |_| true,
+        usize::MAX,
+        &mut SearchContext::for_single_search(max_search),
+    )
+}
 
-    let num_neighbors = 20; //layer.at(0).len();
-    let mut visited = HashSet::with_capacity_and_hasher(max_search * num_neighbors, FxBuildHasher::default());
+/// Searches for neighbors of `goal` in `layer`. All nodes are traversed, but only nodes passing
+/// `filter` are included in the result. The search terminates once `max_search` results have been
+/// found and no closer candidates remain, i.e., a selective filter results in more nodes being
+/// visited, or once `max_expansions` nodes have been expanded.
+#[allow(clippy::too_many_arguments)]
+fn search_for_neighbors_with_filter<Layer: Graph + ?Sized, Elements: QueryDist<Query>, Query>(
+    layer: &Layer,
+    entrypoint: usize,
+    elements: &Elements,
+    goal: &Query,
+    max_search: usize,
+    filter: impl Fn(usize) -> bool,
+    max_expansions: usize,
+    context: &mut SearchContext,
+) -> Vec<(usize, NotNan<f32>)> {
+    begin_search(entrypoint, elements, goal, context);
+
+    let mut res = max_size_heap::MaxSizeHeap::with_heap(std::mem::take(&mut context.res), max_search); // TODO: should this really be max_search or num_neighbors?
+
+    expand_search(
+        layer,
+        elements,
+        goal,
+        &mut res,
+        filter,
+        max_expansions,
+        None,
+        |_| {},
+        context,
+    );
+
+    let mut res = res.into_sorted_vec();
+    let neighbors = res.iter().map(|&(d, idx)| (idx, d)).collect();
+
+    // keep the allocation for the next search
+    res.clear();
+    context.res = BinaryHeap::from(res);
+
+    neighbors
+}
 
-    let distance = elements.dist_to_element(entrypoint, goal);
+/// Clears `context` and adds `entrypoint` as the only candidate.
+fn begin_search<Elements: QueryDist<Query>, Query>(
+    entrypoint: usize,
+    elements: &Elements,
+    goal: &Query,
+    context: &mut SearchContext,
+) {
+    context.clear();
 
-    pq.push(cmp::Reverse((distance, entrypoint)));
+    let distance = elements.dist_to_query(entrypoint, goal);
+    context.pq.push(cmp::Reverse((distance, entrypoint)));
+    context.visited.insert(entrypoint);
 
-    visited.insert(entrypoint);
+    if let Some(stats) = &mut context.stats {
+        stats.begin_layer(entrypoint);
+    }
+}
 
-    while let Some(cmp::Reverse((d, idx))) = pq.pop() {
-        if res.is_full() && d > res.peek().unwrap().0 {
+/// Expands the closest candidates in `context` until `res` is full and no candidate is closer than
+/// the furthest element in `res`, or until `max_expansions` nodes have been expanded. The
+/// candidate ending the search is kept in `context`, i.e., the search can be resumed.
+///
+/// If `max_dist` is given, candidates within `max_dist` are always expanded, i.e., `res` only bounds
+/// the search beyond `max_dist`.
+///
+/// Visited nodes that cannot be part of `res`, i.e., neighbors further away than all elements in
+/// `res` and elements pushed out of `res`, are passed to `discard`.
+#[allow(clippy::too_many_arguments)]
+fn expand_search<Layer: Graph + ?Sized, Elements: QueryDist<Query>, Query>(
+    layer: &Layer,
+    elements: &Elements,
+    goal: &Query,
+    res: &mut max_size_heap::MaxSizeHeap<(NotNan<f32>, usize)>,
+    filter: impl Fn(usize) -> bool,
+    max_expansions: usize,
+    max_dist: Option<NotNan<f32>>,
+    mut discard: impl FnMut((NotNan<f32>, usize)),
+    context: &mut SearchContext,
+) {
+    let SearchContext {
+        pq,
+        neighbors,
+        visited,
+        stats,
+        ..
+    } = context;
+
+    let is_within = |d: NotNan<f32>| max_dist.map_or(false, |max_dist| d <= max_dist);
+
+    let mut num_expanded = 0;
+    while let Some(&cmp::Reverse((d, idx))) = pq.peek() {
+        if (res.is_full() && d > res.peek().unwrap().0 && !is_within(d)) || num_expanded == max_expansions {
+            if let Some(stats) = stats {
+                stats.max_search_reached = true;
+            }
             break;
         }
+        pq.pop();
+        num_expanded += 1;
 
-        res.push((d, idx));
+        if filter(idx) {
+            if res.is_full() {
+                discard(cmp::max(*res.peek().unwrap(), (d, idx)));
+            }
+            res.push((d, idx));
+        }
 
-        for neighbor_idx in layer.get_neighbors(idx) {
-            if visited.insert(neighbor_idx) {
-                let distance = elements.dist_to_element(neighbor_idx, goal);
+        layer.get_neighbors_into(idx, neighbors);
+        neighbors.retain(|&neighbor_idx| visited.insert(neighbor_idx));
 
-                if !res.is_full() || distance < res.peek().unwrap().0 {
-                    pq.push(cmp::Reverse((distance, neighbor_idx)));
-                }
+        if let Some(stats) = stats {
+            stats.expand(idx, neighbors.len());
+        }
+
+        let distances = elements.dists_to_query(goal, neighbors);
+
+        for (&neighbor_idx, distance) in neighbors.iter().zip(distances) {
+            if !res.is_full() || distance < res.peek().unwrap().0 || is_within(distance) {
+                pq.push(cmp::Reverse((distance, neighbor_idx)));
+            } else {
+                discard((distance, neighbor_idx));
             }
         }
     }
+}
 
-    res.into_sorted_vec().into_iter().map(|(d, idx)| (idx, d)).collect()
+/// Searches for all nodes within distance `max_dist` from `goal` in `layer`. Nodes within
+/// `max_dist` are always expanded, while the `max_search` closest nodes bound the expansion of the
+/// search beyond `max_dist`.
+fn search_for_neighbors_within<Layer: Graph + ?Sized, Elements: QueryDist<Query>, Query>(
+    layer: &Layer,
+    entrypoint: usize,
+    elements: &Elements,
+    goal: &Query,
+    max_dist: f32,
+    max_search: usize,
+    context: &mut SearchContext,
+) -> Vec<(usize, NotNan<f32>)> {
+    let max_dist = NotNan::new(max_dist).expect("max_dist is NaN");
+
+    begin_search(entrypoint, elements, goal, context);
+
+    let mut closest = max_size_heap::MaxSizeHeap::with_heap(std::mem::take(&mut context.res), cmp::max(1, max_search));
+
+    // every expanded node ends up either in closest or in discarded
+    let mut res: Vec<(usize, NotNan<f32>)> = Vec::new();
+    let mut discard = |(d, idx)| {
+        if d <= max_dist {
+            res.push((idx, d));
+        }
+    };
+
+    expand_search(
+        layer,
+        elements,
+        goal,
+        &mut closest,
+        |_| true,
+        usize::MAX,
+        Some(max_dist),
+        &mut discard,
+        context,
+    );
+
+    let mut closest = closest.into_sorted_vec();
+    closest.iter().for_each(|&candidate| discard(candidate));
+
+    // keep the allocation for the next search
+    closest.clear();
+    context.res = BinaryHeap::from(closest);
+
+    res.sort_unstable_by_key(|&(_, d)| d);
+
+    res
 }
@@ -0,0 +1,157 @@
+use super::Layers;
+use crate::slice_vector::FixedWidthSliceVector;
+use crate::FiveByteInt;
+
+use std::convert::TryFrom;
+
+/// The integer type used for storing the neighbors of each node while building an index (see
+/// [`GranneBuilder::with_neighbor_ids`](struct.GranneBuilder.html#method.with_neighbor_ids)).
+///
+/// The largest value of the type is reserved for marking unused neighbor slots, i.e., an index
+/// using `u32` neighbor ids can contain at most `2^32 - 2` elements. Wider ids allow for larger
+/// indexes at the cost of more memory while building.
+///
+/// This trait is implemented for `u32` (default), [`FiveByteInt`](struct.FiveByteInt.html) and
+/// `u64`, and cannot be implemented outside of this crate.
+pub trait NeighborId: sealed::Sealed + Copy + Eq + Send + Sync + 'static {
+    /// The value marking an unused neighbor slot.
+    const UNUSED: Self;
+
+    /// Converts `id` into a neighbor id. Panics if `id` does not fit.
+    fn from_usize(id: usize) -> Self;
+
+    /// Converts this neighbor id into a `usize`.
+    fn into_usize(self: Self) -> usize;
+}
+
+// `Layers` is only visible in this crate, while `Sealed` is (unnameable but) public
+#[allow(private_interfaces)]
+mod sealed {
+    use super::{FiveByteInt, FixedWidthSliceVector, Layers};
+
+    /// Supertrait of `NeighborId` preventing implementations outside of this crate.
+    pub trait Sealed: Clone {
+        fn into_layers(layers: Vec<FixedWidthSliceVector<'_, Self>>) -> Layers<'_>;
+
+        /// Returns owned copies of `layers` if they are stored using `Self` as neighbor ids.
+        fn copy_layers(layers: &Layers<'_>) -> Option<Vec<FixedWidthSliceVector<'static, Self>>>;
+    }
+
+    impl Sealed for u32 {
+        fn into_layers(layers: Vec<FixedWidthSliceVector<'_, Self>>) -> Layers<'_> {
+            Layers::FixWidth(layers)
+        }
+
+        fn copy_layers(layers: &Layers<'_>) -> Option<Vec<FixedWidthSliceVector<'static, Self>>> {
+            if let Layers::FixWidth(layers) = layers {
+                Some(layers.iter().map(|layer| layer.borrow().into_owned()).collect())
+            } else {
+                None
+            }
+        }
+    }
+
+    impl Sealed for FiveByteInt {
+        fn into_layers(layers: Vec<FixedWidthSliceVector<'_, Self>>) -> Layers<'_> {
+            Layers::FixWidth40(layers)
+        }
+
+        fn copy_layers(layers: &Layers<'_>) -> Option<Vec<FixedWidthSliceVector<'static, Self>>> {
+            if let Layers::FixWidth40(layers) = layers {
+                Some(layers.iter().map(|layer| layer.borrow().into_owned()).collect())
+            } else {
+                None
+            }
+        }
+    }
+
+    impl Sealed for u64 {
+        fn into_layers(layers: Vec<FixedWidthSliceVector<'_, Self>>) -> Layers<'_> {
+            Layers::FixWidth64(layers)
+        }
+
+        fn copy_layers(layers: &Layers<'_>) -> Option<Vec<FixedWidthSliceVector<'static, Self>>> {
+            if let Layers::FixWidth64(layers) = layers {
+                Some(layers.iter().map(|layer| layer.borrow().into_owned()).collect())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl NeighborId for u32 {
+    const UNUSED: Self = std::u32::MAX;
+
+    #[inline(always)]
+    fn from_usize(id: usize) -> Self {
+        u32::try_from(id).unwrap()
+    }
+
+    #[inline(always)]
+    fn into_usize(self: Self) -> usize {
+        usize::try_from(self).unwrap()
+    }
+}
+
+impl NeighborId for FiveByteInt {
+    const UNUSED: Self = FiveByteInt::max_value();
+
+    #[inline(always)]
+    fn from_usize(id: usize) -> Self {
+        id.into()
+    }
+
+    #[inline(always)]
+    fn into_usize(self: Self) -> usize {
+        self.into()
+    }
+}
+
+impl NeighborId for u64 {
+    const UNUSED: Self = std::u64::MAX;
+
+    #[inline(always)]
+    fn from_usize(id: usize) -> Self {
+        id as u64
+    }
+
+    #[inline(always)]
+    fn into_usize(self: Self) -> usize {
+        usize::try_from(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversions<Id: NeighborId>(max_id: usize) {
+        for &id in &[0, 1, 1234, max_id] {
+            assert_eq!(id, Id::from_usize(id).into_usize());
+        }
+
+        assert!(Id::from_usize(max_id) != Id::UNUSED);
+    }
+
+    #[test]
+    fn u32_conversions() {
+        conversions::<u32>((1 << 32) - 2);
+    }
+
+    #[test]
+    fn five_byte_int_conversions() {
+        conversions::<FiveByteInt>((1 << 40) - 2);
+    }
+
+    #[test]
+    fn u64_conversions() {
+        conversions::<u64>(1 << 50);
+    }
+
+    #[test]
+    #[should_panic]
+    fn u32_overflow() {
+        u32::from_usize(1 << 32);
+    }
+}
@@ -148,7 +148,7 @@ impl<'a, Elements: ElementContainer + Permutable + Sync> Granne<'a, Elements> {
 
                     let mut eps =
                         find_entrypoint_trail(&self.layers.load(), &self.elements, layer, &self.get_element(idx));
-                    eps.iter_mut().for_each(|i| *i = order_inv[*i as usize] as NeighborId);
+                    eps.iter_mut().for_each(|i| *i = order_inv[*i]);
 
                     (eps, idx)
                 })
@@ -182,52 +182,43 @@ fn find_entrypoint_trail<Elements: ElementContainer>(
     elements: &Elements,
     max_layer: usize,
     element: &Elements::Element,
-) -> [NeighborId; NUM_LAYERS] {
+) -> [usize; NUM_LAYERS] {
     fn _find_entrypoint_trail<Layer: Graph, Elements: ElementContainer>(
         layers: &[Layer],
         elements: &Elements,
         max_layer: usize,
         element: &Elements::Element,
-    ) -> [NeighborId; NUM_LAYERS] {
-        let mut eps: [NeighborId; NUM_LAYERS] = [0; NUM_LAYERS];
+    ) -> [usize; NUM_LAYERS] {
+        let mut eps: [usize; NUM_LAYERS] = [0; NUM_LAYERS];
         for (i, layer) in layers.iter().enumerate().take(cmp::min(NUM_LAYERS, max_layer)) {
             let ep = if i == 0 { 0 } else { eps[i] };
             let max_search = 1;
-            let res = search_for_neighbors(layer, ep.try_into().unwrap(), elements, element, max_search);
-            use std::convert::TryInto;
-            eps[i] = res[0].0.try_into().unwrap();
+            let res = search_for_neighbors(layer, ep, elements, element, max_search);
+            eps[i] = res[0].0;
         }
         eps
     }
 
-    match layers {
-        Layers::FixWidth(layers) => _find_entrypoint_trail(layers, elements, max_layer, element),
-        Layers::Compressed(layers) => _find_entrypoint_trail(layers, elements, max_layer, element),
-    }
+    with_layers!(layers, layers => _find_entrypoint_trail(layers, elements, max_layer, element))
 }
 
 fn reorder_layers(layers: &Layers, mapping: &[usize], show_progress: bool) -> Layers<'static> {
     let reverse_mapping = get_reverse_mapping(mapping);
-    match layers {
-        Layers::FixWidth(ref layers) => Layers::Compressed(
-            layers
-                .iter()
-                .map(|layer| reorder_layer(layer, mapping, &reverse_mapping, show_progress))
-                .collect(),
-        ),
-        Layers::Compressed(ref layers) => Layers::Compressed(
-            layers
-                .iter()
-                .map(|layer| reorder_layer(layer, mapping, &reverse_mapping, show_progress))
-                .collect(),
-        ),
-    }
+    let wide = layers.is_wide();
+
+    with_layers!(layers, layers => Layers::Compressed(
+        layers
+            .iter()
+            .map(|layer| reorder_layer(layer, mapping, &reverse_mapping, wide, show_progress))
+            .collect(),
+    ))
 }
 
 fn reorder_layer<Layer: Graph + Sync + Send>(
     layer: &Layer,
     mapping: &[usize],
     reverse_mapping: &[usize],
+    wide: bool,
     show_progress: bool,
 ) -> MultiSetVector<'static> {
     let mut progress_bar = if show_progress {
@@ -236,7 +227,11 @@ fn reorder_layer<Layer: Graph + Sync + Send>(
         None
     };
 
-    let mut new_layer = MultiSetVector::new();
+    let mut new_layer = if wide {
+        MultiSetVector::new_wide()
+    } else {
+        MultiSetVector::new()
+    };
 
     let chunk_size = std::cmp::max(10_000, layer.len() / 1000);
     let chunks: Vec<_> = mapping[..layer.len()]
@@ -246,12 +241,7 @@ fn reorder_layer<Layer: Graph + Sync + Send>(
             let mut layer_chunk = Vec::new();
 
             for &id in c {
-                layer_chunk.extend(
-                    layer
-                        .get_neighbors(id)
-                        .into_iter()
-                        .map(|n| NeighborId::try_from(reverse_mapping[n]).unwrap()),
-                );
+                layer_chunk.extend(layer.get_neighbors(id).into_iter().map(|n| reverse_mapping[n]));
 
                 offset_chunk.push(layer_chunk.len());
             }
@@ -262,7 +252,7 @@ fn reorder_layer<Layer: Graph + Sync + Send>(
 
     for (offset, chunk) in chunks {
         for i in 1..offset.len() {
-            new_layer.push(&chunk[offset[i - 1]..offset[i]]);
+            new_layer.push_ids(&chunk[offset[i - 1]..offset[i]]);
         }
 
         if let Some(ref mut progress_bar) = progress_bar {
@@ -12,10 +12,10 @@ use crate::slice_vector::FixedWidthSliceVector;
 mod rw_lock_slice_vector;
 
 /// A version of GranneBuilder that can build and search concurrently.
-pub struct RwGranneBuilder<Elements: ExtendableElementContainer + Writeable> {
+pub struct RwGranneBuilder<Elements: ExtendableElementContainer + Writeable, Id: NeighborId = u32> {
     layers: RwLock<(
-        rw_lock_slice_vector::RwLockSliceVector,
-        Vec<FixedWidthSliceVector<'static, NeighborId>>,
+        rw_lock_slice_vector::RwLockSliceVector<Id>,
+        Vec<FixedWidthSliceVector<'static, Id>>,
     )>,
     elements: RwLock<Elements>,
     config: BuildConfig,
@@ -25,11 +25,12 @@ pub struct RwGranneBuilder<Elements: ExtendableElementContainer + Writeable> {
     write_lock: RwLock<()>,
 }
 
-impl<Elements> RwGranneBuilder<Elements>
+impl<Elements, Id> RwGranneBuilder<Elements, Id>
 where
     Elements: ExtendableElementContainer + Writeable + Sync + Send + Clone,
+    Id: NeighborId,
 {
-    pub fn new(builder: GranneBuilder<Elements>, max_elements: usize, num_threads: usize) -> Self {
+    pub fn new(builder: GranneBuilder<Elements, Id>, max_elements: usize, num_threads: usize) -> Self {
         let mut builder = builder;
         builder.config.expected_num_elements = Some(max_elements);
 
@@ -45,7 +46,7 @@ where
             compute_num_elements_in_layer(max_elements, builder.config.layer_multiplier, builder.layers.len()),
         );
 
-        current_layer.resize(num_elements_in_layer, UNUSED);
+        current_layer.resize(num_elements_in_layer, Id::UNUSED);
 
         Self {
             layers: RwLock::new((current_layer.into(), builder.layers)),
@@ -79,7 +80,7 @@ where
 
         // this is safe because we have a write lock on self.write_lock, so no writes
         // can happen
-        let last_layer: &[NeighborId] = unsafe { current_layer.as_owner() };
+        let last_layer: &[Id] = unsafe { current_layer.as_owner() };
         let last_layer = FixedWidthSliceVector::with_data(last_layer, self.config.num_neighbors);
 
         let layers = if elements.len() > 0 {
@@ -92,7 +93,7 @@ where
             vec![]
         };
 
-        let layers: Layers = Layers::FixWidth(layers.iter().map(|layer| layer.borrow()).collect());
+        let layers: Layers = Id::into_layers(layers.iter().map(|layer| layer.borrow()).collect());
         io::write_index(&layers, index_file)
     }
 
@@ -129,7 +130,7 @@ where
                         prev_layers.len(),
                     );
 
-                    new_layer.resize(num_elements_in_layer, UNUSED);
+                    new_layer.resize(num_elements_in_layer, Id::UNUSED);
                     new_layer.into()
                 };
             }
@@ -152,19 +153,31 @@ where
             let elements = parking_lot::RwLockWriteGuard::downgrade(elements);
 
             let index = Granne::from_parts(
-                Layers::FixWidth(layers.iter().map(|layer| layer.borrow()).collect()),
+                Id::into_layers(layers.iter().map(|layer| layer.borrow()).collect()),
                 &*elements,
             );
 
             if self.pool.current_num_threads() > 1 {
                 self.pool.install(|| {
                     ids.par_iter().for_each(|id| {
-                        GranneBuilder::index_element(&self.config, &*elements, &index, current_layer.as_slice(), *id)
+                        GranneBuilder::<Elements, Id>::index_element(
+                            &self.config,
+                            &*elements,
+                            &index,
+                            current_layer.as_slice(),
+                            *id,
+                        )
                     })
                 });
             } else {
                 ids.iter().for_each(|id| {
-                    GranneBuilder::index_element(&self.config, &*elements, &index, current_layer.as_slice(), *id)
+                    GranneBuilder::<Elements, Id>::index_element(
+                        &self.config,
+                        &*elements,
+                        &index,
+                        current_layer.as_slice(),
+                        *id,
+                    )
                 })
             }
 
@@ -191,11 +204,11 @@ where
         let (ref current_layer, ref layers) = *self.layers.read();
 
         let index = Granne::from_parts(
-            Layers::FixWidth(layers.iter().map(|layer| layer.borrow()).collect()),
+            Id::into_layers(layers.iter().map(|layer| layer.borrow()).collect()),
             &*elements,
         );
 
-        if let Some((entrypoint, _)) = index.search(&element, 1, 1).first() {
+        if let Some((entrypoint, _)) = index.search(element, 1, 1).first() {
             super::search_for_neighbors(current_layer.as_slice(), *entrypoint, &*elements, element, max_search)
                 .into_iter()
                 .take(num_neighbors)
@@ -4,22 +4,22 @@ use parking_lot::RwLock;
 
 use super::NeighborId;
 
-pub struct RwLockSliceVector {
-    data: owning_ref::OwningHandle<Vec<NeighborId>, Vec<RwLock<&'static mut [NeighborId]>>>,
+pub struct RwLockSliceVector<Id: NeighborId> {
+    data: owning_ref::OwningHandle<Vec<Id>, Vec<RwLock<&'static mut [Id]>>>,
     width: usize,
 }
 
-impl RwLockSliceVector {
-    pub fn new(data: Vec<NeighborId>, width: usize) -> Self {
+impl<Id: NeighborId> RwLockSliceVector<Id> {
+    pub fn new(data: Vec<Id>, width: usize) -> Self {
         Self {
             data: owning_ref::OwningHandle::new_with_fn(data, |d| unsafe {
-                (*(d as *mut [NeighborId])).chunks_mut(width).map(RwLock::new).collect()
+                (*(d as *mut [Id])).chunks_mut(width).map(RwLock::new).collect()
             }),
             width,
         }
     }
 
-    pub fn as_slice(self: &Self) -> &[RwLock<&'static mut [NeighborId]>] {
+    pub fn as_slice(self: &Self) -> &[RwLock<&'static mut [Id]>] {
         &*self.data
     }
 
@@ -28,28 +28,28 @@ impl RwLockSliceVector {
     }
 
     // this is unsafe because the underlying data may be modified through as_slice
-    pub unsafe fn as_owner(self: &Self) -> &[NeighborId] {
+    pub unsafe fn as_owner(self: &Self) -> &[Id] {
         self.data.as_owner()
     }
 
-    pub fn into_owner(self: Self) -> Vec<NeighborId> {
+    pub fn into_owner(self: Self) -> Vec<Id> {
         self.data.into_owner()
     }
 }
 
-impl Into<FixedWidthSliceVector<'static, NeighborId>> for RwLockSliceVector {
-    fn into(self: Self) -> FixedWidthSliceVector<'static, NeighborId> {
+impl<Id: NeighborId> Into<FixedWidthSliceVector<'static, Id>> for RwLockSliceVector<Id> {
+    fn into(self: Self) -> FixedWidthSliceVector<'static, Id> {
         let width = self.width;
         FixedWidthSliceVector::with_data(self.into_owner(), width)
     }
 }
 
-impl From<FixedWidthSliceVector<'static, NeighborId>> for RwLockSliceVector {
-    fn from(fw_vec: FixedWidthSliceVector<'static, NeighborId>) -> Self {
+impl<Id: NeighborId> From<FixedWidthSliceVector<'static, Id>> for RwLockSliceVector<Id> {
+    fn from(fw_vec: FixedWidthSliceVector<'static, Id>) -> Self {
         assert!(fw_vec.width() > 0);
 
         let width = fw_vec.width();
-        let data: Vec<NeighborId> = fw_vec.into();
+        let data: Vec<Id> = fw_vec.into();
 
         RwLockSliceVector::new(data, width)
     }
@@ -0,0 +1,219 @@
+use fxhash::FxBuildHasher;
+use ordered_float::NotNan;
+use std::cmp;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Scratch space used while searching an index, e.g., the set of visited nodes and the candidate
+/// heaps.
+///
+/// [`Granne::search`](struct.Granne.html#method.search) allocates new scratch space for each
+/// query. When running many queries, a `SearchContext` can be kept (e.g. one per thread) and
+/// passed to [`Granne::search_with_context`](struct.Granne.html#method.search_with_context) in
+/// order to reuse the allocations between queries. A context can be used with different indexes.
+///
+/// The visited nodes are tracked using a bitset over the node ids, which grows to the size of the
+/// largest index searched and is cleared incrementally between queries.
+pub struct SearchContext {
+    pub(super) pq: BinaryHeap<cmp::Reverse<(NotNan<f32>, usize)>>,
+    pub(super) res: BinaryHeap<(NotNan<f32>, usize)>,
+    pub(super) neighbors: Vec<usize>,
+    pub(super) visited: VisitedSet,
+    pub(super) stats: Option<SearchStats>,
+}
+
+impl SearchContext {
+    /// Creates a new `SearchContext`.
+    pub fn new() -> Self {
+        Self::with_visited(VisitedSet::Bits {
+            words: Vec::new(),
+            dirty: Vec::new(),
+        })
+    }
+
+    /// Creates a context for a single search visiting approximately `max_search` nodes. Uses a
+    /// hash set for the visited nodes, since allocating a bitset for all nodes would dominate a
+    /// single search.
+    pub(super) fn for_single_search(max_search: usize) -> Self {
+        let num_neighbors = 20; //layer.at(0).len();
+
+        Self::with_visited(VisitedSet::Hashed(HashSet::with_capacity_and_hasher(
+            max_search * num_neighbors,
+            FxBuildHasher::default(),
+        )))
+    }
+
+    fn with_visited(visited: VisitedSet) -> Self {
+        Self {
+            pq: BinaryHeap::new(),
+            res: BinaryHeap::new(),
+            neighbors: Vec::new(),
+            visited,
+            stats: None,
+        }
+    }
+
+    pub(super) fn clear(self: &mut Self) {
+        self.pq.clear();
+        self.res.clear();
+        self.neighbors.clear();
+        self.visited.clear();
+    }
+}
+
+impl Default for SearchContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Statistics collected during a single search, see
+/// [`Granne::search_with_stats`](struct.Granne.html#method.search_with_stats).
+///
+/// Per-layer statistics are indexed by layer, i.e., the first entry corresponds to the top layer
+/// and the last entry to the bottom layer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// The number of distances computed between the query and elements in the index.
+    pub num_distance_computations: usize,
+    /// The number of nodes visited (summed over all layers). Each visited node requires one
+    /// distance computation.
+    pub num_visited: usize,
+    /// The number of nodes expanded, i.e., nodes whose neighbors were visited, in each layer.
+    pub hops_per_layer: Vec<usize>,
+    /// The entrypoint of the bottom layer, found by searching the layers above it.
+    pub entrypoint: usize,
+    /// Whether the search in the bottom layer ended because `max_search` results had been found
+    /// and no closer candidates remained (or because the limit on the number of expanded nodes of a
+    /// filtered search was reached), as opposed to running out of candidates.
+    pub max_search_reached: bool,
+    /// The ids of the expanded nodes in each layer, in the order they were expanded. Only
+    /// collected if requested.
+    pub trace: Option<Vec<Vec<usize>>>,
+}
+
+impl SearchStats {
+    pub(super) fn new(trace: bool) -> Self {
+        Self {
+            trace: if trace { Some(Vec::new()) } else { None },
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn begin_layer(self: &mut Self, entrypoint: usize) {
+        self.num_distance_computations += 1;
+        self.num_visited += 1;
+        self.hops_per_layer.push(0);
+        self.entrypoint = entrypoint;
+        self.max_search_reached = false;
+
+        if let Some(trace) = &mut self.trace {
+            trace.push(Vec::new());
+        }
+    }
+
+    /// Records the expansion of `idx`, resulting in `num_visited` newly visited neighbors.
+    pub(super) fn expand(self: &mut Self, idx: usize, num_visited: usize) {
+        self.num_distance_computations += num_visited;
+        self.num_visited += num_visited;
+        *self.hops_per_layer.last_mut().unwrap() += 1;
+
+        if let Some(trace) = &mut self.trace {
+            trace.last_mut().unwrap().push(idx);
+        }
+    }
+}
+
+pub(super) enum VisitedSet {
+    Hashed(HashSet<usize, FxBuildHasher>),
+    /// A bitset over the node ids together with the indices of the non-zero words, which are the
+    /// only ones that need to be reset when clearing.
+    Bits {
+        words: Vec<u64>,
+        dirty: Vec<usize>,
+    },
+}
+
+impl VisitedSet {
+    /// Marks `idx` as visited. Returns `true` if `idx` was not visited before.
+    #[inline(always)]
+    pub(super) fn insert(self: &mut Self, idx: usize) -> bool {
+        match self {
+            VisitedSet::Hashed(visited) => visited.insert(idx),
+            VisitedSet::Bits { words, dirty } => {
+                let (word_idx, bit) = (idx / 64, 1u64 << (idx % 64));
+                if word_idx >= words.len() {
+                    words.resize(word_idx + 1, 0);
+                }
+
+                let word = &mut words[word_idx];
+                if *word & bit != 0 {
+                    return false;
+                }
+
+                if *word == 0 {
+                    dirty.push(word_idx);
+                }
+                *word |= bit;
+
+                true
+            }
+        }
+    }
+
+    fn clear(self: &mut Self) {
+        match self {
+            VisitedSet::Hashed(visited) => visited.clear(),
+            VisitedSet::Bits { words, dirty } => {
+                for word_idx in dirty.drain(..) {
+                    words[word_idx] = 0;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visited_set() {
+        for mut visited in vec![
+            SearchContext::new().visited,
+            SearchContext::for_single_search(10).visited,
+        ] {
+            for _ in 0..2 {
+                assert!(visited.insert(3));
+                assert!(visited.insert(1000));
+                assert!(visited.insert(64));
+                assert!(!visited.insert(3));
+                assert!(!visited.insert(1000));
+                assert!(visited.insert(2));
+
+                visited.clear();
+            }
+        }
+    }
+
+    #[test]
+    fn bitset_clears_only_dirty_words() {
+        let mut visited = SearchContext::new().visited;
+        visited.insert(1);
+        visited.insert(2);
+        visited.insert(130);
+
+        if let VisitedSet::Bits { words, dirty } = &visited {
+            assert_eq!(3, words.len());
+            assert_eq!(&vec![0, 2], dirty);
+        } else {
+            unreachable!();
+        }
+
+        visited.clear();
+
+        if let VisitedSet::Bits { words, dirty } = &visited {
+            assert!(words.iter().all(|&w| w == 0));
+            assert!(dirty.is_empty());
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use super::{begin_search, expand_search, find_entrypoint, Granne, Layers, SearchContext};
+use crate::{max_size_heap::MaxSizeHeap, QueryDist};
+
+use ordered_float::NotNan;
+use std::cmp;
+
+/// A search yielding the elements closest to a query lazily, in roughly increasing distance. Created
+/// by [`Granne::search_iter`](struct.Granne.html#method.search_iter).
+///
+/// The results are produced in pages of `max_search` elements. The first page contains the same
+/// elements as [`Granne::search`](struct.Granne.html#method.search) with the same `max_search`.
+/// Each following page continues the search from the candidates of the previous one, i.e., only
+/// the nodes needed for the new page are expanded.
+///
+/// All visited nodes that have not yet been yielded are kept as candidates, so the memory used by
+/// the cursor grows with the number of visited nodes (at most the number of elements in the
+/// index).
+pub struct SearchCursor<'b, Elements, Query> {
+    layers: Layers<'b>,
+    elements: &'b Elements,
+    query: &'b Query,
+    max_search: usize,
+    context: SearchContext,
+    discarded: Vec<(NotNan<f32>, usize)>,
+    page: Vec<(NotNan<f32>, usize)>,
+}
+
+impl<'b, Elements: QueryDist<Query>, Query> SearchCursor<'b, Elements, Query> {
+    pub(super) fn new(index: &'b Granne<'_, Elements>, query: &'b Query, max_search: usize) -> Self {
+        let mut cursor = Self {
+            layers: index.layers.load(),
+            elements: &index.elements,
+            query,
+            max_search: cmp::max(1, max_search),
+            context: SearchContext::new(),
+            discarded: Vec::new(),
+            page: Vec::new(),
+        };
+
+        let Self {
+            layers,
+            elements,
+            context,
+            ..
+        } = &mut cursor;
+
+        let entrypoint = with_layers!(layers, layers => {
+            layers
+                .split_last()
+                .map(|(_, top_layers)| find_entrypoint(top_layers, *elements, query, context))
+        });
+
+        if let Some(entrypoint) = entrypoint {
+            begin_search(entrypoint, cursor.elements, query, &mut cursor.context);
+        }
+
+        cursor
+    }
+
+    /// Continues the search until the next `max_search` elements have been found (unless the
+    /// search runs out of candidates).
+    fn next_page(self: &mut Self) {
+        let Self {
+            layers,
+            elements,
+            query,
+            max_search,
+            context,
+            discarded,
+            page,
+        } = self;
+
+        if layers.len() == 0 {
+            return;
+        }
+
+        // the nodes discarded for the previous page are candidates for this one
+        context.pq.extend(discarded.drain(..).map(cmp::Reverse));
+
+        let mut res = MaxSizeHeap::new(*max_search);
+        expand_search(
+            layers.as_graph(layers.len() - 1),
+            *elements,
+            *query,
+            &mut res,
+            |_| true,
+            usize::MAX,
+            None,
+            |candidate| discarded.push(candidate),
+            context,
+        );
+
+        *page = res.into_sorted_vec();
+        page.reverse();
+    }
+}
+
+impl<'b, Elements: QueryDist<Query>, Query> Iterator for SearchCursor<'b, Elements, Query> {
+    type Item = (usize, f32);
+
+    fn next(self: &mut Self) -> Option<Self::Item> {
+        if self.page.is_empty() {
+            self.next_page();
+        }
+
+        self.page.pop().map(|(distance, idx)| (idx, distance.into_inner()))
+    }
+}
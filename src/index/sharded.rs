@@ -0,0 +1,339 @@
+use super::{Granne, Index, Search};
+use crate::{ElementContainer, QueryDist};
+
+#[cfg(not(feature = "singlethreaded"))]
+use rayon::prelude::*;
+use serde_json;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// The name of the file listing the shards in a directory (see
+/// [`ShardedGranne::from_dir`](struct.ShardedGranne.html#method.from_dir)).
+pub const SHARD_MANIFEST_FILE_NAME: &str = "shards.json";
+
+/** An index consisting of several `Granne` indexes (shards), each containing a disjoint part of
+the elements.
+
+The elements of the shards are assigned global ids in shard order, i.e., element `i` in shard `s`
+has the id `i + offset(s)`, where `offset(s)` is the total number of elements in the preceding
+shards. This matches elements written by
+[`parse_elements_and_save_shards_to_disk`](embeddings/parsing/fn.parse_elements_and_save_shards_to_disk.html).
+
+Searches are run in parallel on all shards, after which the results are merged.
+
+`ShardedGranne` implements [`Search`](trait.Search.html), i.e., it can be used interchangeably with
+`Granne` and [`BruteForce`](struct.BruteForce.html) by code that only searches. It does not
+implement [`Index`](trait.Index.html), since the layers of the shards do not form the layers of a
+single index. The graph of each shard is available through [`shards`](#method.shards) (or in
+global ids through [`get_neighbors`](#method.get_neighbors)) and each shard can be written
+separately.
+
+# Examples
+```
+# use granne::*;
+# let shard_elements: Vec<angular::Vectors> = (0..3).map(|_| test_helper::random_vectors(3, 500)).collect();
+# let element = shard_elements[1].get_element(123).into_owned();
+let builders: Vec<_> = shard_elements
+    .into_iter()
+    .map(|elements| {
+        let mut builder = GranneBuilder::new(BuildConfig::default(), elements);
+        builder.build();
+        builder
+    })
+    .collect();
+
+let index = ShardedGranne::new(builders.iter().map(|builder| builder.get_index()).collect());
+assert_eq!(1500, index.len());
+
+let res = index.search(&element, 200, 10);
+assert_eq!(500 + 123, res[0].0);
+```
+ */
+pub struct ShardedGranne<'a, Elements> {
+    shards: Vec<Granne<'a, Elements>>,
+    offsets: Vec<usize>,
+}
+
+impl<'a, Elements: ElementContainer> ShardedGranne<'a, Elements> {
+    /// Creates a `ShardedGranne` from `shards`.
+    pub fn new(shards: Vec<Granne<'a, Elements>>) -> Self {
+        let mut offsets = Vec::with_capacity(shards.len() + 1);
+        offsets.push(0);
+        for shard in &shards {
+            offsets.push(offsets.last().unwrap() + shard.len());
+        }
+
+        Self { shards, offsets }
+    }
+
+    /// Returns the total number of elements in all shards.
+    pub fn len(self: &Self) -> usize {
+        *self.offsets.last().unwrap()
+    }
+
+    /// Returns `true` if there are no elements in any of the shards.
+    pub fn is_empty(self: &Self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of shards.
+    pub fn num_shards(self: &Self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the shards of this index.
+    pub fn shards(self: &Self) -> &[Granne<'a, Elements>] {
+        &self.shards
+    }
+
+    /// Returns the global id of the first element in `shard`.
+    pub fn shard_offset(self: &Self, shard: usize) -> usize {
+        self.offsets[shard]
+    }
+
+    /// Returns the shard containing the element with global id `index` and the id of the element
+    /// within that shard.
+    pub fn shard_and_local_id(self: &Self, index: usize) -> (usize, usize) {
+        assert!(index < self.len(), "Index out of bounds");
+
+        // the last shard starting at or before index, skipping empty shards
+        let shard = self.offsets.partition_point(|&offset| offset <= index) - 1;
+
+        (shard, index - self.offsets[shard])
+    }
+
+    /// Searches for the `num_neighbors` neighbors closest to `element` in all shards. `max_search`
+    /// is used for the search in each shard (see [`Granne::search`](struct.Granne.html#method.search)).
+    /// Returns a `Vec` containing the global id and distance from `element`.
+    pub fn search<Query>(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)>
+    where
+        Elements: QueryDist<Query> + Sync,
+        Query: Sync,
+    {
+        let search_shard = |(shard, offset): (&Granne<'a, Elements>, &usize)| {
+            shard
+                .search(element, max_search, num_neighbors)
+                .into_iter()
+                .map(|(idx, d)| (idx + offset, d))
+                .collect::<Vec<_>>()
+        };
+
+        #[cfg(feature = "singlethreaded")]
+        let shard_results = self.shards.iter().zip(&self.offsets).map(search_shard);
+        #[cfg(not(feature = "singlethreaded"))]
+        let shard_results = self.shards.par_iter().zip(&self.offsets).map(search_shard);
+
+        let mut res: Vec<(usize, f32)> = shard_results.flatten().collect();
+
+        res.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        res.truncate(num_neighbors);
+
+        res
+    }
+
+    /// Returns the element with global id `index`.
+    pub fn get_element(self: &Self, index: usize) -> Elements::Element {
+        let (shard, idx) = self.shard_and_local_id(index);
+
+        self.shards[shard].get_element(idx)
+    }
+
+    /// Returns the neighbors (global ids) of the element with global id `index` in the bottom layer
+    /// of its shard.
+    pub fn get_neighbors(self: &Self, index: usize) -> Vec<usize> {
+        let (shard, idx) = self.shard_and_local_id(index);
+        let shard_index = &self.shards[shard];
+
+        shard_index
+            .get_neighbors(idx, shard_index.num_layers() - 1)
+            .into_iter()
+            .map(|neighbor| neighbor + self.offsets[shard])
+            .collect()
+    }
+
+    /// Writes a manifest to `dir`, listing the index and elements files (relative to `dir`) of
+    /// each shard. The shards can then be loaded using [`from_dir`](#method.from_dir).
+    pub fn write_manifest(dir: &Path, shard_files: &[(&str, &str)]) -> std::io::Result<()> {
+        let shards: Vec<_> = shard_files
+            .iter()
+            .map(|(index, elements)| serde_json::json!({ "index": index, "elements": elements }))
+            .collect();
+
+        let mut file = BufWriter::new(File::create(dir.join(SHARD_MANIFEST_FILE_NAME))?);
+        file.write_all(serde_json::to_string(&serde_json::json!({ "shards": shards }))?.as_bytes())?;
+
+        file.flush()
+    }
+}
+
+impl<'a, Elements: ElementContainer + QueryDist<Query> + Sync, Query: Sync> Search<Query>
+    for ShardedGranne<'a, Elements>
+{
+    fn search(self: &Self, element: &Query, max_search: usize, num_neighbors: usize) -> Vec<(usize, f32)> {
+        ShardedGranne::search(self, element, max_search, num_neighbors)
+    }
+}
+
+impl<Elements: ElementContainer> ShardedGranne<'static, Elements> {
+    /// Loads the shards listed in the manifest in `dir` (see
+    /// [`write_manifest`](#method.write_manifest)). Each index is memory-mapped (see
+    /// [`Granne::from_file`](struct.Granne.html#method.from_file)), while the elements are loaded
+    /// from their file by `load_elements`.
+    ///
+    /// ## Safety
+    ///
+    /// This is unsafe because the underlying files can be modified, which would result in undefined
+    /// behavior. The caller needs to guarantee that the files are not modified while being
+    /// memory-mapped.
+    pub unsafe fn from_dir<F>(dir: &Path, mut load_elements: F) -> std::io::Result<Self>
+    where
+        F: FnMut(&File) -> std::io::Result<Elements>,
+    {
+        let manifest: serde_json::Value =
+            serde_json::from_reader(BufReader::new(File::open(dir.join(SHARD_MANIFEST_FILE_NAME))?))?;
+
+        let shard_files: Vec<serde_json::Value> = serde_json::from_value(manifest["shards"].clone())?;
+
+        let mut shards = Vec::with_capacity(shard_files.len());
+        for files in shard_files {
+            let index_file: String = serde_json::from_value(files["index"].clone())?;
+            let elements_file: String = serde_json::from_value(files["elements"].clone())?;
+
+            let elements = load_elements(&File::open(dir.join(elements_file))?)?;
+            shards.push(Granne::from_file(&File::open(dir.join(index_file))?, elements)?);
+        }
+
+        Ok(Self::new(shards))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{angular, test_helper, BruteForce, BuildConfig, Builder, Dist, GranneBuilder};
+
+    fn build_shards(sizes: &[usize]) -> Vec<GranneBuilder<angular::Vectors<'static>>> {
+        sizes
+            .iter()
+            .map(|&size| {
+                let elements: angular::Vectors = test_helper::random_vectors(5, size);
+                let mut builder = GranneBuilder::new(BuildConfig::default().num_neighbors(20).max_search(20), elements);
+                builder.build();
+                builder
+            })
+            .collect()
+    }
+
+    #[test]
+    fn search() {
+        let builders = build_shards(&[500, 0, 800, 300]);
+        let index = ShardedGranne::new(builders.iter().map(|builder| builder.get_index()).collect());
+
+        assert_eq!(4, index.num_shards());
+        assert_eq!(1600, index.len());
+        assert_eq!((2, 0), index.shard_and_local_id(500));
+        assert_eq!((3, 299), index.shard_and_local_id(1599));
+
+        for _ in 0..20 {
+            let query: angular::Vector = test_helper::random_vector(5);
+            let res = index.search(&query, 50, 10);
+
+            let mut expected: Vec<_> = builders
+                .iter()
+                .enumerate()
+                .flat_map(|(shard, builder)| {
+                    let offset = index.shard_offset(shard);
+                    builder
+                        .get_index()
+                        .search(&query, 50, 10)
+                        .into_iter()
+                        .map(move |(idx, d)| (idx + offset, d))
+                })
+                .collect();
+            expected.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            expected.truncate(10);
+
+            assert_eq!(expected, res);
+
+            for &(id, d) in &res {
+                assert!((index.get_element(id).dist(&query).into_inner() - d).abs() < 10.0 * std::f32::EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn search_trait() {
+        fn search_all<Q>(indexes: &[&dyn Search<Q>], query: &Q) -> Vec<Vec<(usize, f32)>> {
+            indexes.iter().map(|index| index.search(query, 50, 10)).collect()
+        }
+
+        let builders = build_shards(&[700]);
+        let index = builders[0].get_index();
+        let sharded = ShardedGranne::new(vec![builders[0].get_index()]);
+        let brute_force = BruteForce::new(index.get_elements());
+
+        for _ in 0..10 {
+            let query: angular::Vector = test_helper::random_vector(5);
+            let res = search_all(&[&index, &sharded, &brute_force], &query);
+
+            assert_eq!(index.search(&query, 50, 10), res[0]);
+            assert_eq!(res[0], res[1]);
+            assert_eq!(brute_force.search(&query, 50, 10), res[2]);
+        }
+    }
+
+    #[test]
+    fn get_neighbors() {
+        let builders = build_shards(&[50, 3000, 400]);
+        let index = ShardedGranne::new(builders.iter().map(|builder| builder.get_index()).collect());
+
+        for shard in 0..index.num_shards() {
+            let offset = index.shard_offset(shard);
+            let bottom_layer = builders[shard].num_layers() - 1;
+
+            for idx in 0..builders[shard].len() {
+                let expected: Vec<_> = builders[shard]
+                    .get_neighbors(idx, bottom_layer)
+                    .into_iter()
+                    .map(|neighbor| neighbor + offset)
+                    .collect();
+
+                assert_eq!(expected, index.get_neighbors(idx + offset));
+            }
+        }
+    }
+
+    #[test]
+    fn from_dir() {
+        let builders = build_shards(&[300, 200]);
+        let index = ShardedGranne::new(builders.iter().map(|builder| builder.get_index()).collect());
+
+        let dir = tempfile::tempdir().unwrap();
+        for (shard, builder) in builders.iter().enumerate() {
+            let mut index_file = File::create(dir.path().join(format!("index-{}.bin", shard))).unwrap();
+            builder.write_index(&mut index_file).unwrap();
+
+            let mut elements_file = File::create(dir.path().join(format!("elements-{}.bin", shard))).unwrap();
+            builder.write_elements(&mut elements_file).unwrap();
+        }
+
+        ShardedGranne::<angular::Vectors>::write_manifest(
+            dir.path(),
+            &[("index-0.bin", "elements-0.bin"), ("index-1.bin", "elements-1.bin")],
+        )
+        .unwrap();
+
+        let loaded = unsafe { ShardedGranne::from_dir(dir.path(), |file| angular::Vectors::from_file(file)).unwrap() };
+
+        assert_eq!(index.len(), loaded.len());
+        let mut neighbors = index.get_neighbors(250);
+        neighbors.sort();
+        assert_eq!(neighbors, loaded.get_neighbors(250));
+
+        for _ in 0..10 {
+            let query: angular::Vector = test_helper::random_vector(5);
+            assert_eq!(index.search(&query, 50, 10), loaded.search(&query, 50, 10));
+        }
+    }
+}
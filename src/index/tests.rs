@@ -1,6 +1,9 @@
 use super::*;
 
-use crate::{angular, angular_int, test_helper, Dist};
+use crate::{
+    angular, angular_int, dense, euclidean, fn_elements, hamming, inner_product, math, minhash, multi_vector, pq,
+    sparse, test_helper, Dist,
+};
 
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
@@ -19,7 +22,7 @@ fn select_neighbors() {
 
     candidates.sort_unstable_by_key(|&(_, d)| d);
 
-    let neighbors = GranneBuilder::select_neighbors(&other_elements.as_slice(), candidates.clone(), 10);
+    let neighbors = GranneBuilder::<_>::select_neighbors(&other_elements.as_slice(), candidates.clone(), 10);
 
     assert!(0 < neighbors.len() && neighbors.len() <= 10);
 
@@ -28,7 +31,7 @@ fn select_neighbors() {
         assert!(neighbors[i - 1].1 <= neighbors[i].1);
     }
 
-    let neighbors = GranneBuilder::select_neighbors(&other_elements.as_slice(), candidates.clone(), 60);
+    let neighbors = GranneBuilder::<_>::select_neighbors(&other_elements.as_slice(), candidates.clone(), 60);
 
     assert_eq!(candidates.len(), neighbors.len());
 
@@ -47,6 +50,15 @@ fn build_and_search<Elements: ElementContainer + Sync + Send + Clone>(elements:
     verify_search(&index, 0.95, 10);
 }
 
+fn build_random_angular(dim: usize, num_elements: usize) -> GranneBuilder<angular::Vectors<'static>> {
+    let elements: angular::Vectors = test_helper::random_vectors(dim, num_elements);
+
+    let mut builder = GranneBuilder::new(BuildConfig::default().num_neighbors(20).max_search(20), elements);
+    builder.build();
+
+    builder
+}
+
 fn verify_search<Elements: ElementContainer>(index: &Granne<Elements>, precision: f32, max_search: usize) {
     let mut num_found = 0;
     for i in 0..index.len() {
@@ -131,6 +143,511 @@ fn build_and_search_int8() {
     build_and_search(elements);
 }
 
+#[test]
+fn search_int8_with_float_query() {
+    const DIM: usize = 32;
+
+    let vectors: Vec<angular::Vector> = (0..500)
+        .map(|_| test_helper::random_vector::<angular::Vector>(DIM))
+        .collect();
+    let elements: angular_int::Vectors = vectors.iter().map(|v| v.clone().into_vec().into()).collect();
+
+    let mut builder = GranneBuilder::new(BuildConfig::default().num_neighbors(20).max_search(20), elements);
+    builder.build();
+    let index = builder.get_index();
+
+    let mut num_found = 0;
+    for (i, vector) in vectors.iter().enumerate() {
+        // the query is not quantized
+        let res = index.search(vector, 10, 1);
+        if res[0].0 == i {
+            num_found += 1;
+        }
+
+        let expected = angular::Vector::from(
+            index
+                .get_element(i)
+                .into_vec()
+                .iter()
+                .map(|&x| x as f32)
+                .collect::<Vec<f32>>(),
+        )
+        .dist(vector)
+        .into_inner();
+        assert!((expected - index.get_elements().dist_to_query(i, vector).into_inner()).abs() < DIST_EPSILON);
+    }
+
+    assert!(0.95 < num_found as f32 / vectors.len() as f32);
+}
+
+#[test]
+fn search_reranked() {
+    const DIM: usize = 32;
+
+    let vectors: angular::Vectors = test_helper::random_vectors(DIM, 500);
+    let elements: angular_int::Vectors = (0..vectors.len()).map(|i| vectors.get(i).into_vec().into()).collect();
+
+    let mut builder = GranneBuilder::new(BuildConfig::default().num_neighbors(20).max_search(20), elements);
+    builder.build();
+    let index = builder.get_index();
+
+    let mut num_found = 0;
+    for i in 0..vectors.len() {
+        let vector = vectors.get(i);
+        let res = index.search_reranked(&vector, &vectors, 10, 5, 4);
+
+        assert_eq!(5, res.len());
+        if res[0].0 == i {
+            num_found += 1;
+        }
+
+        for &(j, d) in &res {
+            assert!((vectors.dist_to_element(j, &vector).into_inner() - d).abs() < DIST_EPSILON);
+        }
+
+        for k in 1..res.len() {
+            assert!(res[k - 1].1 <= res[k].1);
+        }
+    }
+
+    assert!(0.95 < num_found as f32 / vectors.len() as f32);
+}
+
+#[test]
+fn search_with_filter() {
+    const DIM: usize = 5;
+
+    let builder = build_random_angular(DIM, 2000);
+    let index = builder.get_index();
+
+    let mut num_found = 0;
+    for _ in 0..100 {
+        let query: angular::Vector = test_helper::random_vector(DIM);
+        let res = index.search_with_filter(&query, 50, 10, |id| id % 3 == 0);
+
+        assert_eq!(10, res.len());
+        assert!(res.iter().all(|&(id, _)| id % 3 == 0));
+
+        for k in 1..res.len() {
+            assert!(res[k - 1].1 <= res[k].1);
+        }
+
+        let expected = (0..index.len())
+            .filter(|id| id % 3 == 0)
+            .min_by_key(|&id| index.get_elements().dist_to_element(id, &query))
+            .unwrap();
+
+        if res[0].0 == expected {
+            num_found += 1;
+        }
+    }
+
+    assert!(90 < num_found);
+}
+
+#[test]
+fn search_with_selective_filter() {
+    let builder = build_random_angular(5, 2000);
+    let index = builder.get_index();
+
+    let query: angular::Vector = test_helper::random_vector(5);
+    let passing = [17, 555, 1999];
+
+    // the number of nodes expanded in the bottom layer is bounded, even though few elements pass
+    // the filter
+    let max_search = 10;
+    let num_filter_calls = std::cell::Cell::new(0);
+    let filter = |id| {
+        num_filter_calls.set(num_filter_calls.get() + 1);
+        passing.contains(&id)
+    };
+
+    let mut context = SearchContext::for_single_search(max_search);
+    context.stats = Some(SearchStats::new(false));
+    let res = index.search_with_filter_and_context(&query, max_search, 10, filter, &mut context);
+    let stats = context.stats.take().unwrap();
+
+    let bottom_layer_hops = *stats.hops_per_layer.last().unwrap();
+    assert_eq!(FILTERED_SEARCH_EXPANSION_FACTOR * max_search, bottom_layer_hops);
+    assert!(stats.max_search_reached);
+    // the filter is only applied to the expanded nodes of the bottom layer
+    assert_eq!(bottom_layer_hops, num_filter_calls.get());
+
+    assert!(res.len() <= passing.len());
+    assert!(res.iter().all(|(id, _)| passing.contains(id)));
+    assert_eq!(
+        res,
+        index.search_with_filter(&query, max_search, 10, |id| passing.contains(&id))
+    );
+
+    assert!(index.search_with_filter(&query, 10, 10, |_| false).is_empty());
+    assert_eq!(
+        index.search(&query, 10, 10),
+        index.search_with_filter(&query, 10, 10, |_| true)
+    );
+}
+
+#[test]
+fn search_batch() {
+    const DIM: usize = 5;
+
+    let queries: Vec<angular::Vector> = (0..200).map(|_| test_helper::random_vector(DIM)).collect();
+    let builder = build_random_angular(DIM, 1000);
+    let index = builder.get_index();
+
+    let res = index.search_batch(&queries, 20, 5);
+
+    assert_eq!(queries.len(), res.len());
+    for (query, res) in queries.iter().zip(&res) {
+        assert_eq!(&index.search(query, 20, 5), res);
+    }
+
+    assert!(index.search_batch(&[] as &[angular::Vector], 20, 5).is_empty());
+}
+
+#[test]
+fn search_with_context() {
+    const DIM: usize = 5;
+
+    let mut context = SearchContext::new();
+    for &num_elements in &[1000, 200, 3000] {
+        let elements: angular::Vectors = test_helper::random_vectors(DIM, num_elements);
+        let queries: Vec<angular::Vector> = (0..50).map(|_| test_helper::random_vector(DIM)).collect();
+
+        let mut builder = GranneBuilder::new(
+            BuildConfig::default().num_neighbors(20).max_search(20),
+            elements.borrow(),
+        );
+        builder.build();
+        let index = builder.get_index();
+
+        for query in &queries {
+            assert_eq!(
+                index.search(query, 20, 5),
+                index.search_with_context(query, 20, 5, &mut context)
+            );
+        }
+
+        // compressed layers
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        builder.write_index(&mut buffer).unwrap();
+        let buffer = buffer.into_inner();
+        let index = Granne::from_bytes(&buffer, &elements);
+
+        for query in &queries {
+            assert_eq!(
+                index.search(query, 50, 10),
+                index.search_with_context(query, 50, 10, &mut context)
+            );
+        }
+    }
+}
+
+#[test]
+fn search_with_stats() {
+    const DIM: usize = 5;
+
+    let builder = build_random_angular(DIM, 2000);
+    let index = builder.get_index();
+
+    for _ in 0..20 {
+        let query: angular::Vector = test_helper::random_vector(DIM);
+
+        let (res, stats) = index.search_with_stats(&query, 50, 10, false);
+        assert_eq!(index.search(&query, 50, 10), res);
+        assert!(stats.trace.is_none());
+        assert!(stats.max_search_reached);
+
+        let (traced_res, traced_stats) = index.search_with_stats(&query, 50, 10, true);
+        assert_eq!(res, traced_res);
+
+        let trace = traced_stats.trace.clone().unwrap();
+        assert_eq!(
+            SearchStats {
+                trace: None,
+                ..traced_stats
+            },
+            stats
+        );
+
+        assert_eq!(index.num_layers(), stats.hops_per_layer.len());
+        assert_eq!(
+            stats.hops_per_layer,
+            trace.iter().map(|layer| layer.len()).collect::<Vec<_>>()
+        );
+
+        // the search starts at node 0 in the top layer and at the entrypoint in the bottom layer
+        assert_eq!(0, trace[0][0]);
+        assert_eq!(stats.entrypoint, trace[index.num_layers() - 1][0]);
+
+        assert_eq!(stats.num_visited, stats.num_distance_computations);
+        assert!(stats.num_visited >= stats.hops_per_layer.iter().sum::<usize>());
+        assert!(stats.num_visited < index.len());
+    }
+
+    // the search runs out of candidates before max_search results have been found
+    let query: angular::Vector = test_helper::random_vector(DIM);
+    let (res, stats) = index.search_with_stats(&query, 2 * index.len(), 10, false);
+    assert_eq!(10, res.len());
+    assert!(!stats.max_search_reached);
+    assert_eq!(index.len(), *stats.hops_per_layer.last().unwrap());
+}
+
+#[test]
+fn search_iter() {
+    const DIM: usize = 5;
+
+    let builder = build_random_angular(DIM, 2000);
+    let index = builder.get_index();
+
+    for _ in 0..50 {
+        let query: angular::Vector = test_helper::random_vector(DIM);
+
+        let mut cursor = index.search_iter(&query, 50);
+        let first_page: Vec<_> = cursor.by_ref().take(10).collect();
+        let second_page: Vec<_> = cursor.by_ref().take(10).collect();
+
+        // paginating gives the same results as fetching all at once
+        let all: Vec<_> = index.search_iter(&query, 50).take(20).collect();
+        assert_eq!(all, [first_page.clone(), second_page].concat());
+
+        for &(id, d) in &all {
+            assert!((index.get_elements().dist_to_element(id, &query).into_inner() - d).abs() < DIST_EPSILON);
+        }
+
+        // the first page is the same as the result of a regular search
+        assert_eq!(index.search(&query, 50, 10), first_page);
+
+        // later pages continue the search without yielding any element twice
+        let pages: Vec<_> = index.search_iter(&query, 10).take(30).collect();
+        assert_eq!(index.search(&query, 10, 10), pages[..10]);
+        let mut ids: Vec<_> = pages.iter().map(|&(id, _)| id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(30, ids.len());
+    }
+
+    // all elements are eventually yielded (once)
+    let query: angular::Vector = test_helper::random_vector(DIM);
+    let mut ids: Vec<_> = index.search_iter(&query, 10).map(|(id, _)| id).collect();
+    ids.sort();
+    assert_eq!((0..index.len()).collect::<Vec<_>>(), ids);
+}
+
+#[test]
+fn search_diversified() {
+    const DIM: usize = 5;
+
+    let builder = build_random_angular(DIM, 2000);
+    let index = builder.get_index();
+
+    let min_pairwise_dist = |res: &[(usize, f32)]| {
+        let mut min_dist = std::f32::INFINITY;
+        for (i, &(a, _)) in res.iter().enumerate() {
+            for &(b, _) in &res[i + 1..] {
+                min_dist = min_dist.min(index.get_elements().dist(a, b).into_inner());
+            }
+        }
+        min_dist
+    };
+
+    for _ in 0..20 {
+        let query: angular::Vector = test_helper::random_vector(DIM);
+
+        let res = index.search(&query, 100, 10);
+        assert_eq!(res, index.search_diversified(&query, 100, 10, 1.0));
+
+        let diversified = index.search_diversified(&query, 100, 10, 0.5);
+        assert_eq!(10, diversified.len());
+        assert_eq!(res[0], diversified[0]);
+
+        let candidates = index.search(&query, 100, 100);
+        for &(id, d) in &diversified {
+            assert!(candidates.iter().any(|&(c, _)| c == id));
+            assert!((index.get_elements().dist_to_element(id, &query).into_inner() - d).abs() < DIST_EPSILON);
+        }
+
+        let mut ids: Vec<_> = diversified.iter().map(|&(id, _)| id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(10, ids.len());
+
+        assert!(min_pairwise_dist(&res) <= min_pairwise_dist(&diversified));
+    }
+}
+
+#[test]
+fn search_within() {
+    const DIM: usize = 5;
+
+    let builder = build_random_angular(DIM, 2000);
+    let index = builder.get_index();
+
+    let max_dist = 0.2;
+    let mut num_expected = 0;
+    let mut num_found = 0;
+    for _ in 0..50 {
+        let query: angular::Vector = test_helper::random_vector(DIM);
+        let res = index.search_within(&query, max_dist, 20);
+
+        assert!(res.iter().all(|&(_, d)| d <= max_dist));
+        for k in 1..res.len() {
+            assert!(res[k - 1].1 <= res[k].1);
+        }
+
+        let mut ids: Vec<usize> = res.iter().map(|&(id, _)| id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(res.len(), ids.len());
+
+        let expected: Vec<usize> = (0..index.len())
+            .filter(|&id| index.get_elements().dist_to_element(id, &query).into_inner() <= max_dist)
+            .collect();
+
+        num_expected += expected.len();
+        num_found += res.iter().filter(|(id, _)| expected.contains(id)).count();
+        assert!(res.len() <= expected.len());
+    }
+
+    assert!(num_expected > 100);
+    assert!(0.95 < num_found as f32 / num_expected as f32);
+}
+
+#[test]
+fn search_within_small_radius() {
+    let builder = build_random_angular(5, 500);
+    let index = builder.get_index();
+
+    for i in 0..index.len() {
+        let res = index.search_within(&index.get_element(i), 0.0, 10);
+        assert!(res.len() <= 1);
+    }
+
+    let element = index.get_element(7);
+    assert_eq!(7, index.search_within(&element, DIST_EPSILON, 10)[0].0);
+    assert_eq!(index.len(), index.search_within(&element, 2.0, 0).len());
+}
+
+#[test]
+fn build_and_search_bf16() {
+    let elements: dense::DenseVectors<dense::BF16, dense::Cosine> = (0..1000)
+        .map(|_| test_helper::random_vector::<dense::DenseVector<dense::BF16, dense::Cosine>>(25))
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_f16() {
+    let elements: dense::DenseVectors<dense::F16, dense::Cosine> = (0..1000)
+        .map(|_| test_helper::random_vector::<dense::DenseVector<dense::F16, dense::Cosine>>(25))
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_euclidean() {
+    let elements: euclidean::Vectors = (0..1000)
+        .map(|_| test_helper::random_vector::<euclidean::Vector>(25))
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_dense_manhattan_f64() {
+    let elements: dense::DenseVectors<f64, dense::Manhattan> = (0..1000)
+        .map(|_| test_helper::random_vector::<dense::DenseVector<f64, dense::Manhattan>>(25))
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_fn_elements() {
+    let vectors: Vec<Vec<f32>> = (0..1000)
+        .map(|_| test_helper::random_floats().take(25).collect())
+        .collect();
+    let elements = fn_elements::FnElements::with_elements(vectors, |x: &Vec<f32>, y: &Vec<f32>| {
+        math::squared_euclidean_distance_f32(x, y)
+    });
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_hamming() {
+    let elements: hamming::Vectors = (0..1000)
+        .map(|_| test_helper::random_vector::<hamming::Vector>(256))
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_minhash() {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let elements: minhash::Vectors = (0..1000)
+        .map(|_| {
+            let set: Vec<u32> = (0..50).map(|_| rng.gen_range(0, 500)).collect();
+            minhash::Vector::from_set(set, 128)
+        })
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_multi_vector() {
+    let elements: multi_vector::Vectors = (0..1000)
+        .map(|i| multi_vector::Vector::from_flat(test_helper::random_floats().take(16 * (1 + i % 4)).collect(), 16))
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_sparse() {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let elements: sparse::Vectors = (0..1000)
+        .map(|_| -> sparse::Vector { (0..30).map(|_| (rng.gen_range(0, 200), rng.gen::<f32>())).collect() })
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_inner_product() {
+    let elements: inner_product::Vectors = (0..1000)
+        .map(|i| {
+            let scale = 1.0 + (i % 5) as f32;
+            test_helper::random_floats()
+                .take(25)
+                .map(|x| scale * x)
+                .collect::<inner_product::Vector>()
+        })
+        .collect();
+
+    build_and_search(elements);
+}
+
+#[test]
+fn build_and_search_pq() {
+    let vectors: angular::Vectors = test_helper::random_vectors(32, 1000);
+
+    let mut elements = pq::Vectors::new(pq::Codebook::train(&vectors, 16, 10));
+    for i in 0..vectors.len() {
+        elements.push(&vectors.get_element(i));
+    }
+
+    build_and_search(elements);
+}
+
 #[test]
 fn incremental_build_0() {
     let elements: Vec<_> = (0..1000)
@@ -375,6 +892,195 @@ fn write_and_load() {
     }
 }
 
+#[test]
+fn write_and_load_inner_product() {
+    const DIM: usize = 25;
+    let elements: inner_product::Vectors = (0..500)
+        .map(|i| {
+            let scale = 1.0 + (i % 5) as f32;
+            test_helper::random_floats()
+                .take(DIM)
+                .map(|x| scale * x)
+                .collect::<inner_product::Vector>()
+        })
+        .collect();
+
+    let mut builder = GranneBuilder::new(BuildConfig::default().num_neighbors(20).max_search(20), elements);
+
+    builder.build();
+
+    let mut index_file: File = tempfile::tempfile().unwrap();
+    let mut elements_file: File = tempfile::tempfile().unwrap();
+    builder.write_index(&mut index_file).unwrap();
+    builder.write_elements(&mut elements_file).unwrap();
+
+    index_file.seek(SeekFrom::Start(0)).unwrap();
+    elements_file.seek(SeekFrom::Start(0)).unwrap();
+
+    let elements = unsafe { inner_product::Vectors::from_file(&elements_file).unwrap() };
+    let index = unsafe { Granne::from_file(&index_file, elements).unwrap() };
+
+    assert_eq!(builder.len(), index.len());
+    assert_eq!(builder.elements.dim(), index.get_elements().dim());
+
+    let mut num_found = 0;
+    for _ in 0..100 {
+        let query: inner_product::Vector = test_helper::random_vector(DIM);
+
+        let expected = (0..index.len())
+            .max_by_key(|&i| {
+                let x = index.get_elements().get_element(i);
+                NotNan::new(math::dot_product_f32(x.as_slice(), query.as_slice())).unwrap()
+            })
+            .unwrap();
+
+        if index.search(&query, 50, 1)[0].0 == expected {
+            num_found += 1;
+        }
+    }
+
+    assert!(90 < num_found);
+}
+
+fn write_and_load_with_neighbor_ids<Id: NeighborId>() {
+    const DIM: usize = 10;
+    let elements: angular::Vectors = (0..1000).map(|_| test_helper::random_vector(DIM)).collect();
+
+    let config = BuildConfig::default().num_neighbors(20).max_search(20);
+    let mut builder = GranneBuilder::<_, Id>::with_neighbor_ids(config, elements.borrow());
+
+    builder.build();
+    verify_search(&builder.get_index(), 0.95, 10);
+
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    builder.write_index(&mut buffer).unwrap();
+    let buffer = buffer.into_inner();
+
+    let index = Granne::from_bytes(&buffer, &elements);
+    let narrow_builder = GranneBuilder::from_bytes(config, &buffer, &elements);
+    let wide_builder = GranneBuilder::<_, u64>::from_bytes_with_neighbor_ids(config, &buffer, &elements);
+
+    assert_eq!(builder.len(), index.len());
+    assert_eq!(builder.num_layers(), index.num_layers());
+
+    for layer in 0..builder.num_layers() {
+        for i in 0..builder.layer_len(layer) {
+            let mut builder_neighbors = builder.get_neighbors(i, layer);
+            builder_neighbors.sort();
+
+            assert_eq!(builder_neighbors, index.get_neighbors(i, layer));
+            assert_eq!(builder_neighbors, narrow_builder.layers[layer].get_neighbors(i));
+            assert_eq!(builder_neighbors, wide_builder.layers[layer].get_neighbors(i));
+        }
+    }
+}
+
+#[test]
+fn write_and_load_with_u32_neighbor_ids() {
+    write_and_load_with_neighbor_ids::<u32>();
+}
+
+#[test]
+fn write_and_load_with_five_byte_neighbor_ids() {
+    write_and_load_with_neighbor_ids::<crate::FiveByteInt>();
+}
+
+#[test]
+fn write_and_load_with_u64_neighbor_ids() {
+    write_and_load_with_neighbor_ids::<u64>();
+}
+
+fn build_with_num_neighbors(num_neighbors: usize) -> GranneBuilder<angular::Vectors<'static>> {
+    let elements: angular::Vectors = test_helper::random_vectors(5, 500);
+
+    let mut builder = GranneBuilder::new(
+        BuildConfig::default().num_neighbors(num_neighbors).max_search(20),
+        elements,
+    );
+    builder.build();
+
+    builder
+}
+
+#[test]
+fn load_builder_with_smaller_num_neighbors() {
+    let builder = build_with_num_neighbors(30);
+    let layers = Layers::FixWidth(builder.layers.iter().map(|layer| layer.borrow()).collect());
+
+    let loaded = GranneBuilder::<_, u32>::from_layers(
+        BuildConfig::default().num_neighbors(10),
+        &layers,
+        builder.get_elements(),
+    );
+
+    let bottom_layer = builder.num_layers() - 1;
+    assert!((0..builder.len()).any(|i| builder.get_neighbors(i, bottom_layer).len() > 10));
+    for layer in 0..builder.num_layers() {
+        for i in 0..builder.layer_len(layer) {
+            assert_eq!(builder.get_neighbors(i, layer), loaded.get_neighbors(i, layer));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "more neighbors per node than num_neighbors")]
+fn load_wide_builder_with_smaller_num_neighbors() {
+    let builder = build_with_num_neighbors(30);
+    let layers = Layers::FixWidth(builder.layers.iter().map(|layer| layer.borrow()).collect());
+
+    GranneBuilder::<_, u64>::from_layers(
+        BuildConfig::default().num_neighbors(10),
+        &layers,
+        builder.get_elements(),
+    );
+}
+
+#[test]
+fn write_and_load_large_neighbor_ids() {
+    let ids: Vec<u64> = vec![3, 1 << 32, (1 << 40) - 2, 1 << 45, u64::UNUSED];
+
+    let mut layer = FixedWidthSliceVector::with_width(ids.len());
+    layer.push(&ids);
+    layer.push(&[7, u64::UNUSED, u64::UNUSED, u64::UNUSED, u64::UNUSED]);
+
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    io::write_index(&Layers::FixWidth64(vec![layer]), &mut buffer).unwrap();
+    let buffer = buffer.into_inner();
+
+    let layers = io::load_layers(&buffer);
+
+    assert!(layers.is_wide());
+    assert_eq!(1, layers.len());
+    assert_eq!(2, layers.as_graph(0).len());
+    assert_eq!(
+        vec![3, 1 << 32, (1 << 40) - 2, 1 << 45],
+        layers.as_graph(0).get_neighbors(0)
+    );
+    assert_eq!(vec![7], layers.as_graph(0).get_neighbors(1));
+}
+
+#[test]
+fn reorder_wide_index() {
+    let elements: angular::Vectors = (0..1000).map(|_| test_helper::random_vector(5)).collect();
+
+    let mut builder = GranneBuilder::<_, u64>::with_neighbor_ids(BuildConfig::default().max_search(20), elements);
+    builder.build();
+
+    let mut index = Granne::from_parts(builder.layers.clone(), builder.elements.clone());
+    let permutation = index.reorder(false);
+
+    assert!(index.layers.load().is_wide());
+
+    for &idx in &[0, 123, 999] {
+        let element = builder.get_elements().get(idx);
+        let exp = builder.get_index().search(&element, 20, 5);
+        let res = index.search(&element, 20, 5);
+        for (e, r) in exp.iter().zip(&res) {
+            assert_eq!(e.0, permutation[r.0]);
+        }
+    }
+}
+
 #[test]
 fn write_and_load_compressed() {
     const DIM: usize = 50;
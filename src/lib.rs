@@ -4,13 +4,16 @@
 /*!
 Granne (**g**raph-based **r**etrieval of **a**pproximate **n**earest **ne**ighbors) provides approximate nearest neighbor search among (typically) high-dimensional vectors. It focuses on reducing memory usage in order to allow [indexing billions of vectors](https://0x65.dev/blog/2019-12-07/indexing-billions-of-text-vectors.html).
 
-Note: There is currently a limit on the number of elements that can be indexed: `2^32 - 2 == 4_294_967_294`.
+Note: By default, neighbor ids are stored as `u32`, which limits the number of elements that can be indexed to `2^32 - 2 == 4_294_967_294`. Larger indexes can be built using wider neighbor ids, see [`GranneBuilder::with_neighbor_ids`](struct.GranneBuilder.html#method.with_neighbor_ids).
 
 # Overview
 
 * [`Granne`](struct.Granne.html) is the main struct used for querying/searching for nearest neighbors.
 * [`GranneBuilder`](struct.GranneBuilder.html) is used to build `Granne` indexes.
+* [`ShardedGranne`](struct.ShardedGranne.html) searches several `Granne` indexes (shards) as one index.
+* [`BruteForce`](struct.BruteForce.html) provides exact search (e.g. as ground truth) with the same `search` signature as `Granne`.
 * [`Index`](trait.Index.html) and [`Builder`](trait.Builder.html) are traits implemented for all `Granne` and `GranneBuilder` types, since both `Granne` and `GranneBuilder` are generic over the type of elements.
+* [`Search`](trait.Search.html) is implemented by `Granne`, `ShardedGranne` and `BruteForce`, so that they can be used interchangeably for searching.
 * [`SumEmbeddings`](embeddings/struct.SumEmbeddings.html) support the use case where the element are constructed by summing a smaller number of embeddings.
 
 # Memory-mapping
@@ -75,11 +78,18 @@ mod math;
 mod max_size_heap;
 mod odd_byte_int;
 mod slice_vector;
-use odd_byte_int::{FiveByteInt, ThreeByteInt};
-
-pub use elements::{angular, angular_int, embeddings};
-pub use elements::{Dist, ElementContainer, ExtendableElementContainer, Permutable};
-pub use index::{BuildConfig, Builder, Granne, GranneBuilder, Index};
+pub use odd_byte_int::FiveByteInt;
+use odd_byte_int::{FourByteInt, ThreeByteInt};
+
+pub use elements::{
+    angular, angular_int, dense, embeddings, euclidean, fn_elements, hamming, inner_product, minhash, multi_vector, pq,
+    sparse,
+};
+pub use elements::{Dist, ElementContainer, ExtendableElementContainer, Permutable, QueryDist};
+pub use index::{
+    BruteForce, BuildConfig, Builder, Granne, GranneBuilder, Index, NeighborId, Search, SearchContext, SearchCursor,
+    SearchStats, ShardedGranne, SHARD_MANIFEST_FILE_NAME,
+};
 pub use io::Writeable;
 
 #[cfg(feature = "rw_granne")]
@@ -56,6 +56,106 @@ pub fn dot_product_f32(x: &[f32], y: &[f32]) -> f32 {
     unsafe { blas::sdot(x.len() as i32, x, 1, y, 1) }
 }
 
+pub fn squared_euclidean_distance_f32(x: &[f32], y: &[f32]) -> f32 {
+    // optimized code to compute the squared distance for systems supporting avx2
+    // with fallback for other systems
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn squared_euclidean_distance_avx2(x: &[f32], y: &[f32]) -> f32 {
+        // this function will be inlined and take advantage of avx2 auto-vectorization
+        squared_euclidean_distance_fallback(x, y)
+    }
+
+    #[inline(always)]
+    fn squared_euclidean_distance_fallback(x: &[f32], y: &[f32]) -> f32 {
+        const CHUNK_SIZE: usize = 32;
+        let mut chunk = [0.0f32; CHUNK_SIZE];
+
+        for (a, b) in x.chunks_exact(CHUNK_SIZE).zip(y.chunks_exact(CHUNK_SIZE)) {
+            for i in 0..CHUNK_SIZE {
+                let diff = a[i] - b[i];
+                chunk[i] = diff.mul_add(diff, chunk[i]);
+            }
+        }
+
+        let mut r = 0.0f32;
+        for i in 0..CHUNK_SIZE {
+            r += chunk[i];
+        }
+
+        for (ai, bi) in x
+            .chunks_exact(CHUNK_SIZE)
+            .remainder()
+            .iter()
+            .zip(y.chunks_exact(CHUNK_SIZE).remainder())
+        {
+            let diff = ai - bi;
+            r = diff.mul_add(diff, r);
+        }
+
+        r
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return unsafe { squared_euclidean_distance_avx2(x, y) };
+        }
+    }
+
+    squared_euclidean_distance_fallback(x, y)
+}
+
+pub fn manhattan_distance_f32(x: &[f32], y: &[f32]) -> f32 {
+    // optimized code to compute the manhattan distance for systems supporting avx2
+    // with fallback for other systems
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2")]
+    unsafe fn manhattan_distance_avx2(x: &[f32], y: &[f32]) -> f32 {
+        // this function will be inlined and take advantage of avx2 auto-vectorization
+        manhattan_distance_fallback(x, y)
+    }
+
+    #[inline(always)]
+    fn manhattan_distance_fallback(x: &[f32], y: &[f32]) -> f32 {
+        const CHUNK_SIZE: usize = 32;
+        let mut chunk = [0.0f32; CHUNK_SIZE];
+
+        for (a, b) in x.chunks_exact(CHUNK_SIZE).zip(y.chunks_exact(CHUNK_SIZE)) {
+            for i in 0..CHUNK_SIZE {
+                chunk[i] += (a[i] - b[i]).abs();
+            }
+        }
+
+        let mut r = 0.0f32;
+        for i in 0..CHUNK_SIZE {
+            r += chunk[i];
+        }
+
+        for (ai, bi) in x
+            .chunks_exact(CHUNK_SIZE)
+            .remainder()
+            .iter()
+            .zip(y.chunks_exact(CHUNK_SIZE).remainder())
+        {
+            r += (ai - bi).abs();
+        }
+
+        r
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { manhattan_distance_avx2(x, y) };
+        }
+    }
+
+    manhattan_distance_fallback(x, y)
+}
+
 pub fn dot_product_and_squared_norms_i8(x: &[i8], y: &[i8]) -> (i32, i32, i32) {
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     #[target_feature(enable = "avx2")]
@@ -88,6 +188,286 @@ pub fn dot_product_and_squared_norms_i8(x: &[i8], y: &[i8]) -> (i32, i32, i32) {
     compute_r_dx_dy_fallback(x, y)
 }
 
+pub fn dot_product_i8(x: &[i8], y: &[i8]) -> i32 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2")]
+    unsafe fn dot_product_avx2(x: &[i8], y: &[i8]) -> i32 {
+        // this function will be inlined and take advantage of avx2 auto-vectorization
+        dot_product_fallback(x, y)
+    }
+
+    #[inline(always)]
+    fn dot_product_fallback(x: &[i8], y: &[i8]) -> i32 {
+        x.iter()
+            .zip(y.iter())
+            .map(|(&xi, &yi)| i32::from(xi) * i32::from(yi))
+            .sum()
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { dot_product_avx2(x, y) };
+        }
+    }
+
+    dot_product_fallback(x, y)
+}
+
+pub fn dot_product_i8_f32(x: &[i8], y: &[f32]) -> f32 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn dot_product_avx2(x: &[i8], y: &[f32]) -> f32 {
+        // this function will be inlined and take advantage of avx2 auto-vectorization
+        dot_product_fallback(x, y)
+    }
+
+    #[inline(always)]
+    fn dot_product_fallback(x: &[i8], y: &[f32]) -> f32 {
+        const CHUNK_SIZE: usize = 16;
+        let mut chunk = [0.0f32; CHUNK_SIZE];
+
+        for (a, b) in x.chunks_exact(CHUNK_SIZE).zip(y.chunks_exact(CHUNK_SIZE)) {
+            for i in 0..CHUNK_SIZE {
+                chunk[i] = f32::from(a[i]).mul_add(b[i], chunk[i]);
+            }
+        }
+
+        let mut r: f32 = chunk.iter().sum();
+
+        for (&ai, &bi) in x
+            .chunks_exact(CHUNK_SIZE)
+            .remainder()
+            .iter()
+            .zip(y.chunks_exact(CHUNK_SIZE).remainder())
+        {
+            r = f32::from(ai).mul_add(bi, r);
+        }
+
+        r
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return unsafe { dot_product_avx2(x, y) };
+        }
+    }
+
+    dot_product_fallback(x, y)
+}
+
+pub fn hamming_distance_u64(x: &[u64], y: &[u64]) -> u32 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "popcnt")]
+    unsafe fn hamming_distance_popcnt(x: &[u64], y: &[u64]) -> u32 {
+        // this function will be inlined and use the popcnt instruction
+        hamming_distance_fallback(x, y)
+    }
+
+    #[inline(always)]
+    fn hamming_distance_fallback(x: &[u64], y: &[u64]) -> u32 {
+        x.iter().zip(y).map(|(xi, yi)| (xi ^ yi).count_ones()).sum()
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("popcnt") {
+            return unsafe { hamming_distance_popcnt(x, y) };
+        }
+    }
+
+    hamming_distance_fallback(x, y)
+}
+
+/// Converts `x` into a bfloat16 (stored as `u16`), rounding to nearest even.
+#[inline(always)]
+pub fn f32_to_bf16(x: f32) -> u16 {
+    let bits = x.to_bits();
+
+    if x.is_nan() {
+        // keep the sign and make sure the result is still a NaN
+        return ((bits >> 16) | 0x0040) as u16;
+    }
+
+    let rounding = 0x7fff + ((bits >> 16) & 1);
+
+    (bits.wrapping_add(rounding) >> 16) as u16
+}
+
+/// Converts a bfloat16 (stored as `u16`) into `f32`.
+#[inline(always)]
+pub fn bf16_to_f32(x: u16) -> f32 {
+    f32::from_bits(u32::from(x) << 16)
+}
+
+/// Converts `x` into an IEEE 754 half-precision float (stored as `u16`), rounding to nearest even.
+pub fn f32_to_f16(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let man = bits & 0x007f_ffff;
+
+    if exp == 0xff {
+        // inf or NaN
+        return sign | 0x7c00 | if man != 0 { 0x0200 } else { 0 };
+    }
+
+    let exp = exp - 127 + 15;
+
+    if exp >= 0x1f {
+        // too large, round to inf
+        return sign | 0x7c00;
+    }
+
+    let round = |half: u32, rem: u32, halfway: u32| {
+        if rem > halfway || (rem == halfway && half & 1 == 1) {
+            half + 1
+        } else {
+            half
+        }
+    };
+
+    if exp <= 0 {
+        // subnormal or zero
+        if exp < -10 {
+            return sign;
+        }
+
+        let man = man | 0x0080_0000;
+        let shift = (14 - exp) as u32;
+        let half = round(man >> shift, man & ((1 << shift) - 1), 1 << (shift - 1));
+
+        return sign | half as u16;
+    }
+
+    // a carry from the rounding propagates into the exponent as expected
+    let half = round(((exp as u32) << 10) | (man >> 13), man & 0x1fff, 0x1000);
+
+    sign | half as u16
+}
+
+/// Converts an IEEE 754 half-precision float (stored as `u16`) into `f32`.
+#[inline(always)]
+pub fn f16_to_f32(x: u16) -> f32 {
+    let sign = u32::from(x & 0x8000) << 16;
+    let exp = u32::from((x >> 10) & 0x1f);
+    let man = u32::from(x & 0x03ff);
+
+    let bits = match exp {
+        0 => {
+            // zero or subnormal
+            let v = man as f32 / 16_777_216.0;
+            return if sign == 0 { v } else { -v };
+        }
+        0x1f => sign | 0x7f80_0000 | (man << 13),
+        _ => sign | ((exp + 127 - 15) << 23) | (man << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+pub fn dot_product_bf16(x: &[u16], y: &[u16]) -> f32 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn dot_product_avx2(x: &[u16], y: &[u16]) -> f32 {
+        // this function will be inlined and take advantage of avx2 auto-vectorization
+        dot_product_fallback(x, y)
+    }
+
+    #[inline(always)]
+    fn dot_product_fallback(x: &[u16], y: &[u16]) -> f32 {
+        const CHUNK_SIZE: usize = 16;
+        let mut r = [0.0f32; CHUNK_SIZE];
+
+        for (a, b) in x.chunks_exact(CHUNK_SIZE).zip(y.chunks_exact(CHUNK_SIZE)) {
+            for i in 0..CHUNK_SIZE {
+                r[i] = bf16_to_f32(a[i]).mul_add(bf16_to_f32(b[i]), r[i]);
+            }
+        }
+
+        let mut r: f32 = r.iter().sum();
+
+        for (&ai, &bi) in x
+            .chunks_exact(CHUNK_SIZE)
+            .remainder()
+            .iter()
+            .zip(y.chunks_exact(CHUNK_SIZE).remainder())
+        {
+            r = bf16_to_f32(ai).mul_add(bf16_to_f32(bi), r);
+        }
+
+        r
+    }
+
+    assert_eq!(x.len(), y.len());
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return unsafe { dot_product_avx2(x, y) };
+        }
+    }
+
+    dot_product_fallback(x, y)
+}
+
+pub fn dot_product_f16(x: &[u16], y: &[u16]) -> f32 {
+    // uses the f16c instruction set for the conversion if available
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2,fma,f16c")]
+    unsafe fn dot_product_f16c(x: &[u16], y: &[u16]) -> f32 {
+        #[cfg(target_arch = "x86")]
+        use std::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use std::arch::x86_64::*;
+
+        const CHUNK_SIZE: usize = 8;
+        let mut r = _mm256_setzero_ps();
+
+        for (a, b) in x.chunks_exact(CHUNK_SIZE).zip(y.chunks_exact(CHUNK_SIZE)) {
+            let a = _mm256_cvtph_ps(_mm_loadu_si128(a.as_ptr() as *const __m128i));
+            let b = _mm256_cvtph_ps(_mm_loadu_si128(b.as_ptr() as *const __m128i));
+
+            r = _mm256_fmadd_ps(a, b, r);
+        }
+
+        let mut buffer = [0.0f32; CHUNK_SIZE];
+        _mm256_storeu_ps(buffer.as_mut_ptr(), r);
+        let mut r: f32 = buffer.iter().sum();
+
+        for (&ai, &bi) in x
+            .chunks_exact(CHUNK_SIZE)
+            .remainder()
+            .iter()
+            .zip(y.chunks_exact(CHUNK_SIZE).remainder())
+        {
+            r = f16_to_f32(ai).mul_add(f16_to_f32(bi), r);
+        }
+
+        r
+    }
+
+    #[inline(always)]
+    fn dot_product_fallback(x: &[u16], y: &[u16]) -> f32 {
+        x.iter()
+            .zip(y)
+            .fold(0.0f32, |r, (&ai, &bi)| f16_to_f32(ai).mul_add(f16_to_f32(bi), r))
+    }
+
+    assert_eq!(x.len(), y.len());
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") && is_x86_feature_detected!("f16c") {
+            return unsafe { dot_product_f16c(x, y) };
+        }
+    }
+
+    dot_product_fallback(x, y)
+}
+
 #[cfg(not(feature = "blas"))]
 pub fn sum_into_f32(x: &mut [f32], y: &[f32]) {
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
@@ -120,6 +500,38 @@ pub fn sum_into_f32(x: &mut [f32], y: &[f32]) {
     unsafe { blas::saxpy(x.len() as i32, 1f32, y, 1, x, 1) };
 }
 
+#[cfg(not(feature = "blas"))]
+pub fn scaled_sum_into_f32(x: &mut [f32], a: f32, y: &[f32]) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn scaled_sum_into_avx2(x: &mut [f32], a: f32, y: &[f32]) {
+        scaled_sum_into_fallback(x, a, y)
+    }
+
+    #[inline(always)]
+    fn scaled_sum_into_fallback(x: &mut [f32], a: f32, y: &[f32]) {
+        assert_eq!(x.len(), y.len());
+
+        for i in 0..x.len() {
+            x[i] = a.mul_add(y[i], x[i]);
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return unsafe { scaled_sum_into_avx2(x, a, y) };
+        }
+    }
+
+    scaled_sum_into_fallback(x, a, y)
+}
+
+#[cfg(feature = "blas")]
+pub fn scaled_sum_into_f32(x: &mut [f32], a: f32, y: &[f32]) {
+    unsafe { blas::saxpy(x.len() as i32, a, y, 1, x, 1) };
+}
+
 #[cfg(not(feature = "blas"))]
 pub fn normalize_f32(x: &mut [f32]) {
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
@@ -180,6 +592,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn scaled_sum() {
+        for i in 1..101 {
+            let mut x: Vec<f32> = test_helper::random_floats().take(i).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(i).collect();
+
+            let mut expected = x.clone();
+            for (i, xi) in expected.iter_mut().enumerate() {
+                *xi += 0.3 * y[i];
+            }
+
+            scaled_sum_into_f32(&mut x, 0.3, &y);
+
+            for (e, xi) in expected.iter().zip(&x) {
+                assert!((e - xi).abs() < 0.000001f32);
+            }
+        }
+    }
+
     #[test]
     fn dot_product() {
         for i in 1..101 {
@@ -194,4 +625,158 @@ mod tests {
             assert!((expected - dot_product_f32(&x, &y)).abs() < 0.000001f32);
         }
     }
+
+    #[test]
+    fn squared_euclidean_distance() {
+        for i in 1..101 {
+            let x: Vec<f32> = test_helper::random_floats().take(i).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(i).collect();
+
+            let mut expected = 0.0f32;
+            for i in 0..i {
+                expected += (x[i] - y[i]) * (x[i] - y[i]);
+            }
+
+            assert!((expected - squared_euclidean_distance_f32(&x, &y)).abs() < 0.00001f32);
+        }
+    }
+
+    #[test]
+    fn manhattan_distance() {
+        for i in 1..101 {
+            let x: Vec<f32> = test_helper::random_floats().take(i).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(i).collect();
+
+            let mut expected = 0.0f32;
+            for i in 0..i {
+                expected += (x[i] - y[i]).abs();
+            }
+
+            assert!((expected - manhattan_distance_f32(&x, &y)).abs() < 0.0001f32);
+        }
+    }
+
+    #[test]
+    fn dot_product_int() {
+        for i in 1..101 {
+            let x: Vec<i8> = test_helper::random_floats()
+                .take(i)
+                .map(|x| (x * 250.0) as i8)
+                .collect();
+            let y: Vec<i8> = test_helper::random_floats()
+                .take(i)
+                .map(|x| (x * 250.0) as i8)
+                .collect();
+
+            let mut expected = 0i32;
+            for i in 0..i {
+                expected += i32::from(x[i]) * i32::from(y[i]);
+            }
+
+            assert_eq!(expected, dot_product_i8(&x, &y));
+        }
+    }
+
+    #[test]
+    fn dot_product_mixed() {
+        for i in 1..101 {
+            let x: Vec<i8> = test_helper::random_floats()
+                .take(i)
+                .map(|x| (x * 250.0) as i8)
+                .collect();
+            let y: Vec<f32> = test_helper::random_floats().take(i).collect();
+
+            let mut expected = 0.0f32;
+            for i in 0..i {
+                expected += f32::from(x[i]) * y[i];
+            }
+
+            assert!((expected - dot_product_i8_f32(&x, &y)).abs() < 0.001f32);
+        }
+    }
+
+    #[test]
+    fn hamming_distance() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+
+        for i in 1..20 {
+            let x: Vec<u64> = (0..i).map(|_| rng.gen()).collect();
+            let y: Vec<u64> = (0..i).map(|_| rng.gen()).collect();
+
+            let mut expected = 0u32;
+            for i in 0..i {
+                for bit in 0..64 {
+                    if (x[i] >> bit) & 1 != (y[i] >> bit) & 1 {
+                        expected += 1;
+                    }
+                }
+            }
+
+            assert_eq!(expected, hamming_distance_u64(&x, &y));
+        }
+    }
+
+    #[test]
+    fn bf16_conversion() {
+        for x in 0..=std::u16::MAX {
+            let f = bf16_to_f32(x);
+            if !f.is_nan() {
+                assert_eq!(x, f32_to_bf16(f));
+            }
+        }
+
+        assert_eq!(1.0f32, bf16_to_f32(f32_to_bf16(1.0)));
+        assert_eq!(-2.5f32, bf16_to_f32(f32_to_bf16(-2.5)));
+        assert!(bf16_to_f32(f32_to_bf16(std::f32::NAN)).is_nan());
+
+        for x in test_helper::random_floats().take(1000) {
+            assert!((x - bf16_to_f32(f32_to_bf16(x))).abs() <= x.abs() / 256.0);
+        }
+    }
+
+    #[test]
+    fn f16_conversion() {
+        for x in 0..=std::u16::MAX {
+            let f = f16_to_f32(x);
+            if !f.is_nan() {
+                assert_eq!(x, f32_to_f16(f));
+            }
+        }
+
+        assert_eq!(0x3c00, f32_to_f16(1.0));
+        assert_eq!(0xc100, f32_to_f16(-2.5));
+        assert_eq!(0x7c00, f32_to_f16(1e6));
+        assert_eq!(0x0001, f32_to_f16(6e-8));
+        assert_eq!(0x0000, f32_to_f16(1e-9));
+        assert!(f16_to_f32(f32_to_f16(std::f32::NAN)).is_nan());
+
+        for x in test_helper::random_floats().take(1000) {
+            assert!((x - f16_to_f32(f32_to_f16(x))).abs() <= 1e-3);
+        }
+    }
+
+    #[test]
+    fn dot_product_half() {
+        for i in 1..101 {
+            let x: Vec<f32> = test_helper::random_floats().take(i).collect();
+            let y: Vec<f32> = test_helper::random_floats().take(i).collect();
+
+            for &(to_half, from_half, dot_product) in &[
+                (
+                    f32_to_bf16 as fn(f32) -> u16,
+                    bf16_to_f32 as fn(u16) -> f32,
+                    dot_product_bf16 as fn(&[u16], &[u16]) -> f32,
+                ),
+                (f32_to_f16, f16_to_f32, dot_product_f16),
+            ] {
+                let x: Vec<u16> = x.iter().map(|&xi| to_half(xi)).collect();
+                let y: Vec<u16> = y.iter().map(|&yi| to_half(yi)).collect();
+
+                let expected: f32 = x.iter().zip(&y).map(|(&xi, &yi)| from_half(xi) * from_half(yi)).sum();
+
+                assert!((expected - dot_product(&x, &y)).abs() < 0.00001f32);
+            }
+        }
+    }
 }
@@ -15,6 +15,13 @@ impl<T: Ord> MaxSizeHeap<T> {
         }
     }
 
+    pub fn with_heap(mut heap: BinaryHeap<T>, max_size: usize) -> Self {
+        heap.clear();
+        heap.reserve(max_size);
+
+        Self { heap, max_size }
+    }
+
     pub fn push(self: &mut Self, element: T) -> bool {
         if !self.is_full() {
             self.heap.push(element);
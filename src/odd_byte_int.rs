@@ -2,13 +2,14 @@ use byteorder::{ByteOrder, LittleEndian};
 
 macro_rules! oddbyte_int {
     ($type_name:ident, $num_bytes:expr) => {
-        /// An integer type representing an offset/id using $num_bytes bytes for
-        /// improved compression. Stores integer no larger than 256**$num_bytes
+        /// An integer type representing an offset/id using a fixed number of bytes (see
+        /// `max_value`) for improved compression.
         #[repr(C, packed)]
         #[derive(Clone, Copy, Eq, PartialEq)]
         pub struct $type_name([u8; $num_bytes]);
 
         impl $type_name {
+            /// Returns the largest value that can be represented by this type.
             pub const fn max_value() -> Self {
                 Self([0xFF; $num_bytes])
             }
@@ -22,6 +23,7 @@ macro_rules! oddbyte_int {
         }
 
         impl From<usize> for $type_name {
+            /// Panics if `integer` does not fit in $num_bytes bytes.
             #[inline(always)]
             fn from(integer: usize) -> Self {
                 let mut data = [0u8; $num_bytes];
@@ -33,6 +35,7 @@ macro_rules! oddbyte_int {
 }
 
 oddbyte_int!(ThreeByteInt, 3);
+oddbyte_int!(FourByteInt, 4);
 oddbyte_int!(FiveByteInt, 5);
 
 #[cfg(test)]
@@ -49,6 +52,25 @@ mod tests {
         assert_eq!(original, converted);
     }
 
+    #[test]
+    #[should_panic]
+    fn overflow() {
+        let _: ThreeByteInt = (1usize << 24).into();
+    }
+
+    #[test]
+    fn max_values() {
+        let max_value: usize = ThreeByteInt::max_value().into();
+        assert_eq!((1usize << 24) - 1, max_value);
+
+        let max_value: usize = FourByteInt::max_value().into();
+        assert_eq!((1usize << 32) - 1, max_value);
+
+        let integer: FourByteInt = ((1usize << 32) - 1).into();
+        let integer: usize = integer.into();
+        assert_eq!((1usize << 32) - 1, integer);
+    }
+
     #[test]
     fn query_offset_conversions() {
         for &integer in &[
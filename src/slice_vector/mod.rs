@@ -20,17 +20,28 @@ pub use set_vector::MultiSetVector;
 
 const U64_LEN: usize = ::std::mem::size_of::<u64>();
 
+/// Returns the number of padding bytes between the offsets and the data of a serialized
+/// `VariableWidthSliceVector` with `num_slices` slices, such that the data is aligned for `T`
+/// (relative to the start of the `VariableWidthSliceVector`).
+fn data_padding<T, Offset>(num_slices: usize) -> usize {
+    let align = ::std::mem::align_of::<T>();
+    let data_start = U64_LEN + (1 + num_slices) * ::std::mem::size_of::<Offset>();
+
+    (align - data_start % align) % align
+}
+
 /// A vector containing variably wide slices.
 pub enum VariableWidthSliceVector<'a, T: 'a + Clone, Offset: 'a + Clone> {
-    File(memmap::Mmap),
+    /// A memory-mapped file. The data starts at the given byte offset in the file.
+    File(memmap::Mmap, usize),
     Memory(Cow<'a, [Offset]>, Cow<'a, [T]>),
 }
 
 impl<'a, T: Clone, Offset: Clone> Clone for VariableWidthSliceVector<'a, T, Offset> {
     fn clone(self: &Self) -> Self {
         match self {
-            Self::File(mmap) => {
-                let (offsets, data) = Self::load_mmap(&mmap[..]);
+            Self::File(mmap, start) => {
+                let (offsets, data) = Self::load_mmap(&mmap[*start..]);
                 Self::Memory(Cow::Owned(offsets.to_vec()), Cow::Owned(data.to_vec()))
             }
             Self::Memory(offsets, data) => Self::Memory(offsets.clone(), data.clone()),
@@ -279,6 +290,11 @@ impl<'a, T: Clone> FixedWidthSliceVector<'a, T> {
         let offset_size = ::std::mem::size_of::<Offset>() as u64;
         let mut value_pos = offset_pos + self.len() as u64 * offset_size;
 
+        let padding = vec![0u8; data_padding::<T, Offset>(self.len())];
+        buffer.seek(SeekFrom::Start(value_pos))?;
+        buffer.write_all(&padding)?;
+        value_pos += padding.len() as u64;
+
         let mut slice_buffer: Vec<T> = Vec::new();
         let mut offsets: Vec<Offset> = Vec::new();
 
@@ -506,10 +522,20 @@ where
     }
 
     pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
-        let file = memmap::Mmap::map(&file)?;
-        file.advise_memory_access(AccessPattern::Random)?;
+        Self::from_file_at(file, 0)
+    }
 
-        let slice_vec = Self::File(file);
+    /// Loads a memory-mapped `VariableWidthSliceVector` starting at byte `start` in `file`.
+    pub unsafe fn from_file_at(file: &std::fs::File, start: usize) -> std::io::Result<Self> {
+        Self::from_mmap_at(memmap::Mmap::map(&file)?, start)
+    }
+
+    /// Creates a `VariableWidthSliceVector` from the memory-mapped file `mmap`, starting at byte
+    /// `start`, e.g. after reading a header from the same mapping.
+    pub fn from_mmap_at(mmap: memmap::Mmap, start: usize) -> std::io::Result<Self> {
+        mmap.advise_memory_access(AccessPattern::Random)?;
+
+        let slice_vec = Self::File(mmap, start);
 
         // try to fail early
         let (_offsets, _data) = slice_vec.load();
@@ -587,6 +613,11 @@ where
         io::write_as_bytes(&data[data_begin..data_end], buffer)
     }
 
+    /// Returns the total number of values in all slices.
+    pub fn num_values(self: &Self) -> usize {
+        self.load().1.len()
+    }
+
     pub fn get(self: &Self, idx: usize) -> &[T] {
         let (offsets, data) = self.load();
 
@@ -620,14 +651,23 @@ where
         offsets.push(Offset::try_from(data.len()).unwrap());
     }
 
+    /// Writes the number of slices, the offsets and the data to `buffer`. The offsets and the data
+    /// are written as they are stored in memory, i.e., `Offset` should be a fixed-width type
+    /// (rather than `usize`) for the format to be independent of the platform. The data is padded
+    /// to be aligned for `T`, which allows it to be memory-mapped.
     pub fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
         let (offsets, data) = self.load();
 
         // write metadata
-        buffer.write_all(&self.len().to_le_bytes())?;
+        buffer.write_all(&(self.len() as u64).to_le_bytes())?;
         let mut bytes_written = std::mem::size_of::<u64>();
 
         bytes_written += io::write_as_bytes(&offsets[..], buffer)?;
+
+        let padding = vec![0u8; data_padding::<T, Offset>(self.len())];
+        buffer.write_all(&padding)?;
+        bytes_written += padding.len();
+
         bytes_written += io::write_as_bytes(&data[..], buffer)?;
 
         Ok(bytes_written)
@@ -637,22 +677,22 @@ where
 impl<'a, T: Clone, Offset: Clone> VariableWidthSliceVector<'a, T, Offset> {
     fn load(self: &Self) -> (&[Offset], &[T]) {
         match self {
-            Self::File(mmap) => Self::load_mmap(&mmap[..]),
+            Self::File(mmap, start) => Self::load_mmap(&mmap[*start..]),
             Self::Memory(offsets, data) => (&offsets, &data),
         }
     }
 
     fn load_mut(self: &mut Self) -> (&mut Vec<Offset>, &mut Vec<T>) {
         match self {
-            Self::File(mmap) => {
-                let (offsets, data) = Self::load_mmap(mmap);
+            Self::File(mmap, start) => {
+                let (offsets, data) = Self::load_mmap(&mmap[*start..]);
                 *self = Self::Memory(Cow::Owned(offsets.to_vec()), Cow::Owned(data.to_vec()));
             }
             Self::Memory(_, _) => {}
         }
 
         match self {
-            Self::File(_) => unreachable!(),
+            Self::File(_, _) => unreachable!(),
             Self::Memory(offsets, data) => (offsets.to_mut(), data.to_mut()),
         }
     }
@@ -666,6 +706,7 @@ impl<'a, T: Clone, Offset: Clone> VariableWidthSliceVector<'a, T, Offset> {
 
         let offset_len = ::std::mem::size_of::<Offset>();
         let (offsets, data) = buffer[U64_LEN..].split_at((1 + num_slices) * offset_len);
+        let data = &data[data_padding::<T, Offset>(num_slices)..];
 
         unsafe {
             (
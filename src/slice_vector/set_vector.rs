@@ -4,9 +4,14 @@ use std::convert::TryFrom;
 use std::io::{Read, Result, Seek, SeekFrom, Write};
 use stream_vbyte::{decode, encode, Scalar};
 
+/// A vector of multisets of integers. Each set is stored sorted and delta encoded.
+///
+/// By default the integers are `u32` and the deltas are encoded using stream vbyte. A wide
+/// `MultiSetVector` stores `u64` integers with the deltas encoded as LEB128 varints.
 #[derive(Clone)]
 pub struct MultiSetVector<'a> {
     data: CompressedVariableWidthSliceVector<'a, u8>,
+    wide: bool,
 }
 
 const MIN_NUMBERS_TO_ENCODE: usize = 4;
@@ -15,21 +20,42 @@ impl<'a> MultiSetVector<'a> {
     pub fn new() -> Self {
         Self {
             data: CompressedVariableWidthSliceVector::new(),
+            wide: false,
+        }
+    }
+
+    pub fn new_wide() -> Self {
+        Self {
+            data: CompressedVariableWidthSliceVector::new(),
+            wide: true,
         }
     }
 
     pub unsafe fn from_file(file: &std::fs::File) -> std::io::Result<Self> {
         Ok(Self {
             data: CompressedVariableWidthSliceVector::from_file(file)?,
+            wide: false,
         })
     }
 
     pub fn from_bytes(buffer: &'a [u8]) -> Self {
         Self {
             data: CompressedVariableWidthSliceVector::from_bytes(buffer),
+            wide: false,
         }
     }
 
+    pub fn from_bytes_wide(buffer: &'a [u8]) -> Self {
+        Self {
+            data: CompressedVariableWidthSliceVector::from_bytes(buffer),
+            wide: true,
+        }
+    }
+
+    pub fn is_wide(self: &Self) -> bool {
+        self.wide
+    }
+
     pub fn len(self: &Self) -> usize {
         self.data.len()
     }
@@ -46,9 +72,26 @@ impl<'a> MultiSetVector<'a> {
         self.push_sorted(data);
     }
 
+    /// Pushes the sorted `data`. Panics if this vector is wide (use `push_ids` instead).
     pub fn push_sorted(self: &mut Self, data: Vec<u32>) {
+        assert!(!self.wide, "Use push_ids for wide vectors");
         self.data.push(&set_encode(data));
     }
+
+    /// Pushes `ids` using the encoding of this vector. Panics if any id does not fit in a `u32`
+    /// and this vector is not wide.
+    pub fn push_ids(self: &mut Self, ids: &[usize]) {
+        if self.wide {
+            let mut data: Vec<u64> = ids.iter().map(|&id| id as u64).collect();
+            data.sort();
+
+            self.data.push(&wide_set_encode(data));
+        } else {
+            let data: Vec<u32> = ids.iter().map(|&id| u32::try_from(id).unwrap()).collect();
+
+            self.push(&data);
+        }
+    }
     /*
         pub fn extend_from_multi_set_vector(self: &mut Self, other: &Self) {
             self.data.extend_from_slice_vector(&other.data);
@@ -62,12 +105,40 @@ impl<'a> MultiSetVector<'a> {
         decoded_nums
     }
 
+    /// Replaces the contents of `res` with the numbers at `idx`. Panics if this vector is wide (use
+    /// `get_ids_into` instead).
     pub fn get_into(self: &Self, idx: usize, res: &mut Vec<u32>) {
+        assert!(!self.wide, "Use get_ids_into for wide vectors");
         let encoded_data = self.data.get(idx);
 
         decode_into(&encoded_data, res);
     }
 
+    /// Returns the ids at `idx`, regardless of the encoding of this vector.
+    pub fn get_ids(self: &Self, idx: usize) -> Vec<usize> {
+        let mut ids = Vec::new();
+
+        self.get_ids_into(idx, &mut ids);
+
+        ids
+    }
+
+    /// Replaces the contents of `res` with the ids at `idx`, regardless of the encoding of this
+    /// vector.
+    pub fn get_ids_into(self: &Self, idx: usize, res: &mut Vec<usize>) {
+        let encoded_data = self.data.get(idx);
+
+        if self.wide {
+            wide_decode_into(&encoded_data, res);
+        } else {
+            let mut decoded_nums = [0u32; u8::MAX as usize + 1];
+            let count = decode_into_slice(&encoded_data, &mut decoded_nums);
+
+            res.clear();
+            res.extend(decoded_nums[..count].iter().map(|&x| usize::try_from(x).unwrap()));
+        }
+    }
+
     pub fn write<B: Write>(self: &Self, buffer: &mut B) -> Result<usize> {
         self.data.write(buffer)
     }
@@ -78,12 +149,14 @@ impl<'a> MultiSetVector<'a> {
     {
         Self {
             data: self.data.borrow(),
+            wide: self.wide,
         }
     }
 
     pub fn into_owned(self: Self) -> MultiSetVector<'static> {
         MultiSetVector {
             data: self.data.into_owned(),
+            wide: self.wide,
         }
     }
 }
@@ -91,27 +164,41 @@ impl<'a> MultiSetVector<'a> {
 fn decode_into(encoded_data: &[u8], decoded_nums: &mut Vec<u32>) {
     let count = encoded_data[0] as usize;
     decoded_nums.clear();
+    decoded_nums.resize(std::cmp::max(MIN_NUMBERS_TO_ENCODE, count), 0);
+
+    decode_into_slice(encoded_data, decoded_nums);
+
+    decoded_nums.truncate(count);
+}
+
+/// Decodes `encoded_data` into the beginning of `decoded_nums`, which needs to fit at least
+/// `MIN_NUMBERS_TO_ENCODE` numbers. Returns the number of decoded numbers.
+fn decode_into_slice(encoded_data: &[u8], decoded_nums: &mut [u32]) -> usize {
+    let count = encoded_data[0] as usize;
     let encoded_data = &encoded_data[1..];
 
     if encoded_data.len() != count * ::std::mem::size_of::<u32>() {
-        decoded_nums.resize(std::cmp::max(MIN_NUMBERS_TO_ENCODE, count), 0);
+        let num_encoded = std::cmp::max(MIN_NUMBERS_TO_ENCODE, count);
 
-        //let bytes_decoded = decode::<stream_vbyte::x86::Ssse3>(encoded_data, decoded_nums.len(),
+        //let bytes_decoded = decode::<stream_vbyte::x86::Ssse3>(encoded_data, num_encoded,
         // decoded_nums);
-        let bytes_decoded = decode::<Scalar>(encoded_data, decoded_nums.len(), decoded_nums);
+        let bytes_decoded = decode::<Scalar>(encoded_data, num_encoded, &mut decoded_nums[..num_encoded]);
 
         debug_assert_eq!(encoded_data.len(), bytes_decoded);
-
-        decoded_nums.resize(count, 0);
     } else {
         let mut buf = [0x0; ::std::mem::size_of::<u32>()];
-        for num in encoded_data.chunks_exact(::std::mem::size_of::<u32>()).take(count) {
+        for (num, decoded) in encoded_data
+            .chunks_exact(::std::mem::size_of::<u32>())
+            .zip(decoded_nums.iter_mut())
+        {
             buf.copy_from_slice(num);
-            decoded_nums.push(u32::from_le_bytes(buf));
+            *decoded = u32::from_le_bytes(buf);
         }
     }
 
-    delta_decode(decoded_nums);
+    delta_decode(&mut decoded_nums[..count]);
+
+    count
 }
 
 fn set_encode(mut data: Vec<u32>) -> Vec<u8> {
@@ -147,17 +234,62 @@ fn set_encode(mut data: Vec<u32>) -> Vec<u8> {
     encoded_data
 }
 
+fn wide_decode_into(encoded_data: &[u8], decoded_nums: &mut Vec<usize>) {
+    let count = encoded_data[0] as usize;
+    decoded_nums.clear();
+    decoded_nums.reserve(count);
+
+    let mut num = 0u64;
+    let mut shift = 0;
+    for &byte in &encoded_data[1..] {
+        num |= u64::from(byte & 0x7F) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            decoded_nums.push(usize::try_from(num).unwrap());
+            num = 0;
+            shift = 0;
+        }
+    }
+
+    debug_assert_eq!(count, decoded_nums.len());
+
+    delta_decode(decoded_nums);
+}
+
+fn wide_set_encode(mut data: Vec<u64>) -> Vec<u8> {
+    debug_assert!(data.len() < u8::max_value() as usize);
+    if data.len() >= u8::max_value() as usize {
+        data.resize(u8::max_value() as usize, 0)
+    }
+
+    delta_encode(&mut data);
+
+    let mut encoded_data = vec![data.len() as u8];
+    for mut num in data {
+        while num >= 0x80 {
+            encoded_data.push((num as u8 & 0x7F) | 0x80);
+            num >>= 7;
+        }
+        encoded_data.push(num as u8);
+    }
+
+    encoded_data
+}
+
 #[inline(always)]
-fn delta_encode(data: &mut [u32]) {
+fn delta_encode<T: Copy + std::ops::SubAssign>(data: &mut [T]) {
     for i in (1..data.len()).rev() {
-        data[i] -= data[i - 1];
+        let prev = data[i - 1];
+        data[i] -= prev;
     }
 }
 
 #[inline(always)]
-fn delta_decode(data: &mut [u32]) {
+fn delta_decode<T: Copy + std::ops::AddAssign>(data: &mut [T]) {
     for i in 1..data.len() {
-        data[i] += data[i - 1];
+        let prev = data[i - 1];
+        data[i] += prev;
     }
 }
 
@@ -170,6 +302,43 @@ where
     where
         B: Write + Seek,
         P: FnMut(&T) -> bool,
+    {
+        let mut slice_buffer: Vec<u32> = Vec::new();
+        self.write_encoded_slices(buffer, |slice| {
+            slice_buffer.clear();
+            for val in slice {
+                if predicate(val) {
+                    slice_buffer.push(u32::try_from(val.clone()).unwrap());
+                }
+            }
+
+            slice_buffer.sort();
+
+            set_encode(slice_buffer.clone())
+        })
+    }
+}
+
+impl<'a, T: 'a + Clone> FixedWidthSliceVector<'a, T> {
+    /// Writes this vector in the format of a wide `MultiSetVector` (see
+    /// `MultiSetVector::from_bytes_wide`). Values for which `to_id` returns `None` are skipped.
+    pub fn write_as_wide_multi_set_vector<B, F>(self: &Self, buffer: &mut B, mut to_id: F) -> Result<usize>
+    where
+        B: Write + Seek,
+        F: FnMut(&T) -> Option<u64>,
+    {
+        self.write_encoded_slices(buffer, |slice| {
+            let mut ids: Vec<u64> = slice.iter().filter_map(&mut to_id).collect();
+            ids.sort();
+
+            wide_set_encode(ids)
+        })
+    }
+
+    fn write_encoded_slices<B, E>(self: &Self, buffer: &mut B, mut encode: E) -> Result<usize>
+    where
+        B: Write + Seek,
+        E: FnMut(&[T]) -> Vec<u8>,
     {
         let initial_pos = buffer.seek(SeekFrom::Current(0))?;
 
@@ -178,26 +347,16 @@ where
 
         buffer.write_all(&(bytes_for_offsets as u64).to_le_bytes())?;
 
-        let mut offset_pos = buffer.seek(SeekFrom::Current(0))?;
+        let offset_pos = buffer.seek(SeekFrom::Current(0))?;
         let mut offsets = super::Offsets::new();
         offsets.push(0);
 
         // write values
         buffer.seek(SeekFrom::Start(offset_pos + bytes_for_offsets as u64))?;
-        let mut slice_buffer: Vec<u32> = Vec::new();
         let mut total_len: usize = 0;
         if !self.is_empty() {
             for slice in self.iter() {
-                slice_buffer.clear();
-                for val in slice {
-                    if predicate(val) {
-                        slice_buffer.push(u32::try_from(val.clone()).unwrap());
-                    }
-                }
-
-                slice_buffer.sort();
-
-                let encoded = set_encode(slice_buffer.clone());
+                let encoded = encode(slice);
                 write_as_bytes(encoded.as_slice(), buffer)?;
 
                 total_len += encoded.len();
@@ -423,4 +582,74 @@ mod tests {
             assert!(loaded_vec.get(i).is_empty());
         }
     }
+
+    #[test]
+    fn push_and_get_wide() {
+        let mut vec = MultiSetVector::new_wide();
+
+        let ids = vec![0, 1, 127, 128, (1 << 32) - 1, 1 << 32, 1 << 40, std::usize::MAX];
+        vec.push_ids(&[ids[3], ids[7], ids[0], ids[5], ids[1], ids[6], ids[2], ids[4]]);
+        vec.push_ids(&[]);
+        vec.push_ids(&[5, 5]);
+
+        assert_eq!(3, vec.len());
+        assert_eq!(ids, vec.get_ids(0));
+        assert!(vec.get_ids(1).is_empty());
+        assert_eq!(vec![5, 5], vec.get_ids(2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_wide() {
+        let mut vec = MultiSetVector::new_wide();
+        vec.push_ids(&[1, 2, 3]);
+
+        vec.get(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_sorted_wide() {
+        let mut vec = MultiSetVector::new_wide();
+
+        vec.push_sorted(vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn push_ids_and_get_ids() {
+        let mut vec = MultiSetVector::new();
+
+        vec.push_ids(&[7, 3, 1 << 20]);
+
+        assert_eq!(vec![3, 7], vec.get(0)[..2].to_vec());
+        assert_eq!(vec![3, 7, 1 << 20], vec.get_ids(0));
+    }
+
+    #[test]
+    fn write_fixed_width_vector_as_wide_multi_set_vector() {
+        let width = 5;
+        let mut vec = FixedWidthSliceVector::new();
+        for i in 0..300u64 {
+            let data: Vec<u64> = (0..width).map(|j| (i << 33) + j).collect();
+            vec.push(&data);
+        }
+
+        let mut file: File = tempfile::tempfile().unwrap();
+        vec.write_as_wide_multi_set_vector(&mut file, |&x| if x % 5 != 0 { Some(x) } else { None })
+            .unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).unwrap();
+
+        let loaded_vec = MultiSetVector::from_bytes_wide(&buffer);
+
+        assert_eq!(vec.len(), loaded_vec.len());
+        assert!(loaded_vec.is_wide());
+
+        for i in 0..vec.len() {
+            let exp: Vec<usize> = vec.get(i).iter().filter(|&x| x % 5 != 0).map(|&x| x as usize).collect();
+            assert_eq!(exp, loaded_vec.get_ids(i));
+        }
+    }
 }